@@ -0,0 +1,608 @@
+use chrono::{Duration as ChronoDuration, Utc};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::io::Read;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::api_status;
+use crate::calendar::PracticeEvent;
+use crate::library::SheetMusicItem;
+use crate::session;
+use crate::sightreading_progress::Attempt;
+
+// Same timeouts the frontend uses in src/config/api.ts
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+pub const HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+// Whole PDFs can take a while on slow connections
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+
+const MAX_RETRIES: u32 = 3;
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+
+// Matches API_URL in src/config/api.ts until the frontend configures something else
+fn default_api_url() -> String {
+    if cfg!(debug_assertions) {
+        "http://localhost:3001".to_string()
+    } else {
+        "https://partitura-api.onrender.com".to_string()
+    }
+}
+
+static API_URL: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(default_api_url()));
+
+// Every failure the client can report, serialized as { kind, ... } for the frontend
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ApiError {
+    Offline { message: String },
+    Timeout,
+    Unauthorized,
+    // Signed in fine but not allowed; a fresh token doesn't change that
+    Forbidden,
+    NotFound,
+    Rejected { status: u16, message: String },
+    Server { status: u16, message: String },
+    InvalidResponse { message: String },
+    Local { message: String },
+}
+
+impl ApiError {
+    // Same meaning as ApiError.isConnectionError on the frontend
+    pub fn is_connection_error(&self) -> bool {
+        matches!(self, ApiError::Offline { .. } | ApiError::Timeout)
+    }
+
+    // Connection problems are only retried for requests that are safe to send twice
+    fn is_retryable(&self, method: &str) -> bool {
+        let idempotent = matches!(method, "GET" | "HEAD" | "PUT" | "DELETE");
+        match self {
+            ApiError::Offline { .. } | ApiError::Timeout => idempotent,
+            ApiError::Server { status, .. } => *status == 429 || *status == 503 || idempotent,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiError::Offline { message } => write!(f, "Unable to connect to the Partitura API: {}", message),
+            ApiError::Timeout => write!(f, "The Partitura API did not respond in time"),
+            ApiError::Unauthorized => write!(f, "Your session has expired, please sign in again"),
+            ApiError::Forbidden => write!(f, "You don't have permission to do that"),
+            ApiError::NotFound => write!(f, "The requested item was not found"),
+            ApiError::Rejected { status, message } => write!(f, "API Error {}: {}", status, message),
+            ApiError::Server { status, message } => write!(f, "API Error {}: {}", status, message),
+            ApiError::InvalidResponse { message } => write!(f, "Unexpected API response: {}", message),
+            ApiError::Local { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SheetMusicUpdate {
+    pub title: Option<String>,
+    pub composer: Option<String>,
+    pub is_favorite: Option<bool>,
+}
+
+// Field names follow the API's snake_case preferences table
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct UserPreferences {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notifications_enabled: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadResult {
+    pub pdf_path: String,
+}
+
+// Server-side state of a chunked upload; receivedBytes is where the next chunk has to start
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadSession {
+    pub upload_id: String,
+    pub received_bytes: u64,
+}
+
+// Result of a conditional download; validators are kept for the next revalidation
+pub enum FileFetch {
+    NotModified,
+    Modified {
+        data: Vec<u8>,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+}
+
+enum RequestBody<'a> {
+    Empty,
+    Json(&'a Value),
+    Bytes { content_type: &'a str, data: &'a [u8] },
+}
+
+#[derive(Clone)]
+pub struct ApiClient {
+    base_url: String,
+    agent: ureq::Agent,
+    max_retries: u32,
+}
+
+pub fn client() -> ApiClient {
+    ApiClient::new(&API_URL.lock().unwrap())
+}
+
+fn event_body(event: &PracticeEvent) -> Value {
+    json!({
+        "title": event.title,
+        "description": event.description,
+        "startTime": event.start_time.to_rfc3339(),
+        "endTime": event.end_time.to_rfc3339(),
+        "isCompleted": event.is_completed,
+        "sheetMusicId": event.sheet_music_id,
+        "color": event.color.clone().unwrap_or_else(|| "#3B82F6".to_string()),
+        "type": event.event_type,
+    })
+}
+
+fn multipart_body(boundary: &str, fields: &[(&str, String)], file_field: &str, file_name: &str, file: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n", boundary, name, value).as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: application/pdf\r\n\r\n",
+            boundary, file_field, file_name
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(file);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+// Pulls the message out of { error } or { message } bodies like handleApiError does
+fn error_message(status: u16, response: ureq::Response) -> String {
+    let status_text = response.status_text().to_string();
+    let body = response.into_string().unwrap_or_default();
+    serde_json::from_str::<Value>(&body)
+        .ok()
+        .and_then(|value| {
+            value
+                .get("error")
+                .or_else(|| value.get("message"))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| format!("API Error: {} {}", status, status_text))
+}
+
+fn classify(error: ureq::Error) -> (ApiError, Option<Duration>) {
+    match error {
+        ureq::Error::Status(status, response) => {
+            let retry_after = response
+                .header("Retry-After")
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .map(Duration::from_secs);
+            let error = match status {
+                401 => ApiError::Unauthorized,
+                403 => ApiError::Forbidden,
+                404 => ApiError::NotFound,
+                408 => ApiError::Timeout,
+                429 | 500..=599 => ApiError::Server {
+                    status,
+                    message: error_message(status, response),
+                },
+                _ => ApiError::Rejected {
+                    status,
+                    message: error_message(status, response),
+                },
+            };
+            (error, retry_after)
+        }
+        ureq::Error::Transport(transport) => {
+            let message = transport.to_string();
+            if message.contains("timed out") {
+                (ApiError::Timeout, None)
+            } else {
+                (ApiError::Offline { message }, None)
+            }
+        }
+    }
+}
+
+// Exponential backoff with equal jitter: half the delay is fixed, the other half random
+fn backoff(attempt: u32) -> Duration {
+    let cap = BASE_BACKOFF.saturating_mul(2u32.saturating_pow(attempt)).min(MAX_BACKOFF);
+    let half = cap / 2;
+    half + Duration::from_millis(rand::thread_rng().gen_range(0..=half.as_millis() as u64))
+}
+
+impl ApiClient {
+    pub fn new(base_url: &str) -> Self {
+        ApiClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new().build(),
+            max_retries: MAX_RETRIES,
+        }
+    }
+
+    // Same rules as buildApiUrl: endpoints get an /api prefix unless they already have one
+    fn url(&self, endpoint: &str) -> String {
+        let endpoint = if endpoint.starts_with('/') {
+            endpoint.to_string()
+        } else {
+            format!("/{}", endpoint)
+        };
+        if endpoint.starts_with("/api/") {
+            format!("{}{}", self.base_url, endpoint)
+        } else {
+            format!("{}/api{}", self.base_url, endpoint)
+        }
+    }
+
+    fn execute(&self, method: &str, endpoint: &str, body: RequestBody, timeout: Duration) -> Result<ureq::Response, ApiError> {
+        let url = self.url(endpoint);
+        let mut attempt = 0;
+        let mut refreshed = false;
+        loop {
+            let mut request = self.agent.request(method, &url).timeout(timeout);
+            if let Some(token) = session::access_token() {
+                request = request.set("Authorization", &format!("Bearer {}", token));
+            }
+            let result = match &body {
+                RequestBody::Empty => request.call(),
+                RequestBody::Json(value) => request.send_json((*value).clone()),
+                RequestBody::Bytes { content_type, data } => request.set("Content-Type", content_type).send_bytes(data),
+            };
+
+            let (error, retry_after) = match result {
+                Ok(response) => return Ok(response),
+                Err(e) => classify(e),
+            };
+
+            // A 401 gets one retry with a refreshed token, like apiRequest on the frontend
+            if error == ApiError::Unauthorized && !refreshed {
+                refreshed = true;
+                if self.refresh_session() {
+                    continue;
+                }
+                return Err(error);
+            }
+
+            if attempt < self.max_retries && error.is_retryable(method) {
+                let delay = retry_after.unwrap_or_else(|| backoff(attempt)).min(MAX_BACKOFF);
+                println!("{} {} failed ({}), retrying in {:?}", method, endpoint, error, delay);
+                thread::sleep(delay);
+                attempt += 1;
+                continue;
+            }
+            return Err(error);
+        }
+    }
+
+    // Uses the same /auth/refresh endpoint as getAuthToken in userService.ts
+    fn refresh_session(&self) -> bool {
+        let refresh_token = match session::current().and_then(|session| session.refresh_token) {
+            Some(token) if !token.is_empty() => token,
+            _ => return false,
+        };
+        let response = self
+            .agent
+            .post(&self.url("/auth/refresh"))
+            .timeout(REQUEST_TIMEOUT)
+            .send_json(json!({ "refresh_token": refresh_token }));
+        let access_token = response
+            .ok()
+            .and_then(|response| response.into_json::<Value>().ok())
+            .and_then(|body| body.pointer("/session/access_token").and_then(Value::as_str).map(str::to_string));
+        match access_token {
+            Some(token) => {
+                println!("Refreshed session token");
+                session::update_access_token(token, Some(Utc::now() + ChronoDuration::hours(1)));
+                true
+            }
+            None => {
+                println!("Failed to refresh session token");
+                false
+            }
+        }
+    }
+
+    // Empty bodies (e.g. 204 No Content) decode as null
+    fn decode<T: DeserializeOwned>(response: ureq::Response) -> Result<T, ApiError> {
+        let body = response.into_string().map_err(|e| ApiError::InvalidResponse { message: e.to_string() })?;
+        let body = if body.trim().is_empty() { "null" } else { body.as_str() };
+        serde_json::from_str(body).map_err(|e| ApiError::InvalidResponse { message: e.to_string() })
+    }
+
+    pub fn get_json<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T, ApiError> {
+        let response = self.execute("GET", endpoint, RequestBody::Empty, REQUEST_TIMEOUT)?;
+        Self::decode(response)
+    }
+
+    pub fn send_json<T: DeserializeOwned>(&self, method: &str, endpoint: &str, body: &Value) -> Result<T, ApiError> {
+        let response = self.execute(method, endpoint, RequestBody::Json(body), REQUEST_TIMEOUT)?;
+        Self::decode(response)
+    }
+
+    pub fn send_bytes<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        endpoint: &str,
+        content_type: &str,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<T, ApiError> {
+        let response = self.execute(method, endpoint, RequestBody::Bytes { content_type, data }, timeout)?;
+        Self::decode(response)
+    }
+
+    pub fn delete(&self, endpoint: &str) -> Result<(), ApiError> {
+        self.execute("DELETE", endpoint, RequestBody::Empty, REQUEST_TIMEOUT).map(|_| ())
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    // Health lives outside /api, so it skips url() and the retry loop
+    pub fn health(&self) -> Result<Duration, ApiError> {
+        self.health_with_timeout(HEALTH_TIMEOUT)
+    }
+
+    // Not retried, so callers see the raw latency of a single round trip
+    pub fn health_with_timeout(&self, timeout: Duration) -> Result<Duration, ApiError> {
+        let started = Instant::now();
+        self.agent
+            .get(&format!("{}/health", self.base_url))
+            .timeout(timeout)
+            .call()
+            .map_err(|e| classify(e).0)?;
+        Ok(started.elapsed())
+    }
+
+    // Fetches a file by absolute URL, e.g. a pdfPath; the session token is only sent to our own API
+    pub fn fetch_file(&self, url: &str, etag: Option<&str>, last_modified: Option<&str>) -> Result<FileFetch, ApiError> {
+        let mut request = self.agent.get(url).timeout(DOWNLOAD_TIMEOUT);
+        if url.starts_with(&self.base_url) {
+            if let Some(token) = session::access_token() {
+                request = request.set("Authorization", &format!("Bearer {}", token));
+            }
+        }
+        if let Some(etag) = etag {
+            request = request.set("If-None-Match", etag);
+        }
+        if let Some(last_modified) = last_modified {
+            request = request.set("If-Modified-Since", last_modified);
+        }
+
+        let response = request.call().map_err(|e| classify(e).0)?;
+        if response.status() == 304 {
+            return Ok(FileFetch::NotModified);
+        }
+        let etag = response.header("ETag").map(str::to_string);
+        let last_modified = response.header("Last-Modified").map(str::to_string);
+        let mut data = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut data)
+            .map_err(|e| ApiError::Offline { message: e.to_string() })?;
+        Ok(FileFetch::Modified { data, etag, last_modified })
+    }
+
+    pub fn list_sheet_music(&self, user_id: &str) -> Result<Vec<SheetMusicItem>, ApiError> {
+        let items: Option<Vec<SheetMusicItem>> = self.get_json(&format!("/sheet-music/{}", user_id))?;
+        Ok(items.unwrap_or_default())
+    }
+
+    // Same form fields saveSheetMusic sends from the frontend
+    pub fn upload_sheet_music(&self, user_id: &str, item: &SheetMusicItem, file_name: &str, pdf: &[u8]) -> Result<UploadResult, ApiError> {
+        let boundary = format!("partitura-{}", uuid::Uuid::new_v4());
+        let fields = [
+            ("id", item.id.clone()),
+            ("title", item.title.clone()),
+            ("composer", item.composer.clone()),
+            ("isFavorite", item.is_favorite.to_string()),
+            ("dateAdded", item.date_added.to_rfc3339()),
+        ];
+        let body = multipart_body(&boundary, &fields, "pdfFile", file_name, pdf);
+        let content_type = format!("multipart/form-data; boundary={}", boundary);
+        self.send_bytes("POST", &format!("/sheet-music/{}", user_id), &content_type, &body, UPLOAD_TIMEOUT)
+    }
+
+    // Chunked uploads carry the same fields as upload_sheet_music, plus what the server needs to verify the file
+    pub fn create_upload(
+        &self,
+        user_id: &str,
+        item: &SheetMusicItem,
+        file_name: &str,
+        total_bytes: u64,
+        sha256: &str,
+    ) -> Result<UploadSession, ApiError> {
+        let body = json!({
+            "id": item.id,
+            "title": item.title,
+            "composer": item.composer,
+            "isFavorite": item.is_favorite,
+            "dateAdded": item.date_added.to_rfc3339(),
+            "fileName": file_name,
+            "totalBytes": total_bytes,
+            "sha256": sha256,
+        });
+        self.send_json("POST", &format!("/sheet-music/{}/uploads", user_id), &body)
+    }
+
+    pub fn get_upload(&self, user_id: &str, upload_id: &str) -> Result<UploadSession, ApiError> {
+        self.get_json(&format!("/sheet-music/{}/uploads/{}", user_id, upload_id))
+    }
+
+    // Chunks are addressed by offset, so sending one twice is harmless and PUT can be retried
+    pub fn upload_chunk(&self, user_id: &str, upload_id: &str, offset: u64, data: &[u8]) -> Result<UploadSession, ApiError> {
+        let endpoint = format!("/sheet-music/{}/uploads/{}?offset={}", user_id, upload_id, offset);
+        self.send_bytes("PUT", &endpoint, "application/octet-stream", data, UPLOAD_TIMEOUT)
+    }
+
+    pub fn complete_upload(&self, user_id: &str, upload_id: &str) -> Result<UploadResult, ApiError> {
+        self.send_json("POST", &format!("/sheet-music/{}/uploads/{}/complete", user_id, upload_id), &json!({}))
+    }
+
+    pub fn abort_upload(&self, user_id: &str, upload_id: &str) -> Result<(), ApiError> {
+        self.delete(&format!("/sheet-music/{}/uploads/{}", user_id, upload_id))
+    }
+
+    pub fn update_sheet_music(&self, user_id: &str, item_id: &str, update: &SheetMusicUpdate) -> Result<(), ApiError> {
+        let body = serde_json::to_value(update).map_err(|e| ApiError::Local { message: e.to_string() })?;
+        self.send_json::<Value>("PUT", &format!("/sheet-music/{}/{}", user_id, item_id), &body)
+            .map(|_| ())
+    }
+
+    pub fn delete_sheet_music(&self, user_id: &str, item_id: &str) -> Result<(), ApiError> {
+        self.delete(&format!("/sheet-music/{}/{}", user_id, item_id))
+    }
+
+    pub fn list_events(&self, user_id: &str) -> Result<Vec<PracticeEvent>, ApiError> {
+        let events: Option<Vec<PracticeEvent>> = self.get_json(&format!("/calendar/{}", user_id))?;
+        Ok(events.unwrap_or_default())
+    }
+
+    pub fn create_event(&self, user_id: &str, event: &PracticeEvent) -> Result<PracticeEvent, ApiError> {
+        self.send_json("POST", &format!("/calendar/{}", user_id), &event_body(event))
+    }
+
+    pub fn update_event(&self, user_id: &str, event: &PracticeEvent) -> Result<(), ApiError> {
+        self.send_json::<Value>("PUT", &format!("/calendar/{}/{}", user_id, event.id), &event_body(event))
+            .map(|_| ())
+    }
+
+    pub fn delete_event(&self, user_id: &str, event_id: &str) -> Result<(), ApiError> {
+        self.delete(&format!("/calendar/{}/{}", user_id, event_id))
+    }
+
+    pub fn toggle_event_completion(&self, user_id: &str, event_id: &str, is_completed: bool) -> Result<(), ApiError> {
+        let endpoint = format!("/calendar/{}/{}/toggle-completion", user_id, event_id);
+        self.send_json::<Value>("PATCH", &endpoint, &json!({ "isCompleted": is_completed }))
+            .map(|_| ())
+    }
+
+    pub fn get_preferences(&self, user_id: &str) -> Result<UserPreferences, ApiError> {
+        self.get_json(&format!("/users/{}/preferences", user_id))
+    }
+
+    pub fn update_preferences(&self, user_id: &str, preferences: &UserPreferences) -> Result<UserPreferences, ApiError> {
+        let body = serde_json::to_value(preferences).map_err(|e| ApiError::Local { message: e.to_string() })?;
+        self.send_json("PUT", &format!("/users/{}/preferences", user_id), &body)
+    }
+
+    // The server doesn't serve sight-reading progress yet, so these answer 404 until it adds this endpoint
+    pub fn list_sight_reading_attempts(&self, user_id: &str) -> Result<Vec<Attempt>, ApiError> {
+        let attempts: Option<Vec<Attempt>> = self.get_json(&format!("/sight-reading/{}/attempts", user_id))?;
+        Ok(attempts.unwrap_or_default())
+    }
+
+    pub fn add_sight_reading_attempts(&self, user_id: &str, attempts: &[Attempt]) -> Result<(), ApiError> {
+        let body = serde_json::to_value(attempts).map_err(|e| ApiError::Local { message: e.to_string() })?;
+        self.send_json::<Value>("POST", &format!("/sight-reading/{}/attempts", user_id), &body)
+            .map(|_| ())
+    }
+}
+
+// The client blocks, so commands run it off the async runtime
+async fn run_blocking<T, F>(f: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(ApiClient) -> Result<T, ApiError> + Send + 'static,
+{
+    let api = client();
+    tauri::async_runtime::spawn_blocking(move || f(api))
+        .await
+        .map_err(|e| ApiError::Local { message: e.to_string() })?
+}
+
+#[tauri::command]
+pub fn configure_api(api_url: String) {
+    println!("Using API at {}", api_url);
+    *API_URL.lock().unwrap() = api_url.trim_end_matches('/').to_string();
+    api_status::check_now();
+}
+
+#[tauri::command]
+pub async fn api_list_sheet_music(user_id: String) -> Result<Vec<SheetMusicItem>, ApiError> {
+    run_blocking(move |api| api.list_sheet_music(&user_id)).await
+}
+
+#[tauri::command]
+pub async fn api_upload_sheet_music(user_id: String, item: SheetMusicItem, pdf_path: String) -> Result<UploadResult, ApiError> {
+    run_blocking(move |api| {
+        let pdf = std::fs::read(&pdf_path).map_err(|e| ApiError::Local {
+            message: format!("Failed to read {}: {}", pdf_path, e),
+        })?;
+        let file_name = std::path::Path::new(&pdf_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| format!("{}.pdf", item.id));
+        api.upload_sheet_music(&user_id, &item, &file_name, &pdf)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn api_update_sheet_music(user_id: String, item_id: String, update: SheetMusicUpdate) -> Result<(), ApiError> {
+    run_blocking(move |api| api.update_sheet_music(&user_id, &item_id, &update)).await
+}
+
+#[tauri::command]
+pub async fn api_delete_sheet_music(user_id: String, item_id: String) -> Result<(), ApiError> {
+    run_blocking(move |api| api.delete_sheet_music(&user_id, &item_id)).await
+}
+
+#[tauri::command]
+pub async fn api_list_events(user_id: String) -> Result<Vec<PracticeEvent>, ApiError> {
+    run_blocking(move |api| api.list_events(&user_id)).await
+}
+
+#[tauri::command]
+pub async fn api_create_event(user_id: String, event: PracticeEvent) -> Result<PracticeEvent, ApiError> {
+    run_blocking(move |api| api.create_event(&user_id, &event)).await
+}
+
+#[tauri::command]
+pub async fn api_update_event(user_id: String, event: PracticeEvent) -> Result<(), ApiError> {
+    run_blocking(move |api| api.update_event(&user_id, &event)).await
+}
+
+#[tauri::command]
+pub async fn api_delete_event(user_id: String, event_id: String) -> Result<(), ApiError> {
+    run_blocking(move |api| api.delete_event(&user_id, &event_id)).await
+}
+
+#[tauri::command]
+pub async fn api_toggle_event_completion(user_id: String, event_id: String, is_completed: bool) -> Result<(), ApiError> {
+    run_blocking(move |api| api.toggle_event_completion(&user_id, &event_id, is_completed)).await
+}
+
+#[tauri::command]
+pub async fn api_get_preferences(user_id: String) -> Result<UserPreferences, ApiError> {
+    run_blocking(move |api| api.get_preferences(&user_id)).await
+}
+
+#[tauri::command]
+pub async fn api_update_preferences(user_id: String, preferences: UserPreferences) -> Result<UserPreferences, ApiError> {
+    run_blocking(move |api| api.update_preferences(&user_id, &preferences)).await
+}
@@ -0,0 +1,220 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::api_client::{self, ApiError};
+
+// How often to check, depending on how the last check went
+const ONLINE_INTERVAL: Duration = Duration::from_secs(60);
+const DEGRADED_INTERVAL: Duration = Duration::from_secs(15);
+const WAKING_INTERVAL: Duration = Duration::from_secs(5);
+const OFFLINE_BASE_INTERVAL: Duration = Duration::from_secs(10);
+const OFFLINE_MAX_INTERVAL: Duration = Duration::from_secs(120);
+
+// Render holds requests while a sleeping service boots, so the probe waits long enough to see it finish
+const WAKE_TIMEOUT: Duration = Duration::from_secs(60);
+// A free Render instance takes about a minute to boot; anything longer is a real outage
+const COLD_START_WINDOW: Duration = Duration::from_secs(180);
+
+const LATENCY_WINDOW: usize = 10;
+const DEGRADED_LATENCY_MS: u64 = 1500;
+const OFFLINE_AFTER_FAILURES: u32 = 3;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ApiState {
+    Online,
+    Degraded,
+    Offline,
+    Waking,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiStatus {
+    pub status: ApiState,
+    pub latency_ms: Option<u64>,
+    pub average_latency_ms: Option<u64>,
+    pub consecutive_failures: u32,
+    pub last_checked: Option<DateTime<Utc>>,
+    pub last_online: Option<DateTime<Utc>>,
+    pub next_check: Option<DateTime<Utc>>,
+    pub message: Option<String>,
+}
+
+struct Monitor {
+    status: ApiState,
+    latency_ms: Option<u64>,
+    latencies: VecDeque<u64>,
+    consecutive_failures: u32,
+    waking_since: Option<DateTime<Utc>>,
+    last_checked: Option<DateTime<Utc>>,
+    last_online: Option<DateTime<Utc>>,
+    next_check: Option<DateTime<Utc>>,
+    message: Option<String>,
+}
+
+// Assume the API is there until the first check says otherwise, like ApiStatusContext does
+static MONITOR: Lazy<Mutex<Monitor>> = Lazy::new(|| {
+    Mutex::new(Monitor {
+        status: ApiState::Online,
+        latency_ms: None,
+        latencies: VecDeque::new(),
+        consecutive_failures: 0,
+        waking_since: None,
+        last_checked: None,
+        last_online: None,
+        next_check: None,
+        message: None,
+    })
+});
+static CONTROL: Lazy<Mutex<Option<Sender<()>>>> = Lazy::new(|| Mutex::new(None));
+// Scheduled and manual checks would otherwise count each other's failures twice
+static CHECK_RUNNING: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+impl Monitor {
+    fn snapshot(&self) -> ApiStatus {
+        let average_latency_ms = if self.latencies.is_empty() {
+            None
+        } else {
+            Some(self.latencies.iter().sum::<u64>() / self.latencies.len() as u64)
+        };
+        ApiStatus {
+            status: self.status,
+            latency_ms: self.latency_ms,
+            average_latency_ms,
+            consecutive_failures: self.consecutive_failures,
+            last_checked: self.last_checked,
+            last_online: self.last_online,
+            next_check: self.next_check,
+            message: self.message.clone(),
+        }
+    }
+
+    fn record_success(&mut self, latency: Duration, now: DateTime<Utc>) {
+        let latency_ms = latency.as_millis() as u64;
+        if self.status == ApiState::Waking {
+            // The boot time says nothing about how the API performs once it's up
+            self.latencies.clear();
+        } else {
+            self.latencies.push_back(latency_ms);
+            if self.latencies.len() > LATENCY_WINDOW {
+                self.latencies.pop_front();
+            }
+        }
+        let slow = self.snapshot().average_latency_ms.unwrap_or(latency_ms) > DEGRADED_LATENCY_MS;
+
+        self.latency_ms = Some(latency_ms);
+        self.status = if slow { ApiState::Degraded } else { ApiState::Online };
+        self.message = if slow { Some(format!("API is responding slowly ({} ms)", latency_ms)) } else { None };
+        self.consecutive_failures = 0;
+        self.waking_since = None;
+        self.last_online = Some(now);
+    }
+
+    fn record_failure(&mut self, error: &ApiError, cold_start: bool, now: DateTime<Utc>) {
+        self.consecutive_failures += 1;
+        self.latency_ms = None;
+        self.message = Some(error.to_string());
+
+        let waking_for = self.waking_since.map(|since| (now - since).to_std().unwrap_or_default());
+        if cold_start && waking_for.map(|elapsed| elapsed < COLD_START_WINDOW).unwrap_or(true) {
+            self.waking_since.get_or_insert(now);
+            self.status = ApiState::Waking;
+            self.message = Some("API server is starting up".to_string());
+        } else if self.consecutive_failures < OFFLINE_AFTER_FAILURES && self.status != ApiState::Offline {
+            self.status = ApiState::Degraded;
+        } else {
+            self.status = ApiState::Offline;
+            self.waking_since = None;
+        }
+    }
+
+    fn next_interval(&self) -> Duration {
+        match self.status {
+            ApiState::Online => ONLINE_INTERVAL,
+            ApiState::Degraded => DEGRADED_INTERVAL,
+            ApiState::Waking => WAKING_INTERVAL,
+            ApiState::Offline => {
+                let exponent = self.consecutive_failures.saturating_sub(OFFLINE_AFTER_FAILURES).min(5);
+                (OFFLINE_BASE_INTERVAL * 2u32.pow(exponent)).min(OFFLINE_MAX_INTERVAL)
+            }
+        }
+    }
+}
+
+// Render-hosted services sleep when idle and time out or answer 502/503 until they've booted
+fn is_cold_start(base_url: &str, error: &ApiError) -> bool {
+    base_url.contains(".onrender.com")
+        && matches!(error, ApiError::Timeout | ApiError::Server { status: 502 | 503, .. })
+}
+
+fn check(app: &AppHandle) -> ApiStatus {
+    let _running = CHECK_RUNNING.lock().unwrap();
+    let api = api_client::client();
+    let waking = MONITOR.lock().unwrap().status == ApiState::Waking;
+    let timeout = if waking { WAKE_TIMEOUT } else { api_client::HEALTH_TIMEOUT };
+    let result = api.health_with_timeout(timeout);
+
+    let status = {
+        let mut monitor = MONITOR.lock().unwrap();
+        let previous = monitor.status;
+        let now = Utc::now();
+        match &result {
+            Ok(latency) => monitor.record_success(*latency, now),
+            Err(e) => monitor.record_failure(e, is_cold_start(api.base_url(), e), now),
+        }
+        monitor.last_checked = Some(now);
+        monitor.next_check = chrono::Duration::from_std(monitor.next_interval()).ok().map(|interval| now + interval);
+        if monitor.status != previous {
+            println!("API status changed from {:?} to {:?}", previous, monitor.status);
+        }
+        monitor.snapshot()
+    };
+
+    if let Err(e) = app.emit("api-status", status.clone()) {
+        println!("Failed to emit API status: {}", e);
+    }
+    status
+}
+
+// Checks right away and then on a schedule that tightens while the API is unhealthy
+pub fn start(app: AppHandle) {
+    let (tx, rx) = mpsc::channel();
+    *CONTROL.lock().unwrap() = Some(tx);
+
+    thread::spawn(move || loop {
+        check(&app);
+        let interval = MONITOR.lock().unwrap().next_interval();
+        match rx.recv_timeout(interval) {
+            Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    });
+}
+
+// Asks the monitor to check on its own thread, e.g. after the API URL changed
+pub fn check_now() {
+    if let Some(control) = CONTROL.lock().unwrap().as_ref() {
+        let _ = control.send(());
+    }
+}
+
+#[tauri::command]
+pub fn get_api_status() -> ApiStatus {
+    MONITOR.lock().unwrap().snapshot()
+}
+
+// Backs the retry button in ApiUnavailable
+#[tauri::command]
+pub async fn check_api_status(app: AppHandle) -> Result<ApiStatus, String> {
+    tauri::async_runtime::spawn_blocking(move || check(&app))
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,489 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::calendar::{self, Calendar, PracticeEvent};
+use crate::library::{self, Collection, ItemTag, Library, Setlist, SheetMusicItem, Tag};
+use crate::store;
+
+// Bump when the manifest layout changes in a way older builds can't read
+const ARCHIVE_VERSION: u32 = 1;
+const MANIFEST_NAME: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub items: Vec<SheetMusicItem>,
+    pub tags: Vec<Tag>,
+    pub item_tags: Vec<ItemTag>,
+    pub collections: Vec<Collection>,
+    pub setlists: Vec<Setlist>,
+    #[serde(default)]
+    pub calendar_events: Vec<PracticeEvent>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictStrategy {
+    #[default]
+    KeepLocal,
+    ReplaceLocal,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportConflict {
+    pub kind: String,
+    pub id: String,
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub conflicts: Vec<ImportConflict>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub path: String,
+    pub items: usize,
+    pub pdfs: usize,
+    pub events: usize,
+}
+
+fn pdf_entry_name(id: &str) -> String {
+    format!("pdfs/{}.pdf", id)
+}
+
+pub fn write_archive(path: &Path, library: &Library, calendar: &Calendar) -> Result<ExportSummary, String> {
+    let manifest = Manifest {
+        version: ARCHIVE_VERSION,
+        exported_at: Utc::now(),
+        items: library.items.clone(),
+        tags: library.tags.clone(),
+        item_tags: library.item_tags.clone(),
+        collections: library.collections.clone(),
+        setlists: library.setlists.clone(),
+        calendar_events: calendar.events.clone(),
+    };
+
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    // PDFs are already compressed, so storing them as-is keeps exports fast
+    let stored = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+
+    let manifest_json = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    zip.start_file(MANIFEST_NAME, deflated).map_err(|e| e.to_string())?;
+    zip.write_all(&manifest_json).map_err(|e| e.to_string())?;
+
+    let mut pdfs = 0;
+    for item in &library.items {
+        let pdf_path = Path::new(&item.pdf_path);
+        if !pdf_path.is_file() {
+            continue;
+        }
+        let mut pdf = File::open(pdf_path).map_err(|e| format!("Failed to open {}: {}", item.pdf_path, e))?;
+        zip.start_file(pdf_entry_name(&item.id), stored).map_err(|e| e.to_string())?;
+        io::copy(&mut pdf, &mut zip).map_err(|e| format!("Failed to add {}: {}", item.pdf_path, e))?;
+        pdfs += 1;
+    }
+
+    zip.finish().map_err(|e| format!("Failed to finish archive: {}", e))?;
+    Ok(ExportSummary {
+        path: path.to_string_lossy().to_string(),
+        items: library.items.len(),
+        pdfs,
+        events: calendar.events.len(),
+    })
+}
+
+// IDs name the files an import writes, and archives get passed between people, so anything but a plain
+// hyphenated UUID (which rules out separators, "..", drive prefixes and absolute paths) is refused
+fn check_id(kind: &str, id: &str) -> Result<(), String> {
+    let unsafe_path = id.contains(['/', '\\', ':']) || id.contains("..");
+    if id.len() != 36 || unsafe_path || uuid::Uuid::parse_str(id).is_err() {
+        return Err(format!("Archive has an invalid {} id \"{}\"", kind, id));
+    }
+    Ok(())
+}
+
+fn check_ids(manifest: &Manifest) -> Result<(), String> {
+    for item in &manifest.items {
+        check_id("sheet music", &item.id)?;
+    }
+    for tag in &manifest.tags {
+        check_id("tag", &tag.id)?;
+    }
+    for link in &manifest.item_tags {
+        check_id("sheet music", &link.sheet_music_id)?;
+        check_id("tag", &link.tag_id)?;
+    }
+    for collection in &manifest.collections {
+        check_id("collection", &collection.id)?;
+    }
+    for setlist in &manifest.setlists {
+        check_id("setlist", &setlist.id)?;
+        for entry in &setlist.entries {
+            check_id("setlist entry", &entry.id)?;
+        }
+    }
+    // Events without an id are given one on import
+    for event in manifest.calendar_events.iter().filter(|event| !event.id.is_empty()) {
+        check_id("event", &event.id)?;
+    }
+    Ok(())
+}
+
+pub fn read_manifest(archive: &mut ZipArchive<File>) -> Result<Manifest, String> {
+    let manifest_file = archive
+        .by_name(MANIFEST_NAME)
+        .map_err(|_| "Archive has no manifest.json".to_string())?;
+    let manifest: Manifest =
+        serde_json::from_reader(manifest_file).map_err(|e| format!("Invalid manifest: {}", e))?;
+    if manifest.version > ARCHIVE_VERSION {
+        return Err(format!(
+            "Archive version {} was created by a newer version of Partitura (supported: {})",
+            manifest.version, ARCHIVE_VERSION
+        ));
+    }
+    check_ids(&manifest)?;
+    Ok(manifest)
+}
+
+// Copies a bundled PDF into the data dir and returns its new location, if the archive has one
+fn extract_pdf(archive: &mut ZipArchive<File>, id: &str) -> Result<Option<String>, String> {
+    check_id("sheet music", id)?;
+    let mut entry = match archive.by_name(&pdf_entry_name(id)) {
+        Ok(entry) => entry,
+        Err(_) => return Ok(None),
+    };
+    let target = store::sub_dir("pdfs")?.join(format!("{}.pdf", id));
+    let mut file = File::create(&target).map_err(|e| format!("Failed to create {}: {}", target.display(), e))?;
+    io::copy(&mut entry, &mut file).map_err(|e| format!("Failed to extract {}: {}", target.display(), e))?;
+    Ok(Some(target.to_string_lossy().to_string()))
+}
+
+// The PDF location is machine specific, so it doesn't count as a difference
+fn same_item(local: &SheetMusicItem, incoming: &SheetMusicItem) -> bool {
+    local.title == incoming.title
+        && local.composer == incoming.composer
+        && local.is_favorite == incoming.is_favorite
+        && local.date_added == incoming.date_added
+}
+
+fn conflict(kind: &str, id: &str, name: &str, reason: &str) -> ImportConflict {
+    ImportConflict {
+        kind: kind.to_string(),
+        id: id.to_string(),
+        name: name.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+pub fn merge_archive(
+    library: &mut Library,
+    manifest: &mut Manifest,
+    archive: &mut ZipArchive<File>,
+    strategy: ConflictStrategy,
+) -> Result<ImportReport, String> {
+    let mut report = ImportReport::default();
+    let replace = strategy == ConflictStrategy::ReplaceLocal;
+
+    for mut item in manifest.items.drain(..) {
+        let local = library.item(&item.id).cloned();
+        match local {
+            Some(local) if same_item(&local, &item) => report.unchanged += 1,
+            Some(local) => {
+                report.conflicts.push(conflict("sheetMusic", &item.id, &local.title, "Local piece has different details"));
+                if replace {
+                    item.pdf_path = extract_pdf(archive, &item.id)?.unwrap_or(local.pdf_path);
+                    library.upsert_item(item);
+                    report.updated += 1;
+                }
+            }
+            None => {
+                if let Some(pdf_path) = extract_pdf(archive, &item.id)? {
+                    item.pdf_path = pdf_path;
+                }
+                library.items.push(item);
+                report.added += 1;
+            }
+        }
+    }
+
+    // Incoming tags that share a name with a local one are folded into it, links included
+    let mut tag_ids: HashMap<String, String> = HashMap::new();
+    for tag in manifest.tags.drain(..) {
+        let by_name = library
+            .tags
+            .iter()
+            .find(|local| local.name.eq_ignore_ascii_case(&tag.name) && local.id != tag.id);
+        if let Some(local) = by_name {
+            tag_ids.insert(tag.id, local.id.clone());
+            report.unchanged += 1;
+            continue;
+        }
+        match library.tags.iter_mut().find(|local| local.id == tag.id) {
+            Some(local) if *local == tag => report.unchanged += 1,
+            Some(local) => {
+                report.conflicts.push(conflict("tag", &tag.id, &local.name, "Local tag has different details"));
+                if replace {
+                    *local = tag;
+                    report.updated += 1;
+                }
+            }
+            None => {
+                library.tags.push(tag);
+                report.added += 1;
+            }
+        }
+    }
+
+    // Links only survive when both ends made it into the library
+    for mut link in manifest.item_tags.drain(..) {
+        if let Some(local_id) = tag_ids.get(&link.tag_id) {
+            link.tag_id = local_id.clone();
+        }
+        let valid = library.item(&link.sheet_music_id).is_some() && library.tags.iter().any(|tag| tag.id == link.tag_id);
+        if valid && !library.item_tags.contains(&link) {
+            library.item_tags.push(link);
+        }
+    }
+
+    for mut collection in manifest.collections.drain(..) {
+        collection.sheet_music_ids.retain(|id| library.item(id).is_some());
+        match library.collections.iter_mut().find(|local| local.id == collection.id) {
+            Some(local) if *local == collection => report.unchanged += 1,
+            Some(local) => {
+                report.conflicts.push(conflict("collection", &collection.id, &local.name, "Local collection has different details"));
+                if replace {
+                    *local = collection;
+                    report.updated += 1;
+                } else {
+                    // Keep the local collection but still pick up pieces that came with the archive
+                    for id in collection.sheet_music_ids {
+                        if !local.sheet_music_ids.contains(&id) {
+                            local.sheet_music_ids.push(id);
+                        }
+                    }
+                }
+            }
+            None => {
+                library.collections.push(collection);
+                report.added += 1;
+            }
+        }
+    }
+    let collection_ids: Vec<String> = library.collections.iter().map(|collection| collection.id.clone()).collect();
+    for collection in &mut library.collections {
+        if collection.parent_id.as_ref().is_some_and(|parent| !collection_ids.contains(parent)) {
+            collection.parent_id = None;
+        }
+    }
+
+    for mut setlist in manifest.setlists.drain(..) {
+        setlist.entries.retain(|entry| library.item(&entry.sheet_music_id).is_some());
+        match library.setlists.iter_mut().find(|local| local.id == setlist.id) {
+            Some(local) if *local == setlist => report.unchanged += 1,
+            Some(local) => {
+                report.conflicts.push(conflict("setlist", &setlist.id, &local.name, "Local setlist has different details"));
+                if replace {
+                    *local = setlist;
+                    report.updated += 1;
+                }
+            }
+            None => {
+                library.setlists.push(setlist);
+                report.added += 1;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+// Runs after the library merge, so links to pieces that didn't make it in can be dropped
+pub fn merge_events(
+    calendar: &mut Calendar,
+    events: Vec<PracticeEvent>,
+    sheet_music_ids: &[String],
+    strategy: ConflictStrategy,
+    report: &mut ImportReport,
+) {
+    for mut event in events {
+        if event.id.is_empty() {
+            event.id = library::new_id();
+        }
+        if event.sheet_music_id.as_ref().is_some_and(|id| !sheet_music_ids.contains(id)) {
+            event.sheet_music_id = None;
+        }
+        match calendar.events.iter_mut().find(|local| local.id == event.id) {
+            Some(local) if *local == event => report.unchanged += 1,
+            Some(local) => {
+                report.conflicts.push(conflict("event", &event.id, &local.title, "Local event has different details"));
+                if strategy == ConflictStrategy::ReplaceLocal {
+                    *local = event;
+                    report.updated += 1;
+                }
+            }
+            None => {
+                calendar.events.push(event);
+                report.added += 1;
+            }
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn export_library(path: String) -> Result<ExportSummary, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let library = library::read(|library| library.clone())?;
+        let calendar = calendar::read(|calendar| calendar.clone())?;
+        let summary = write_archive(Path::new(&path), &library, &calendar)?;
+        println!(
+            "Exported {} pieces, {} PDFs and {} events to {}",
+            summary.items, summary.pdfs, summary.events, summary.path
+        );
+        Ok(summary)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn import_library(path: String, strategy: Option<ConflictStrategy>) -> Result<ImportReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let file = File::open(&path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        let mut archive = ZipArchive::new(file).map_err(|e| format!("{} is not a valid archive: {}", path, e))?;
+        let mut manifest = read_manifest(&mut archive)?;
+        let strategy = strategy.unwrap_or_default();
+
+        let mut report = library::update(|library| merge_archive(library, &mut manifest, &mut archive, strategy))?;
+        let sheet_music_ids = library::read(|library| library.items.iter().map(|item| item.id.clone()).collect::<Vec<_>>())?;
+        calendar::update(|calendar| {
+            merge_events(calendar, manifest.calendar_events, &sheet_music_ids, strategy, &mut report);
+            Ok(())
+        })?;
+        println!(
+            "Imported {}: {} added, {} updated, {} unchanged, {} conflicts",
+            path,
+            report.added,
+            report.updated,
+            report.unchanged,
+            report.conflicts.len()
+        );
+        Ok(report)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// Lets the UI show what's inside an archive before merging it
+#[tauri::command]
+pub fn inspect_library_archive(path: String) -> Result<Manifest, String> {
+    let file = File::open(&path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("{} is not a valid archive: {}", path, e))?;
+    read_manifest(&mut archive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn item(id: &str) -> Value {
+        json!({
+            "id": id,
+            "title": "Gymnopédie No. 1",
+            "composer": "Satie",
+            "pdfPath": "/Users/teacher/Scores/gymnopedie.pdf",
+            "isFavorite": false,
+            "dateAdded": "2026-01-10T09:00:00Z",
+        })
+    }
+
+    // Writes an archive by hand, the way someone crafting one would
+    fn archive_file(manifest: Value, pdfs: &[&str]) -> ZipArchive<File> {
+        let path = store::sub_dir("archives").unwrap().join(format!("{}.zip", library::new_id()));
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file(MANIFEST_NAME, SimpleFileOptions::default()).unwrap();
+        zip.write_all(manifest.to_string().as_bytes()).unwrap();
+        for id in pdfs {
+            zip.start_file(pdf_entry_name(id), SimpleFileOptions::default()).unwrap();
+            zip.write_all(b"%PDF-1.4\n%%EOF\n").unwrap();
+        }
+        zip.finish().unwrap();
+        ZipArchive::new(File::open(&path).unwrap()).unwrap()
+    }
+
+    fn manifest(items: Vec<Value>) -> Value {
+        json!({
+            "version": ARCHIVE_VERSION,
+            "exportedAt": "2026-01-10T09:00:00Z",
+            "items": items,
+            "tags": [],
+            "itemTags": [],
+            "collections": [],
+            "setlists": [],
+        })
+    }
+
+    #[test]
+    fn tags_matching_a_local_name_keep_their_links() {
+        let _lock = store::lock_for_test();
+        let (piece, incoming_tag, local_tag) = (library::new_id(), library::new_id(), library::new_id());
+        let mut manifest_json = manifest(vec![item(&piece)]);
+        manifest_json["tags"] = json!([{ "id": incoming_tag, "name": "exam pieces", "color": null }]);
+        manifest_json["itemTags"] = json!([{ "sheetMusicId": piece, "tagId": incoming_tag }]);
+        let mut archive = archive_file(manifest_json, &[]);
+        let mut manifest = read_manifest(&mut archive).unwrap();
+
+        let mut library = Library::default();
+        library.tags.push(Tag { id: local_tag.clone(), name: "Exam Pieces".to_string(), color: Some("#f00".to_string()) });
+        let report = merge_archive(&mut library, &mut manifest, &mut archive, ConflictStrategy::KeepLocal).unwrap();
+
+        assert!(report.conflicts.is_empty());
+        assert_eq!(library.tags.len(), 1);
+        assert_eq!(library.item_tags, vec![ItemTag { sheet_music_id: piece, tag_id: local_tag }]);
+    }
+
+    #[test]
+    fn ids_that_could_escape_the_data_dir_are_refused() {
+        let _lock = store::lock_for_test();
+        let escape = store::data_dir().unwrap().join("escaped.pdf");
+        for id in ["../escaped", "../../x", "/tmp/x", "C:\\x", "c:x", "pdfs/../../escaped"] {
+            let mut archive = archive_file(manifest(vec![item(id)]), &[id]);
+            assert!(read_manifest(&mut archive).is_err(), "{} was accepted", id);
+
+            // Merging a manifest that skipped read_manifest still refuses to write the PDF
+            let mut manifest: Manifest = serde_json::from_value(manifest(vec![item(id)])).unwrap();
+            let mut library = Library::default();
+            assert!(merge_archive(&mut library, &mut manifest, &mut archive, ConflictStrategy::KeepLocal).is_err());
+            assert!(library.items.is_empty());
+        }
+        assert!(!escape.exists());
+
+        let id = library::new_id();
+        let mut archive = archive_file(manifest(vec![item(&id)]), &[&id]);
+        let mut manifest = read_manifest(&mut archive).unwrap();
+        let mut library = Library::default();
+        merge_archive(&mut library, &mut manifest, &mut archive, ConflictStrategy::KeepLocal).unwrap();
+        assert_eq!(library.items[0].pdf_path, store::sub_dir("pdfs").unwrap().join(format!("{}.pdf", id)).to_string_lossy());
+    }
+}
@@ -0,0 +1,1377 @@
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use url::Url;
+
+use crate::archive::ConflictStrategy;
+use crate::calendar::{self, EventType, PracticeEvent};
+use crate::{ics, library, store};
+
+const CALDAV_DOCUMENT: &str = "caldav";
+const CALDAV_SYNC_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_REDIRECTS: usize = 5;
+// Keeps calendar-multiget requests and their responses a manageable size
+const MULTIGET_BATCH: usize = 50;
+const XML_NAMESPACES: &str =
+    r#"xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav" xmlns:cs="http://calendarserver.org/ns/" xmlns:ic="http://apple.com/ns/ical/""#;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CalDavAccount {
+    pub server_url: String,
+    pub username: String,
+    // Only held in memory, like the API session; caldav.json never has it, so after a restart the frontend asks for it
+    // again through unlock_caldav. Files written before this still load it once and lose it on the next save
+    #[serde(default, skip_serializing)]
+    pub password: String,
+}
+
+// What we last saw of one calendar object resource, keyed by UID (the local series ID)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncedResource {
+    pub href: String,
+    pub etag: Option<String>,
+    // Hash of the local events as of the last sync, used to tell local edits apart
+    pub hash: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkedCalendar {
+    pub url: String,
+    pub display_name: String,
+    pub color: Option<String>,
+    // New local events of these types are pushed here; remote events without a known category get the first one
+    pub event_types: Vec<EventType>,
+    pub ctag: Option<String>,
+    pub sync_token: Option<String>,
+    pub resources: HashMap<String, SyncedResource>,
+    pub last_synced: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CalDavConflict {
+    pub calendar_url: String,
+    pub uid: String,
+    pub title: String,
+    pub winner: String,
+    pub resolved_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct CalDavState {
+    account: Option<CalDavAccount>,
+    calendars: Vec<LinkedCalendar>,
+    #[serde(default)]
+    strategy: ConflictStrategy,
+    conflicts: Vec<CalDavConflict>,
+    last_sync: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteCalendar {
+    pub url: String,
+    pub display_name: String,
+    pub color: Option<String>,
+    pub linked: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkedCalendarInfo {
+    pub url: String,
+    pub display_name: String,
+    pub color: Option<String>,
+    pub event_types: Vec<EventType>,
+    pub events: usize,
+    pub last_synced: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CalDavStatus {
+    pub configured: bool,
+    pub needs_password: bool,
+    pub server_url: Option<String>,
+    pub username: Option<String>,
+    pub calendars: Vec<LinkedCalendarInfo>,
+    pub strategy: ConflictStrategy,
+    pub conflicts: usize,
+    pub last_sync: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CalDavReport {
+    pub status: String,
+    pub pulled: usize,
+    pub pushed: usize,
+    pub deleted_locally: usize,
+    pub deleted_remotely: usize,
+    pub conflicts: usize,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug)]
+enum DavError {
+    Status(u16),
+    Transport(String),
+    Local(String),
+}
+
+impl fmt::Display for DavError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DavError::Status(401) => write!(f, "The CalDAV server rejected the username or password"),
+            DavError::Status(status) => write!(f, "The CalDAV server answered with status {}", status),
+            DavError::Transport(message) => write!(f, "Couldn't reach the CalDAV server: {}", message),
+            DavError::Local(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for DavError {
+    fn from(message: String) -> Self {
+        DavError::Local(message)
+    }
+}
+
+// One <response> of a 207 Multi-Status body, keeping only properties the server reported as found
+#[derive(Default, Debug)]
+struct DavResponse {
+    href: String,
+    status: Option<u16>,
+    props: HashMap<String, String>,
+    resource_types: Vec<String>,
+    components: Vec<String>,
+}
+
+impl DavResponse {
+    fn prop(&self, name: &str) -> Option<String> {
+        self.props.get(name).map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+    }
+}
+
+#[derive(Default, Debug)]
+struct Multistatus {
+    responses: Vec<DavResponse>,
+    sync_token: Option<String>,
+}
+
+struct FetchedResource {
+    href: String,
+    etag: Option<String>,
+    data: String,
+}
+
+enum Precondition {
+    Match(String),
+    NoneMatch,
+    Unconditional,
+}
+
+enum RemoteChange {
+    Updated {
+        href: String,
+        etag: Option<String>,
+        events: Vec<PracticeEvent>,
+    },
+    Deleted,
+}
+
+enum LocalChange {
+    Replace(String, Vec<PracticeEvent>),
+    Remove(String),
+}
+
+static STATE: Lazy<Mutex<Option<CalDavState>>> = Lazy::new(|| Mutex::new(None));
+// Held for a whole sync so the timer and the sync button never run one concurrently
+static SYNC_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+fn with_state<R>(f: impl FnOnce(&mut CalDavState) -> Result<R, String>) -> Result<R, String> {
+    let mut guard = STATE.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(CALDAV_DOCUMENT)?);
+    }
+    let state = guard.as_mut().unwrap();
+    let result = f(state)?;
+    store::save(CALDAV_DOCUMENT, state)?;
+    Ok(result)
+}
+
+// For lookups; sync checks the account every few minutes and shouldn't rewrite caldav.json each time
+fn read_state<R>(f: impl FnOnce(&CalDavState) -> R) -> Result<R, String> {
+    let mut guard = STATE.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(CALDAV_DOCUMENT)?);
+    }
+    Ok(f(guard.as_ref().unwrap()))
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn propfind_body(props: &str) -> String {
+    format!(r#"<?xml version="1.0" encoding="utf-8"?><d:propfind {}><d:prop>{}</d:prop></d:propfind>"#, XML_NAMESPACES, props)
+}
+
+fn parse_status(line: &str) -> Option<u16> {
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn local_name(element: &BytesStart) -> String {
+    String::from_utf8_lossy(element.local_name().as_ref()).to_string()
+}
+
+// Namespaces are matched on local names only; the properties we read don't clash across DAV, CalDAV and the CS extensions
+fn parse_multistatus(xml: &str) -> Result<Multistatus, String> {
+    let invalid = |e: quick_xml::Error| format!("Invalid WebDAV response: {}", e);
+    let mut reader = Reader::from_str(xml);
+    let mut stack: Vec<String> = Vec::new();
+    let mut result = Multistatus::default();
+    let mut response = DavResponse::default();
+    let mut propstat = DavResponse::default();
+
+    loop {
+        let (element, text) = match reader.read_event().map_err(invalid)? {
+            Event::Start(element) => (Some((element.clone(), true)), None),
+            Event::Empty(element) => (Some((element.clone(), false)), None),
+            Event::Text(text) => (None, Some(text.unescape().map_err(invalid)?.to_string())),
+            Event::CData(data) => (None, Some(String::from_utf8_lossy(&data.into_inner()).to_string())),
+            Event::End(_) => {
+                match stack.pop().as_deref() {
+                    Some("propstat") => {
+                        let found = propstat.status.map(|status| (200..300).contains(&status)).unwrap_or(true);
+                        let finished = std::mem::take(&mut propstat);
+                        if found {
+                            response.props.extend(finished.props);
+                            response.resource_types.extend(finished.resource_types);
+                            response.components.extend(finished.components);
+                        }
+                    }
+                    Some("response") => result.responses.push(std::mem::take(&mut response)),
+                    _ => {}
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        if let Some((element, is_start)) = element {
+            let name = local_name(&element);
+            match stack.last().map(String::as_str) {
+                Some("resourcetype") => propstat.resource_types.push(name.clone()),
+                Some("supported-calendar-component-set") if name == "comp" => {
+                    if let Ok(Some(attribute)) = element.try_get_attribute("name") {
+                        propstat.components.push(String::from_utf8_lossy(&attribute.value).to_uppercase());
+                    }
+                }
+                _ => {}
+            }
+            if is_start {
+                stack.push(name);
+            }
+            continue;
+        }
+
+        let text = match text {
+            Some(text) => text,
+            None => continue,
+        };
+        let depth = stack.len();
+        let parent = if depth >= 2 { stack[depth - 2].as_str() } else { "" };
+        match (parent, stack.last().map(String::as_str)) {
+            ("response", Some("href")) => response.href.push_str(text.trim()),
+            ("response", Some("status")) => response.status = parse_status(&text),
+            ("propstat", Some("status")) => propstat.status = parse_status(&text),
+            ("multistatus", Some("sync-token")) if depth == 2 => result.sync_token = Some(text.trim().to_string()),
+            _ => {
+                // Text anywhere under propstat/prop/<name> belongs to that property, including nested hrefs
+                if let Some(index) = stack.iter().position(|name| name == "prop") {
+                    if index > 0 && stack[index - 1] == "propstat" && depth > index + 1 {
+                        propstat.props.entry(stack[index + 1].clone()).or_default().push_str(&text);
+                    }
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+struct DavClient {
+    agent: ureq::Agent,
+    authorization: String,
+}
+
+impl DavClient {
+    fn new(account: &CalDavAccount) -> Self {
+        let credentials = format!("{}:{}", account.username, account.password);
+        DavClient {
+            // Redirects are followed by hand, since ureq would turn PROPFIND into GET
+            agent: ureq::AgentBuilder::new().redirects(0).timeout(REQUEST_TIMEOUT).build(),
+            authorization: format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials)),
+        }
+    }
+
+    fn send(&self, method: &str, url: &str, headers: &[(&str, &str)], body: Option<&str>) -> Result<ureq::Response, DavError> {
+        let mut url = url.to_string();
+        for _ in 0..=MAX_REDIRECTS {
+            let mut request = self.agent.request(method, &url).set("Authorization", &self.authorization);
+            for (name, value) in headers {
+                request = request.set(name, value);
+            }
+            let result = match body {
+                Some(body) => request.send_string(body),
+                None => request.call(),
+            };
+            let response = match result {
+                Ok(response) => response,
+                Err(ureq::Error::Status(status, _)) => return Err(DavError::Status(status)),
+                Err(ureq::Error::Transport(transport)) => return Err(DavError::Transport(transport.to_string())),
+            };
+            let location = response.header("Location").map(str::to_string);
+            match (response.status(), location) {
+                (301 | 302 | 307 | 308, Some(location)) => {
+                    url = Url::parse(&url)
+                        .and_then(|base| base.join(&location))
+                        .map_err(|e| DavError::Local(format!("Invalid redirect to {}: {}", location, e)))?
+                        .to_string();
+                }
+                _ => return Ok(response),
+            }
+        }
+        Err(DavError::Local(format!("Too many redirects for {}", url)))
+    }
+
+    // Returns the parsed body along with the URL it came from, for resolving the hrefs in it
+    fn xml(&self, method: &str, url: &str, depth: &str, body: &str) -> Result<(Multistatus, Url), DavError> {
+        let response = self.send(
+            method,
+            url,
+            &[("Depth", depth), ("Content-Type", "application/xml; charset=utf-8")],
+            Some(body),
+        )?;
+        let final_url = Url::parse(response.get_url()).map_err(|e| DavError::Local(e.to_string()))?;
+        let text = response.into_string().map_err(|e| DavError::Transport(e.to_string()))?;
+        Ok((parse_multistatus(&text)?, final_url))
+    }
+
+    fn put(&self, url: &str, data: &str, precondition: &Precondition) -> Result<Option<String>, DavError> {
+        let mut headers = vec![("Content-Type", "text/calendar; charset=utf-8")];
+        match precondition {
+            Precondition::Match(etag) => headers.push(("If-Match", etag.as_str())),
+            Precondition::NoneMatch => headers.push(("If-None-Match", "*")),
+            Precondition::Unconditional => {}
+        }
+        let response = self.send("PUT", url, &headers, Some(data))?;
+        // Servers that rewrite what we sent leave the ETag out; the next sync then fetches their version
+        Ok(response.header("ETag").map(str::to_string))
+    }
+
+    fn delete(&self, url: &str, precondition: &Precondition) -> Result<(), DavError> {
+        let headers: Vec<(&str, &str)> = match precondition {
+            Precondition::Match(etag) => vec![("If-Match", etag.as_str())],
+            _ => Vec::new(),
+        };
+        match self.send("DELETE", url, &headers, None) {
+            Ok(_) | Err(DavError::Status(404)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn resolve(base: &Url, href: &str) -> Result<String, DavError> {
+    base.join(href)
+        .map(|url| url.to_string())
+        .map_err(|e| DavError::Local(format!("Invalid href {}: {}", href, e)))
+}
+
+fn collection_url(url: &str) -> String {
+    if url.ends_with('/') {
+        url.to_string()
+    } else {
+        format!("{}/", url)
+    }
+}
+
+// RFC 6764 discovery: principal, then its calendar home, then the calendars in it
+fn discover(client: &DavClient, server_url: &str) -> Result<Vec<RemoteCalendar>, DavError> {
+    let principal_body = propfind_body("<d:current-user-principal/>");
+    let (principal, base) = match client.xml("PROPFIND", server_url, "0", &principal_body) {
+        Err(DavError::Status(404 | 405)) => {
+            let well_known = resolve(&Url::parse(server_url).map_err(|e| DavError::Local(e.to_string()))?, "/.well-known/caldav")?;
+            client.xml("PROPFIND", &well_known, "0", &principal_body)?
+        }
+        result => result?,
+    };
+    let principal_url = match principal.responses.iter().find_map(|response| response.prop("current-user-principal")) {
+        Some(href) => resolve(&base, &href)?,
+        None => base.to_string(),
+    };
+
+    let (home, base) = client.xml("PROPFIND", &principal_url, "0", &propfind_body("<c:calendar-home-set/>"))?;
+    let home_url = match home.responses.iter().find_map(|response| response.prop("calendar-home-set")) {
+        Some(href) => resolve(&base, &href)?,
+        None => principal_url,
+    };
+
+    let props = "<d:resourcetype/><d:displayname/><c:supported-calendar-component-set/><ic:calendar-color/>";
+    let (listing, base) = client.xml("PROPFIND", &home_url, "1", &propfind_body(props))?;
+    let mut calendars = Vec::new();
+    for response in listing.responses {
+        let is_calendar = response.resource_types.iter().any(|kind| kind == "calendar");
+        let has_events = response.components.is_empty() || response.components.iter().any(|component| component == "VEVENT");
+        if !is_calendar || !has_events {
+            continue;
+        }
+        let url = collection_url(&resolve(&base, &response.href)?);
+        calendars.push(RemoteCalendar {
+            display_name: response.prop("displayname").unwrap_or_else(|| url.clone()),
+            // Apple's calendar-color is #RRGGBBAA; the frontend expects #RRGGBB
+            color: response.prop("calendar-color").map(|color| color.chars().take(7).collect()),
+            url,
+            linked: false,
+        });
+    }
+    Ok(calendars)
+}
+
+// Hrefs of resources that changed or disappeared on the server since the last sync
+fn remote_changes(client: &DavClient, linked: &mut LinkedCalendar) -> Result<(Vec<String>, Vec<String>), DavError> {
+    let known: HashMap<String, Option<String>> = linked
+        .resources
+        .values()
+        .map(|resource| (resource.href.clone(), resource.etag.clone()))
+        .collect();
+    let is_new = |href: &str, etag: &Option<String>| known.get(href).map(|known| known != etag || etag.is_none()).unwrap_or(true);
+
+    if let Some(token) = linked.sync_token.clone() {
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?><d:sync-collection {}><d:sync-token>{}</d:sync-token><d:sync-level>1</d:sync-level><d:prop><d:getetag/></d:prop></d:sync-collection>"#,
+            XML_NAMESPACES,
+            xml_escape(&token)
+        );
+        match client.xml("REPORT", &linked.url, "1", &body) {
+            Ok((changes, base)) => {
+                let (mut changed, mut deleted) = (Vec::new(), Vec::new());
+                for response in changes.responses {
+                    let href = resolve(&base, &response.href)?;
+                    if href == linked.url {
+                        continue;
+                    }
+                    if response.status == Some(404) {
+                        deleted.push(href);
+                    } else if is_new(&href, &response.prop("getetag")) {
+                        changed.push(href);
+                    }
+                }
+                linked.sync_token = changes.sync_token.or(Some(token));
+                return Ok((changed, deleted));
+            }
+            // The server forgot the token (valid-sync-token precondition); start over with a full listing
+            Err(DavError::Status(400 | 403 | 409 | 410 | 412)) => {
+                println!("Sync token for {} expired, listing the whole calendar", linked.display_name);
+                linked.sync_token = None;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    // Read before listing, so anything that changes while we list shows up next time
+    let (collection, _) = client.xml("PROPFIND", &linked.url, "0", &propfind_body("<cs:getctag/><d:sync-token/>"))?;
+    let collection = collection.responses.into_iter().next().unwrap_or_default();
+    let ctag = collection.prop("getctag");
+    let unchanged = ctag.is_some() && ctag == linked.ctag && linked.last_synced.is_some();
+    linked.ctag = ctag;
+    linked.sync_token = collection.prop("sync-token");
+    if unchanged {
+        return Ok((Vec::new(), Vec::new()));
+    }
+
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?><c:calendar-query {}><d:prop><d:getetag/></d:prop><c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VEVENT"/></c:comp-filter></c:filter></c:calendar-query>"#,
+        XML_NAMESPACES
+    );
+    let (listing, base) = client.xml("REPORT", &linked.url, "1", &body)?;
+    let mut listed = HashSet::new();
+    let mut changed = Vec::new();
+    for response in listing.responses {
+        let href = resolve(&base, &response.href)?;
+        if href == linked.url {
+            continue;
+        }
+        if is_new(&href, &response.prop("getetag")) {
+            changed.push(href.clone());
+        }
+        listed.insert(href);
+    }
+    let deleted = known.into_keys().filter(|href| !listed.contains(href)).collect();
+    Ok((changed, deleted))
+}
+
+// Calendar data and ETags for the given hrefs; ones the server no longer has are returned as deleted
+fn fetch_resources(client: &DavClient, calendar_url: &str, hrefs: &[String]) -> Result<(Vec<FetchedResource>, Vec<String>), DavError> {
+    let (mut fetched, mut missing) = (Vec::new(), Vec::new());
+    for batch in hrefs.chunks(MULTIGET_BATCH) {
+        let mut body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?><c:calendar-multiget {}><d:prop><d:getetag/><c:calendar-data/></d:prop>"#,
+            XML_NAMESPACES
+        );
+        for href in batch {
+            let path = Url::parse(href).map(|url| url.path().to_string()).unwrap_or_else(|_| href.clone());
+            body.push_str(&format!("<d:href>{}</d:href>", xml_escape(&path)));
+        }
+        body.push_str("</c:calendar-multiget>");
+        let (result, base) = client.xml("REPORT", calendar_url, "1", &body)?;
+        for response in result.responses {
+            let href = resolve(&base, &response.href)?;
+            match response.props.get("calendar-data") {
+                Some(data) if response.status.map(|status| status < 300).unwrap_or(true) => {
+                    fetched.push(FetchedResource {
+                        etag: response.prop("getetag"),
+                        data: data.clone(),
+                        href,
+                    })
+                }
+                _ => missing.push(href),
+            }
+        }
+    }
+    Ok((fetched, missing))
+}
+
+fn resource_url(calendar_url: &str, uid: &str) -> Result<String, DavError> {
+    let name: String = uid
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    resolve(&Url::parse(calendar_url).map_err(|e| DavError::Local(e.to_string()))?, &format!("{}.ics", name))
+}
+
+fn series_id(event: &PracticeEvent) -> String {
+    match (&event.recurring_event_id, event.is_override()) {
+        (Some(series_id), true) => series_id.clone(),
+        _ => event.id.clone(),
+    }
+}
+
+// Local events grouped the way they're stored on the server: a series together with its edited occurrences
+fn local_groups(calendar: &calendar::Calendar) -> HashMap<String, Vec<PracticeEvent>> {
+    let mut groups: HashMap<String, Vec<PracticeEvent>> = HashMap::new();
+    for event in &calendar.events {
+        groups.entry(series_id(event)).or_default().push(event.clone());
+    }
+    groups
+}
+
+fn group_hash(events: &[PracticeEvent]) -> String {
+    let mut events = events.to_vec();
+    events.sort_by(|a, b| (a.original_start, &a.id).cmp(&(b.original_start, &b.id)));
+    format!("{:x}", Sha256::digest(serde_json::to_vec(&events).unwrap_or_default()))
+}
+
+// Swaps in the server's version of a series, keeping the IDs of edited occurrences we already have
+fn replace_group(calendar: &mut calendar::Calendar, uid: &str, events: Vec<PracticeEvent>) {
+    let existing: HashMap<DateTime<Utc>, String> = calendar
+        .events
+        .iter()
+        .filter(|event| event.is_override() && event.recurring_event_id.as_deref() == Some(uid))
+        .filter_map(|event| event.original_start.map(|start| (start, event.id.clone())))
+        .collect();
+    calendar.events.retain(|event| series_id(event) != uid);
+    for mut event in events {
+        if let Some(id) = event.original_start.and_then(|start| existing.get(&start)).filter(|_| event.is_override()) {
+            event.id = id.clone();
+        }
+        calendar.events.push(event);
+    }
+}
+
+fn push_group(
+    client: &DavClient,
+    linked: &mut LinkedCalendar,
+    uid: &str,
+    local: Option<&Vec<PracticeEvent>>,
+    precondition: Precondition,
+    report: &mut CalDavReport,
+) -> Result<(), DavError> {
+    let href = match linked.resources.get(uid) {
+        Some(resource) => resource.href.clone(),
+        None => resource_url(&linked.url, uid)?,
+    };
+    let result = match local {
+        Some(events) => client.put(&href, &ics::write_ics(events, None), &precondition).map(|etag| {
+            linked.resources.insert(
+                uid.to_string(),
+                SyncedResource {
+                    href: href.clone(),
+                    etag,
+                    hash: group_hash(events),
+                },
+            );
+            report.pushed += 1;
+        }),
+        None => client.delete(&href, &precondition).map(|_| {
+            linked.resources.remove(uid);
+            report.deleted_remotely += 1;
+        }),
+    };
+    match result {
+        // Changed on the server after we listed it; the record stays as is, so the next sync sees it as a remote change
+        Err(DavError::Status(412)) => {
+            report.errors.push(format!("{} changed on the server during sync", href));
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+fn sync_calendar(
+    client: &DavClient,
+    mut linked: LinkedCalendar,
+    strategy: ConflictStrategy,
+    claimed: &mut HashSet<String>,
+    report: &mut CalDavReport,
+    conflicts: &mut Vec<CalDavConflict>,
+) -> Result<LinkedCalendar, DavError> {
+    let default_type = linked.event_types.first().copied().unwrap_or_default();
+    let (changed, mut deleted) = remote_changes(client, &mut linked)?;
+    let (fetched, missing) = fetch_resources(client, &linked.url, &changed)?;
+    deleted.extend(missing);
+
+    let uid_by_href: HashMap<String, String> = linked
+        .resources
+        .iter()
+        .map(|(uid, resource)| (resource.href.clone(), uid.clone()))
+        .collect();
+    let mut remote: HashMap<String, RemoteChange> = HashMap::new();
+    for href in deleted {
+        if let Some(uid) = uid_by_href.get(&href) {
+            remote.insert(uid.clone(), RemoteChange::Deleted);
+        }
+    }
+    let sheet_music_ids: HashSet<String> = library::read(|library| library.items.iter().map(|item| item.id.clone()).collect())?;
+    for FetchedResource { href, etag, data } in fetched {
+        let (mut events, warnings) = ics::parse_ics(&data, default_type);
+        for warning in warnings {
+            println!("{}: {}", href, warning);
+        }
+        let uid = match events.iter().find(|event| !event.is_override()) {
+            Some(series) => series.id.clone(),
+            None => {
+                report.errors.push(format!("{} has no event Partitura can show", href));
+                continue;
+            }
+        };
+        for event in events.iter_mut() {
+            if event.sheet_music_id.as_ref().is_some_and(|id| !sheet_music_ids.contains(id)) {
+                event.sheet_music_id = None;
+            }
+        }
+        remote.insert(uid, RemoteChange::Updated { href, etag, events });
+    }
+
+    let groups = calendar::read(local_groups)?;
+    let mut uids: Vec<String> = linked.resources.keys().chain(remote.keys()).cloned().collect();
+    for (uid, events) in &groups {
+        let series_type = events.iter().find(|event| event.id == *uid).map(|event| event.event_type);
+        if !claimed.contains(uid) && series_type.is_some_and(|kind| linked.event_types.contains(&kind)) {
+            uids.push(uid.clone());
+        }
+    }
+    let mut seen = HashSet::new();
+    uids.retain(|uid| seen.insert(uid.clone()));
+
+    let mut local_changes = Vec::new();
+    for uid in uids {
+        claimed.insert(uid.clone());
+        let record = linked.resources.get(&uid).cloned();
+        let local = groups.get(&uid);
+        let local_changed = match (&record, local) {
+            (Some(record), Some(events)) => group_hash(events) != record.hash,
+            (Some(_), None) | (None, Some(_)) => true,
+            (None, None) => false,
+        };
+        let remote_change = match remote.remove(&uid) {
+            None if local_changed => {
+                let precondition = match record.as_ref().map(|record| record.etag.clone()) {
+                    Some(Some(etag)) => Precondition::Match(etag),
+                    Some(None) => Precondition::Unconditional,
+                    None => Precondition::NoneMatch,
+                };
+                push_group(client, &mut linked, &uid, local, precondition, report)?;
+                continue;
+            }
+            None => continue,
+            Some(remote_change) => remote_change,
+        };
+
+        if local_changed && (record.is_some() || local.is_some()) {
+            let title = local
+                .and_then(|events| events.iter().find(|event| event.id == uid))
+                .map(|event| event.title.clone())
+                .unwrap_or_else(|| uid.clone());
+            report.conflicts += 1;
+            conflicts.push(CalDavConflict {
+                calendar_url: linked.url.clone(),
+                uid: uid.clone(),
+                title,
+                winner: if strategy == ConflictStrategy::KeepLocal { "local" } else { "remote" }.to_string(),
+                resolved_at: Utc::now(),
+            });
+            if strategy == ConflictStrategy::KeepLocal {
+                let precondition = match &remote_change {
+                    RemoteChange::Updated { etag: Some(etag), .. } => Precondition::Match(etag.clone()),
+                    RemoteChange::Updated { etag: None, .. } => Precondition::Unconditional,
+                    RemoteChange::Deleted => {
+                        linked.resources.remove(&uid);
+                        Precondition::NoneMatch
+                    }
+                };
+                push_group(client, &mut linked, &uid, local, precondition, report)?;
+                continue;
+            }
+        }
+
+        match remote_change {
+            RemoteChange::Updated { href, etag, events } => {
+                linked.resources.insert(uid.clone(), SyncedResource { href, etag, hash: String::new() });
+                local_changes.push(LocalChange::Replace(uid, events));
+                report.pulled += 1;
+            }
+            RemoteChange::Deleted => {
+                linked.resources.remove(&uid);
+                if local.is_some() {
+                    local_changes.push(LocalChange::Remove(uid));
+                    report.deleted_locally += 1;
+                }
+            }
+        }
+    }
+
+    if !local_changes.is_empty() {
+        let replaced: Vec<String> = local_changes
+            .iter()
+            .filter_map(|change| match change {
+                LocalChange::Replace(uid, _) => Some(uid.clone()),
+                LocalChange::Remove(_) => None,
+            })
+            .collect();
+        calendar::update(|calendar| {
+            for change in local_changes {
+                match change {
+                    LocalChange::Replace(uid, events) => replace_group(calendar, &uid, events),
+                    LocalChange::Remove(uid) => calendar.events.retain(|event| series_id(event) != uid),
+                }
+            }
+            Ok(())
+        })?;
+        // Hashed as stored, so the pulled version doesn't look like a local edit next time
+        let groups = calendar::read(local_groups)?;
+        for uid in replaced {
+            if let (Some(resource), Some(events)) = (linked.resources.get_mut(&uid), groups.get(&uid)) {
+                resource.hash = group_hash(events);
+            }
+        }
+    }
+
+    linked.last_synced = Some(Utc::now());
+    Ok(linked)
+}
+
+fn sync_once() -> CalDavReport {
+    let _guard = SYNC_LOCK.lock().unwrap();
+    let loaded = read_state(|state| (state.account.clone(), state.calendars.clone(), state.strategy));
+    let (account, calendars, strategy) = match loaded {
+        Ok((Some(account), calendars, _)) if !calendars.is_empty() && account.password.is_empty() => {
+            return CalDavReport {
+                status: "needsPassword".to_string(),
+                ..Default::default()
+            }
+        }
+        Ok((Some(account), calendars, strategy)) if !calendars.is_empty() => (account, calendars, strategy),
+        Ok(_) => {
+            return CalDavReport {
+                status: "notConfigured".to_string(),
+                ..Default::default()
+            }
+        }
+        Err(e) => {
+            return CalDavReport {
+                status: "error".to_string(),
+                errors: vec![e],
+                ..Default::default()
+            }
+        }
+    };
+
+    let client = DavClient::new(&account);
+    let mut report = CalDavReport {
+        status: "ok".to_string(),
+        ..Default::default()
+    };
+    // A new local event goes to the first linked calendar that takes its type, never to several
+    let mut claimed: HashSet<String> = calendars.iter().flat_map(|linked| linked.resources.keys().cloned()).collect();
+    let mut conflicts = Vec::new();
+    let mut synced = Vec::new();
+    for linked in calendars {
+        let name = linked.display_name.clone();
+        match sync_calendar(&client, linked, strategy, &mut claimed, &mut report, &mut conflicts) {
+            Ok(linked) => synced.push(linked),
+            Err(DavError::Transport(message)) => {
+                report.status = "offline".to_string();
+                report.errors.push(message);
+                break;
+            }
+            Err(e) => {
+                report.status = "error".to_string();
+                report.errors.push(format!("{}: {}", name, e));
+            }
+        }
+    }
+
+    let saved = with_state(|state| {
+        // Calendars unlinked while we were syncing stay unlinked
+        for linked in synced {
+            if let Some(existing) = state.calendars.iter_mut().find(|existing| existing.url == linked.url) {
+                existing.ctag = linked.ctag;
+                existing.sync_token = linked.sync_token;
+                existing.resources = linked.resources;
+                existing.last_synced = linked.last_synced;
+            }
+        }
+        state.conflicts.extend(conflicts);
+        if report.status == "ok" {
+            state.last_sync = Some(Utc::now());
+        }
+        Ok(())
+    });
+    if let Err(e) = saved {
+        report.status = "error".to_string();
+        report.errors.push(e);
+    }
+
+    println!(
+        "CalDAV sync {}: {} pulled, {} pushed, {} deleted locally, {} deleted remotely, {} conflicts",
+        report.status, report.pulled, report.pushed, report.deleted_locally, report.deleted_remotely, report.conflicts
+    );
+    report
+}
+
+pub fn start(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(CALDAV_SYNC_INTERVAL);
+        let report = sync_once();
+        if report.status == "notConfigured" {
+            continue;
+        }
+        if let Err(e) = app.emit("caldav-sync-status", report) {
+            println!("Failed to emit CalDAV sync status: {}", e);
+        }
+    });
+}
+
+fn saved_account() -> Result<CalDavAccount, String> {
+    match read_state(|state| state.account.clone())? {
+        Some(account) if account.password.is_empty() => Err("Enter the CalDAV password again to reconnect".to_string()),
+        Some(account) => Ok(account),
+        None => Err("No CalDAV account is configured".to_string()),
+    }
+}
+
+fn mark_linked(mut calendars: Vec<RemoteCalendar>) -> Result<Vec<RemoteCalendar>, String> {
+    let linked: HashSet<String> = read_state(|state| state.calendars.iter().map(|linked| linked.url.clone()).collect())?;
+    for calendar in calendars.iter_mut() {
+        calendar.linked = linked.contains(&calendar.url);
+    }
+    Ok(calendars)
+}
+
+// Checks the credentials by discovering the account's calendars before saving anything
+#[tauri::command]
+pub async fn configure_caldav(server_url: String, username: String, password: String) -> Result<Vec<RemoteCalendar>, String> {
+    let account = CalDavAccount {
+        server_url: server_url.trim().to_string(),
+        username: username.trim().to_string(),
+        password,
+    };
+    Url::parse(&account.server_url).map_err(|e| format!("Invalid server URL: {}", e))?;
+    let calendars = tauri::async_runtime::spawn_blocking({
+        let account = account.clone();
+        move || discover(&DavClient::new(&account), &account.server_url).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    with_state(|state| {
+        let same_account = state
+            .account
+            .as_ref()
+            .map(|existing| existing.server_url == account.server_url && existing.username == account.username)
+            .unwrap_or(false);
+        if !same_account {
+            // Another account's sync records would make all of its events look deleted
+            state.calendars.clear();
+            state.conflicts.clear();
+            state.last_sync = None;
+        }
+        state.account = Some(account);
+        Ok(())
+    })?;
+    println!("Found {} CalDAV calendars", calendars.len());
+    mark_linked(calendars)
+}
+
+// Hands the password back after a restart; it's checked against the server before sync uses it
+#[tauri::command]
+pub async fn unlock_caldav(password: String) -> Result<(), String> {
+    let mut account = read_state(|state| state.account.clone())?.ok_or_else(|| "No CalDAV account is configured".to_string())?;
+    account.password = password;
+    tauri::async_runtime::spawn_blocking({
+        let account = account.clone();
+        move || discover(&DavClient::new(&account), &account.server_url).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    with_state(|state| {
+        match state.account.as_mut() {
+            Some(existing) if existing.server_url == account.server_url && existing.username == account.username => {
+                existing.password = account.password;
+            }
+            _ => return Err("The CalDAV account changed while checking the password".to_string()),
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn list_caldav_calendars() -> Result<Vec<RemoteCalendar>, String> {
+    let account = saved_account()?;
+    let calendars = tauri::async_runtime::spawn_blocking(move || discover(&DavClient::new(&account), &account.server_url).map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| e.to_string())??;
+    mark_linked(calendars)
+}
+
+#[tauri::command]
+pub fn link_caldav_calendar(url: String, display_name: String, color: Option<String>, event_types: Vec<EventType>) -> Result<(), String> {
+    let url = collection_url(&url);
+    with_state(|state| {
+        if state.account.is_none() {
+            return Err("No CalDAV account is configured".to_string());
+        }
+        match state.calendars.iter_mut().find(|linked| linked.url == url) {
+            Some(linked) => {
+                linked.display_name = display_name;
+                linked.color = color;
+                linked.event_types = event_types;
+            }
+            None => state.calendars.push(LinkedCalendar {
+                url,
+                display_name,
+                color,
+                event_types,
+                ctag: None,
+                sync_token: None,
+                resources: HashMap::new(),
+                last_synced: None,
+            }),
+        }
+        Ok(())
+    })
+}
+
+// Stops syncing; events already pulled stay in the local calendar
+#[tauri::command]
+pub fn unlink_caldav_calendar(url: String) -> Result<(), String> {
+    let url = collection_url(&url);
+    with_state(|state| {
+        state.calendars.retain(|linked| linked.url != url);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn remove_caldav_account() -> Result<(), String> {
+    with_state(|state| {
+        *state = CalDavState::default();
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn set_caldav_conflict_strategy(strategy: ConflictStrategy) -> Result<(), String> {
+    with_state(|state| {
+        state.strategy = strategy;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn sync_caldav() -> Result<CalDavReport, String> {
+    tauri::async_runtime::spawn_blocking(sync_once)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_caldav_status() -> Result<CalDavStatus, String> {
+    read_state(|state| {
+        CalDavStatus {
+            configured: state.account.is_some(),
+            needs_password: state.account.as_ref().is_some_and(|account| account.password.is_empty()),
+            server_url: state.account.as_ref().map(|account| account.server_url.clone()),
+            username: state.account.as_ref().map(|account| account.username.clone()),
+            calendars: state
+                .calendars
+                .iter()
+                .map(|linked| LinkedCalendarInfo {
+                    url: linked.url.clone(),
+                    display_name: linked.display_name.clone(),
+                    color: linked.color.clone(),
+                    event_types: linked.event_types.clone(),
+                    events: linked.resources.len(),
+                    last_synced: linked.last_synced,
+                })
+                .collect(),
+            strategy: state.strategy,
+            conflicts: state.conflicts.len(),
+            last_sync: state.last_sync,
+        }
+    })
+}
+
+#[tauri::command]
+pub fn list_caldav_conflicts() -> Result<Vec<CalDavConflict>, String> {
+    read_state(|state| state.conflicts.clone())
+}
+
+#[tauri::command]
+pub fn clear_caldav_conflicts() -> Result<(), String> {
+    with_state(|state| {
+        state.conflicts.clear();
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    const PASSWORD: &str = "secret";
+    const LESSON: &str = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nUID:lesson\r\nDTSTART:20260302T160000Z\r\nDTEND:20260302T170000Z\r\nSUMMARY:Lesson\r\nCATEGORIES:Lesson\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+
+    // Answers the way Radicale does: the principal at the root, calendars under it and a sync token per change
+    #[derive(Default)]
+    struct Radicale {
+        resources: BTreeMap<String, (String, String)>,
+        changes: Vec<(u64, String)>,
+        version: u64,
+        requests: Vec<String>,
+        // Another client's write that lands between our listing and our PUT
+        interfere: bool,
+    }
+
+    impl Radicale {
+        fn write(&mut self, path: &str, data: &str) -> String {
+            self.version += 1;
+            let etag = format!("\"v{}\"", self.version);
+            self.resources.insert(path.to_string(), (etag.clone(), data.to_string()));
+            self.changes.push((self.version, path.to_string()));
+            etag
+        }
+
+        fn remove(&mut self, path: &str) {
+            self.version += 1;
+            self.resources.remove(path);
+            self.changes.push((self.version, path.to_string()));
+        }
+
+        fn token(&self) -> String {
+            format!("http://radicale.test/sync/{}", self.version)
+        }
+
+        fn etag_response(&self, path: &str) -> String {
+            match self.resources.get(path) {
+                Some((etag, _)) => found(path, &format!("<d:getetag>{}</d:getetag>", xml_escape(etag))),
+                None => format!("<d:response><d:href>{}</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>", path),
+            }
+        }
+    }
+
+    fn found(href: &str, props: &str) -> String {
+        format!(
+            "<d:response><d:href>{}</d:href><d:propstat><d:prop>{}</d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+            href, props
+        )
+    }
+
+    fn multistatus(body: &str) -> (u16, String) {
+        (207, format!(r#"<?xml version="1.0" encoding="utf-8"?><d:multistatus {}>{}</d:multistatus>"#, XML_NAMESPACES, body))
+    }
+
+    fn between<'a>(text: &'a str, open: &str, close: &str) -> Vec<&'a str> {
+        text.split(open).skip(1).filter_map(|part| part.split(close).next()).collect()
+    }
+
+    fn respond(server: &mut Radicale, method: &str, path: &str, headers: &HashMap<String, String>, body: &str) -> (u16, String, Option<String>) {
+        let collection = "/alice/practice/";
+        let (status, body) = match (method, path) {
+            ("PROPFIND", "/") => multistatus(&found("/", "<d:current-user-principal><d:href>/alice/</d:href></d:current-user-principal>")),
+            ("PROPFIND", "/alice/") if body.contains("calendar-home-set") => {
+                multistatus(&found("/alice/", "<c:calendar-home-set><d:href>/alice/</d:href></c:calendar-home-set>"))
+            }
+            ("PROPFIND", "/alice/") => multistatus(&[
+                found("/alice/", "<d:resourcetype><d:collection/></d:resourcetype>"),
+                found(
+                    collection,
+                    r##"<d:resourcetype><d:collection/><c:calendar/></d:resourcetype><d:displayname>Practice</d:displayname><c:supported-calendar-component-set><c:comp name="VEVENT"/></c:supported-calendar-component-set><ic:calendar-color>#FF0000FF</ic:calendar-color>"##,
+                ),
+                found(
+                    "/alice/chores/",
+                    r#"<d:resourcetype><d:collection/><c:calendar/></d:resourcetype><d:displayname>Chores</d:displayname><c:supported-calendar-component-set><c:comp name="VTODO"/></c:supported-calendar-component-set>"#,
+                ),
+            ]
+            .concat()),
+            ("PROPFIND", _) if path == collection => multistatus(&found(
+                collection,
+                &format!("<cs:getctag>{}</cs:getctag><d:sync-token>{}</d:sync-token>", server.version, server.token()),
+            )),
+            ("REPORT", _) if path == collection && body.contains("sync-collection") => {
+                let since = between(body, "<d:sync-token>", "</d:sync-token>")
+                    .first()
+                    .and_then(|token| token.rsplit('/').next())
+                    .and_then(|version| version.parse::<u64>().ok())
+                    .filter(|version| *version <= server.version);
+                match since {
+                    Some(since) => {
+                        let mut paths: Vec<String> = server.changes.iter().filter(|(version, _)| *version > since).map(|(_, path)| path.clone()).collect();
+                        paths.sort();
+                        paths.dedup();
+                        let responses: String = paths.iter().map(|path| server.etag_response(path)).collect();
+                        multistatus(&format!("{}<d:sync-token>{}</d:sync-token>", responses, server.token()))
+                    }
+                    None => (403, "valid-sync-token".to_string()),
+                }
+            }
+            ("REPORT", _) if path == collection && body.contains("calendar-query") => {
+                multistatus(&server.resources.keys().map(|path| server.etag_response(path)).collect::<String>())
+            }
+            ("REPORT", _) if path == collection && body.contains("calendar-multiget") => multistatus(
+                &between(body, "<d:href>", "</d:href>")
+                    .into_iter()
+                    .map(|href| match server.resources.get(href) {
+                        Some((etag, data)) => found(
+                            href,
+                            &format!("<d:getetag>{}</d:getetag><c:calendar-data>{}</c:calendar-data>", xml_escape(etag), xml_escape(data)),
+                        ),
+                        None => server.etag_response(href),
+                    })
+                    .collect::<String>(),
+            ),
+            ("PUT" | "DELETE", _) if path.starts_with(collection) => {
+                if server.interfere && server.resources.contains_key(path) {
+                    server.interfere = false;
+                    let data = server.resources[path].1.replace("SUMMARY:", "SUMMARY:Edited elsewhere: ");
+                    server.write(path, &data);
+                }
+                let current = server.resources.get(path).map(|(etag, _)| etag.clone());
+                let if_match = headers.get("if-match");
+                let rejected = (if_match.is_some() && if_match != current.as_ref())
+                    || (headers.get("if-none-match").map(String::as_str) == Some("*") && current.is_some());
+                if rejected {
+                    (412, String::new())
+                } else if method == "PUT" {
+                    let etag = server.write(path, body);
+                    return (if current.is_some() { 204 } else { 201 }, String::new(), Some(etag));
+                } else {
+                    server.remove(path);
+                    (204, String::new())
+                }
+            }
+            _ => (404, String::new()),
+        };
+        (status, body, None)
+    }
+
+    fn serve(server: Arc<Mutex<Radicale>>) -> String {
+        let http = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", http.server_addr().to_ip().unwrap());
+        let authorization = format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(format!("alice:{}", PASSWORD)));
+        thread::spawn(move || {
+            for mut request in http.incoming_requests() {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let headers: HashMap<String, String> =
+                    request.headers().iter().map(|h| (h.field.as_str().as_str().to_ascii_lowercase(), h.value.as_str().to_string())).collect();
+                let method = request.method().to_string();
+                let path = request.url().to_string();
+                let mut server = server.lock().unwrap();
+                let detail = match method.as_str() {
+                    "REPORT" => ["sync-collection", "calendar-query", "calendar-multiget"].into_iter().find(|kind| body.contains(kind)).unwrap_or(""),
+                    _ if headers.contains_key("if-match") => "If-Match",
+                    _ if headers.contains_key("if-none-match") => "If-None-Match",
+                    _ => "",
+                };
+                server.requests.push(format!("{} {} {}", method, path, detail).trim_end().to_string());
+                let (status, body, etag) = if headers.get("authorization") != Some(&authorization) {
+                    (401, String::new(), None)
+                } else {
+                    respond(&mut server, &method, &path, &headers, &body)
+                };
+                let mut response = tiny_http::Response::from_string(body).with_status_code(status);
+                if let Some(etag) = etag {
+                    response = response.with_header(tiny_http::Header::from_bytes("ETag", etag).unwrap());
+                }
+                let _ = request.respond(response);
+            }
+        });
+        url
+    }
+
+    fn account(url: &str, password: &str) -> CalDavAccount {
+        CalDavAccount {
+            server_url: url.to_string(),
+            username: "alice".to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    fn reset(url: &str) -> String {
+        calendar::clear_for_test();
+        let calendar_url = format!("{}/alice/practice/", url);
+        with_state(|state| {
+            *state = CalDavState {
+                account: Some(account(url, PASSWORD)),
+                ..CalDavState::default()
+            };
+            Ok(())
+        })
+        .unwrap();
+        link_caldav_calendar(calendar_url.clone(), "Practice".to_string(), None, vec![EventType::Practice, EventType::Lesson]).unwrap();
+        calendar_url
+    }
+
+    fn rename(id: &str, title: &str) {
+        let mut event = calendar::get_event(id.to_string()).unwrap();
+        event.title = title.to_string();
+        calendar::update_event(event).unwrap();
+    }
+
+    fn take_requests(server: &Arc<Mutex<Radicale>>) -> Vec<String> {
+        std::mem::take(&mut server.lock().unwrap().requests)
+    }
+
+    #[test]
+    fn discovers_event_calendars_from_the_principal() {
+        let url = serve(Arc::new(Mutex::new(Radicale::default())));
+        let calendars = discover(&DavClient::new(&account(&url, PASSWORD)), &url).unwrap();
+        assert_eq!(calendars.len(), 1);
+        assert_eq!(calendars[0].url, format!("{}/alice/practice/", url));
+        assert_eq!(calendars[0].display_name, "Practice");
+        assert_eq!(calendars[0].color.as_deref(), Some("#FF0000"));
+
+        let error = discover(&DavClient::new(&account(&url, "wrong")), &url).unwrap_err();
+        assert_eq!(error.to_string(), "The CalDAV server rejected the username or password");
+    }
+
+    #[test]
+    fn syncs_both_ways_with_sync_tokens_and_multiget() {
+        let _lock = store::lock_for_test();
+        let server = Arc::new(Mutex::new(Radicale::default()));
+        let url = serve(server.clone());
+        server.lock().unwrap().write("/alice/practice/lesson.ics", LESSON);
+        reset(&url);
+        let scales = calendar::create_event(calendar::test_event("Scales")).unwrap().event.id;
+
+        let report = sync_once();
+        assert_eq!((report.status.as_str(), report.pulled, report.pushed, report.conflicts), ("ok", 1, 1, 0));
+        let lesson = calendar::get_event("lesson".to_string()).unwrap();
+        assert_eq!((lesson.title.as_str(), lesson.event_type), ("Lesson", EventType::Lesson));
+        let requests = take_requests(&server);
+        assert!(requests.contains(&"REPORT /alice/practice/ calendar-query".to_string()));
+        assert!(requests.contains(&"REPORT /alice/practice/ calendar-multiget".to_string()));
+        assert!(requests.contains(&format!("PUT /alice/practice/{}.ics If-None-Match", scales)));
+        assert!(server.lock().unwrap().resources[&format!("/alice/practice/{}.ics", scales)].1.contains("SUMMARY:Scales"));
+
+        // Our own PUT comes back in the next sync-collection report with the ETag we already have
+        let report = sync_once();
+        assert_eq!((report.pulled, report.pushed), (0, 0));
+        assert_eq!(take_requests(&server), vec!["REPORT /alice/practice/ sync-collection"]);
+
+        server.lock().unwrap().write("/alice/practice/lesson.ics", &LESSON.replace("SUMMARY:Lesson", "SUMMARY:Lesson with Ms. Chen"));
+        rename(&scales, "Scales and arpeggios");
+        let report = sync_once();
+        assert_eq!((report.pulled, report.pushed), (1, 1));
+        assert_eq!(calendar::get_event("lesson".to_string()).unwrap().title, "Lesson with Ms. Chen");
+        let requests = take_requests(&server);
+        assert!(requests.contains(&"REPORT /alice/practice/ calendar-multiget".to_string()));
+        assert!(requests.contains(&format!("PUT /alice/practice/{}.ics If-Match", scales)));
+
+        server.lock().unwrap().remove("/alice/practice/lesson.ics");
+        let report = sync_once();
+        assert_eq!(report.deleted_locally, 1);
+        assert!(calendar::get_event("lesson".to_string()).is_err());
+    }
+
+    #[test]
+    fn a_412_leaves_the_change_for_the_next_sync() {
+        let _lock = store::lock_for_test();
+        let server = Arc::new(Mutex::new(Radicale::default()));
+        let url = serve(server.clone());
+        reset(&url);
+        let scales = calendar::create_event(calendar::test_event("Scales")).unwrap().event.id;
+        assert_eq!(sync_once().pushed, 1);
+
+        rename(&scales, "Scales in thirds");
+        server.lock().unwrap().interfere = true;
+        let report = sync_once();
+        assert_eq!(report.pushed, 0);
+        assert!(report.errors.iter().any(|error| error.ends_with("changed on the server during sync")), "{:?}", report.errors);
+
+        // Both sides changed now; keeping the local version overwrites the other client's edit
+        let report = sync_once();
+        assert_eq!((report.conflicts, report.pushed), (1, 1));
+        assert_eq!(calendar::get_event(scales.clone()).unwrap().title, "Scales in thirds");
+        assert!(server.lock().unwrap().resources[&format!("/alice/practice/{}.ics", scales)].1.contains("SUMMARY:Scales in thirds"));
+        assert_eq!(list_caldav_conflicts().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn the_password_stays_out_of_the_saved_state() {
+        let _lock = store::lock_for_test();
+        let url = serve(Arc::new(Mutex::new(Radicale::default())));
+        reset(&url);
+        let saved = serde_json::to_string(&*STATE.lock().unwrap()).unwrap();
+        assert!(saved.contains("alice") && !saved.contains(PASSWORD));
+
+        // As after a restart, which loads the account without it
+        *STATE.lock().unwrap() = Some(serde_json::from_str(&saved).unwrap());
+        assert_eq!(sync_once().status, "needsPassword");
+        assert!(get_caldav_status().unwrap().needs_password);
+    }
+}
@@ -0,0 +1,809 @@
+use chrono::{DateTime, Duration, Local, LocalResult, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::recurrence::{self, RecurrenceRule, Until};
+use crate::scheduling::{self, SavedEvent};
+use crate::{library, store, sync};
+
+const CALENDAR_DOCUMENT: &str = "calendar";
+// Same fallback addEvent uses in calendarService.ts
+const DEFAULT_EVENT_COLOR: &str = "#3B82F6";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum EventType {
+    #[default]
+    Practice,
+    Concert,
+    Lesson,
+    Rehearsal,
+    Recital,
+}
+
+// Mirrors PracticeEvent from the frontend types
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PracticeEvent {
+    #[serde(default)]
+    pub id: String,
+    #[serde(rename = "type", default)]
+    pub event_type: EventType,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    // IANA zone the event was planned in, e.g. Europe/Vienna; repeats keep their wall-clock time there.
+    // Events without one follow the machine's zone, which is how they behaved before zones were stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+    // All-day events float: their dates are stored as midnight UTC and mean the same dates wherever you are
+    #[serde(default)]
+    pub all_day: bool,
+    #[serde(default)]
+    pub is_completed: bool,
+    pub sheet_music_id: Option<String>,
+    pub color: Option<String>,
+    // RFC 5545 RRULE value, e.g. FREQ=WEEKLY;BYDAY=TU,TH;COUNT=10
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rrule: Option<String>,
+    // Start times of occurrences that were deleted from the series
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exdates: Vec<DateTime<Utc>>,
+    // Set on edited occurrences and on expanded ones, pointing at the series they belong to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurring_event_id: Option<String>,
+    // The occurrence's start time as the series generates it, even if the occurrence was moved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_start: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum EditScope {
+    This,
+    ThisAndFollowing,
+    All,
+}
+
+// The wall clock an event's times are read on
+pub enum EventZone {
+    Named(Tz),
+    Local,
+    Floating,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ZonedTime {
+    pub utc: DateTime<Utc>,
+    // RFC 3339 with the zone's offset at that instant
+    pub local: String,
+    // "gap" when the wall time was skipped by a DST change and got moved forward,
+    // "ambiguous" when it happened twice and the earlier one was taken
+    pub adjustment: Option<String>,
+    pub later: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplayEvent {
+    #[serde(flatten)]
+    pub event: PracticeEvent,
+    // RFC 3339 in the display zone, or plain dates for all-day events (the end date is exclusive)
+    pub local_start: String,
+    pub local_end: String,
+    pub display_zone: String,
+}
+
+pub fn parse_zone(name: &str) -> Result<Tz, String> {
+    name.parse::<Tz>().map_err(|_| format!("Unknown time zone {}", name))
+}
+
+// Floating dates are stored as midnight UTC; this is the instant that date starts on this machine
+fn floating_instant(time: DateTime<Utc>) -> DateTime<Utc> {
+    recurrence::resolve_local(&Local, time.naive_utc())
+}
+
+fn local_string<Z: TimeZone>(time: DateTime<Utc>, zone: &Z) -> String
+where
+    Z::Offset: std::fmt::Display,
+{
+    time.with_timezone(zone).to_rfc3339()
+}
+
+impl PracticeEvent {
+    pub fn zone(&self) -> EventZone {
+        if self.all_day {
+            return EventZone::Floating;
+        }
+        match self.time_zone.as_deref().map(parse_zone) {
+            Some(Ok(tz)) => EventZone::Named(tz),
+            _ => EventZone::Local,
+        }
+    }
+
+    // The instants the event actually covers; all-day dates become this machine's midnights
+    pub fn span(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        if self.all_day {
+            (floating_instant(self.start_time), floating_instant(self.end_time))
+        } else {
+            (self.start_time, self.end_time)
+        }
+    }
+
+    // Half-open like the month grid's days; an instant event still shows on the day it happens
+    pub fn overlaps(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        let (event_start, event_end) = self.span();
+        event_start < end && (event_end > start || event_start >= start)
+    }
+
+    pub fn recurrence(&self) -> Result<Option<RecurrenceRule>, String> {
+        self.rrule.as_deref().map(str::parse).transpose()
+    }
+
+    // Recurrence follows the wall clock of the event's zone; all-day dates repeat as dates
+    pub fn occurrence_starts(&self, rule: &RecurrenceRule, before: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        match self.zone() {
+            EventZone::Named(tz) => rule.occurrences(self.start_time, &tz, before),
+            EventZone::Local => rule.occurrences(self.start_time, &Local, before),
+            EventZone::Floating => rule.occurrences(self.start_time, &Utc, before),
+        }
+    }
+
+    // Snaps all-day events to whole days, so a date picked anywhere means the same date everywhere
+    fn normalize(&mut self) {
+        if !self.all_day {
+            return;
+        }
+        let midnight = |time: DateTime<Utc>| time.date_naive().and_time(NaiveTime::MIN).and_utc();
+        self.start_time = midnight(self.start_time);
+        let end = midnight(self.end_time);
+        let end = if end < self.end_time { end + Duration::days(1) } else { end };
+        self.end_time = end.max(self.start_time + Duration::days(1));
+        self.time_zone = None;
+    }
+
+    fn display(self, zone: Option<&Tz>) -> DisplayEvent {
+        let (local_start, local_end, display_zone) = match (self.all_day, zone) {
+            (true, _) => (
+                self.start_time.date_naive().to_string(),
+                self.end_time.date_naive().to_string(),
+                "floating".to_string(),
+            ),
+            (false, Some(tz)) => (local_string(self.start_time, tz), local_string(self.end_time, tz), tz.name().to_string()),
+            (false, None) => (local_string(self.start_time, &Local), local_string(self.end_time, &Local), "local".to_string()),
+        };
+        DisplayEvent {
+            event: self,
+            local_start,
+            local_end,
+            display_zone,
+        }
+    }
+
+    pub fn is_override(&self) -> bool {
+        self.recurring_event_id.is_some() && self.rrule.is_none()
+    }
+
+    // A concrete occurrence of this series, addressed by its series ID and original start
+    pub fn instance(&self, start: DateTime<Utc>) -> PracticeEvent {
+        PracticeEvent {
+            start_time: start,
+            end_time: start + (self.end_time - self.start_time),
+            rrule: None,
+            exdates: Vec::new(),
+            recurring_event_id: Some(self.id.clone()),
+            original_start: Some(start),
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Calendar {
+    pub events: Vec<PracticeEvent>,
+}
+
+impl Calendar {
+    pub fn event(&self, id: &str) -> Option<&PracticeEvent> {
+        self.events.iter().find(|event| event.id == id)
+    }
+
+    fn event_mut(&mut self, id: &str) -> Result<&mut PracticeEvent, String> {
+        self.events
+            .iter_mut()
+            .find(|event| event.id == id)
+            .ok_or_else(|| format!("Event {} not found", id))
+    }
+
+    fn series(&self, id: &str) -> Result<(&PracticeEvent, RecurrenceRule), String> {
+        let event = self.event(id).ok_or_else(|| format!("Event {} not found", id))?;
+        let rule = event.recurrence()?.ok_or_else(|| format!("Event {} doesn't repeat", id))?;
+        Ok((event, rule))
+    }
+
+    // Recurring events are expanded into their occurrences, with edited ones replacing what the rule generates;
+    // sorted by start time, which is the order the grid and agenda render in
+    pub fn in_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<PracticeEvent> {
+        let mut events = Vec::new();
+        for event in &self.events {
+            let rule = match event.recurrence() {
+                Ok(Some(rule)) => rule,
+                Ok(None) => {
+                    if event.overlaps(start, end) {
+                        events.push(event.clone());
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    println!("Skipping event {} with invalid recurrence: {}", event.id, e);
+                    continue;
+                }
+            };
+            let overridden: HashSet<DateTime<Utc>> = self
+                .events
+                .iter()
+                .filter(|other| other.is_override() && other.recurring_event_id.as_deref() == Some(event.id.as_str()))
+                .filter_map(|other| other.original_start)
+                .collect();
+            // All-day dates are midnight UTC, which can be up to a day after the local range end
+            let before = if event.all_day {
+                end.checked_add_signed(Duration::days(1)).unwrap_or(DateTime::<Utc>::MAX_UTC)
+            } else {
+                end
+            };
+            for occurrence in event.occurrence_starts(&rule, before) {
+                if event.exdates.contains(&occurrence) || overridden.contains(&occurrence) {
+                    continue;
+                }
+                let instance = event.instance(occurrence);
+                if instance.overlaps(start, end) {
+                    events.push(instance);
+                }
+            }
+        }
+        events.sort_by_key(|event| event.span().0);
+        events
+    }
+
+    fn require_occurrence(&self, series_id: &str, original_start: DateTime<Utc>) -> Result<(), String> {
+        let (series, rule) = self.series(series_id)?;
+        if series.occurrence_starts(&rule, original_start + Duration::seconds(1)).contains(&original_start) {
+            Ok(())
+        } else {
+            Err(format!("Event {} has no occurrence at {}", series_id, original_start))
+        }
+    }
+
+    // Ends a series right before the given occurrence and returns how many occurrences it keeps
+    fn truncate_series(&mut self, series_id: &str, original_start: DateTime<Utc>) -> Result<(RecurrenceRule, u32), String> {
+        let (series, mut rule) = self.series(series_id)?;
+        let original_rule = rule.clone();
+        let kept = series.occurrence_starts(&rule, original_start).len() as u32;
+        if rule.count.is_some() {
+            rule.count = Some(kept);
+        } else {
+            rule.until = Some(Until::Utc(original_start - Duration::seconds(1)));
+        }
+
+        let series = self.event_mut(series_id)?;
+        series.rrule = Some(rule.to_string());
+        series.exdates.retain(|exdate| *exdate < original_start);
+        Ok((original_rule, kept))
+    }
+
+    fn edit_this(&mut self, series_id: &str, original_start: DateTime<Utc>, mut event: PracticeEvent) -> PracticeEvent {
+        event.recurring_event_id = Some(series_id.to_string());
+        event.original_start = Some(original_start);
+        event.rrule = None;
+        event.exdates.clear();
+        let existing = self
+            .events
+            .iter_mut()
+            .find(|other| other.is_override() && other.recurring_event_id.as_deref() == Some(series_id) && other.original_start == Some(original_start));
+        match existing {
+            Some(existing) => {
+                event.id = existing.id.clone();
+                *existing = event.clone();
+            }
+            None => {
+                event.id = library::new_id();
+                self.events.push(event.clone());
+            }
+        }
+        event
+    }
+
+    // Moving one occurrence moves the whole series by the same amount, edited occurrences included
+    fn edit_all(&mut self, series_id: &str, original_start: DateTime<Utc>, event: PracticeEvent) -> Result<PracticeEvent, String> {
+        let shift = event.start_time - original_start;
+        let duration = event.end_time - event.start_time;
+        let series = self.event_mut(series_id)?;
+        let updated = PracticeEvent {
+            id: series.id.clone(),
+            start_time: series.start_time + shift,
+            end_time: series.start_time + shift + duration,
+            rrule: event.rrule.clone().or_else(|| series.rrule.clone()),
+            exdates: series.exdates.iter().map(|exdate| *exdate + shift).collect(),
+            recurring_event_id: None,
+            original_start: None,
+            ..event
+        };
+        *series = updated.clone();
+        for other in self.events.iter_mut().filter(|other| other.is_override() && other.recurring_event_id.as_deref() == Some(series_id)) {
+            other.original_start = other.original_start.map(|start| start + shift);
+        }
+        Ok(updated)
+    }
+
+    // Splits the series: the old one ends before the occurrence, a new one carries the change forward
+    fn edit_following(&mut self, series_id: &str, original_start: DateTime<Utc>, event: PracticeEvent) -> Result<PracticeEvent, String> {
+        let exdates = self.series(series_id)?.0.exdates.clone();
+        let (mut rule, kept) = self.truncate_series(series_id, original_start)?;
+        rule.count = rule.count.map(|count| count.saturating_sub(kept));
+        let shift = event.start_time - original_start;
+
+        let new_series = PracticeEvent {
+            id: library::new_id(),
+            rrule: Some(event.rrule.clone().unwrap_or_else(|| rule.to_string())),
+            exdates: exdates.into_iter().filter(|exdate| *exdate >= original_start).map(|exdate| exdate + shift).collect(),
+            recurring_event_id: None,
+            original_start: None,
+            ..event
+        };
+        for other in self.events.iter_mut().filter(|other| other.is_override() && other.recurring_event_id.as_deref() == Some(series_id)) {
+            if other.original_start.is_some_and(|start| start >= original_start) {
+                other.recurring_event_id = Some(new_series.id.clone());
+                other.original_start = other.original_start.map(|start| start + shift);
+            }
+        }
+        self.events.push(new_series.clone());
+        Ok(new_series)
+    }
+
+    pub fn update_occurrence(
+        &mut self,
+        series_id: &str,
+        original_start: DateTime<Utc>,
+        scope: EditScope,
+        event: PracticeEvent,
+    ) -> Result<PracticeEvent, String> {
+        self.require_occurrence(series_id, original_start)?;
+        let is_first = self.series(series_id)?.0.start_time == original_start;
+        match scope {
+            EditScope::This => Ok(self.edit_this(series_id, original_start, event)),
+            EditScope::All => self.edit_all(series_id, original_start, event),
+            EditScope::ThisAndFollowing if is_first => self.edit_all(series_id, original_start, event),
+            EditScope::ThisAndFollowing => self.edit_following(series_id, original_start, event),
+        }
+    }
+
+    // Marks an event done; for a series only the given occurrence is, by editing just that one
+    pub fn complete(&mut self, id: &str, original_start: Option<DateTime<Utc>>) -> Result<PracticeEvent, String> {
+        let event = self.event(id).ok_or_else(|| format!("Event {} not found", id))?;
+        if event.rrule.is_none() {
+            let event = self.event_mut(id)?;
+            event.is_completed = true;
+            return Ok(event.clone());
+        }
+        let original_start = original_start.ok_or_else(|| format!("Event {} repeats; say which occurrence was done", id))?;
+        self.require_occurrence(id, original_start)?;
+        let existing = self
+            .events
+            .iter()
+            .find(|other| other.is_override() && other.recurring_event_id.as_deref() == Some(id) && other.original_start == Some(original_start));
+        let mut occurrence = match existing {
+            Some(existing) => existing.clone(),
+            None => self.event(id).unwrap().instance(original_start),
+        };
+        occurrence.is_completed = true;
+        Ok(self.edit_this(id, original_start, occurrence))
+    }
+
+    pub fn delete_event(&mut self, id: &str) -> Result<(), String> {
+        let index = self
+            .events
+            .iter()
+            .position(|event| event.id == id)
+            .ok_or_else(|| format!("Event {} not found", id))?;
+        let removed = self.events.remove(index);
+        // Edited occurrences go with their series
+        self.events.retain(|event| event.recurring_event_id.as_deref() != Some(id));
+        // A deleted edited occurrence mustn't come back as the one the rule generates
+        if let (true, Some(series_id), Some(original_start)) = (removed.is_override(), &removed.recurring_event_id, removed.original_start) {
+            if let Ok(series) = self.event_mut(series_id) {
+                if !series.exdates.contains(&original_start) {
+                    series.exdates.push(original_start);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn delete_occurrence(&mut self, series_id: &str, original_start: DateTime<Utc>, scope: EditScope) -> Result<(), String> {
+        self.require_occurrence(series_id, original_start)?;
+        let is_first = self.series(series_id)?.0.start_time == original_start;
+        let belongs = |other: &PracticeEvent, from: Option<DateTime<Utc>>| {
+            other.is_override()
+                && other.recurring_event_id.as_deref() == Some(series_id)
+                && from.map(|from| other.original_start.is_some_and(|start| start >= from)).unwrap_or(true)
+        };
+        match scope {
+            EditScope::This => {
+                self.events.retain(|other| !(belongs(other, Some(original_start)) && other.original_start == Some(original_start)));
+                let series = self.event_mut(series_id)?;
+                if !series.exdates.contains(&original_start) {
+                    series.exdates.push(original_start);
+                }
+            }
+            EditScope::ThisAndFollowing if !is_first => {
+                self.truncate_series(series_id, original_start)?;
+                self.events.retain(|other| !belongs(other, Some(original_start)));
+            }
+            EditScope::ThisAndFollowing | EditScope::All => {
+                self.events.retain(|other| other.id != series_id && !belongs(other, None));
+            }
+        }
+        Ok(())
+    }
+}
+
+static CALENDAR: Lazy<Mutex<Option<Calendar>>> = Lazy::new(|| Mutex::new(None));
+// Bumped on every saved change so derived views like the .ics feeds know when to rebuild
+static REVISION: AtomicU64 = AtomicU64::new(0);
+
+pub fn revision() -> u64 {
+    REVISION.load(Ordering::SeqCst)
+}
+
+pub fn read<R>(f: impl FnOnce(&Calendar) -> R) -> Result<R, String> {
+    let mut guard = CALENDAR.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(CALENDAR_DOCUMENT)?);
+    }
+    Ok(f(guard.as_ref().unwrap()))
+}
+
+// Same copy-then-save approach as the library, so a failed save leaves memory untouched
+fn commit<R>(f: impl FnOnce(&mut Calendar) -> Result<R, String>) -> Result<(Vec<PracticeEvent>, Vec<PracticeEvent>, R), String> {
+    let mut guard = CALENDAR.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(CALENDAR_DOCUMENT)?);
+    }
+    let mut next = guard.as_ref().unwrap().clone();
+    let result = f(&mut next)?;
+    store::save(CALENDAR_DOCUMENT, &next)?;
+    let after = next.events.clone();
+    let before = guard.replace(next).map(|previous| previous.events).unwrap_or_default();
+    REVISION.fetch_add(1, Ordering::SeqCst);
+    Ok((before, after, result))
+}
+
+// Local changes go through here so they end up in the sync operation log
+pub fn update<R>(f: impl FnOnce(&mut Calendar) -> Result<R, String>) -> Result<R, String> {
+    let (before, after, result) = commit(f)?;
+    // Recorded after the calendar lock is released, the sync engine takes its own lock
+    sync::record_event_changes(&before, &after);
+    Ok(result)
+}
+
+// Used by the sync engine to apply remote changes without logging them as local edits
+pub fn apply_remote<R>(f: impl FnOnce(&mut Calendar) -> Result<R, String>) -> Result<R, String> {
+    commit(f).map(|(_, _, result)| result)
+}
+
+// Checked against the library before taking the calendar lock, never while holding it
+fn validate(event: &PracticeEvent) -> Result<(), String> {
+    if event.title.trim().is_empty() {
+        return Err("Event title can't be empty".to_string());
+    }
+    if event.end_time < event.start_time {
+        return Err("Event can't end before it starts".to_string());
+    }
+    event.recurrence()?;
+    if let Some(time_zone) = &event.time_zone {
+        parse_zone(time_zone)?;
+    }
+    if let Some(sheet_music_id) = &event.sheet_music_id {
+        library::read(|library| library.require_item(sheet_music_id))??;
+    }
+    Ok(())
+}
+
+// Called once pieces are gone from the library; their events stay but lose the link
+pub fn unlink_sheet_music(ids: &[String]) -> Result<(), String> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    update(|calendar| {
+        for event in calendar.events.iter_mut() {
+            if event.sheet_music_id.as_ref().is_some_and(|id| ids.contains(id)) {
+                event.sheet_music_id = None;
+            }
+        }
+        Ok(())
+    })
+}
+
+// Moves events over to the piece that replaces another, e.g. when duplicates are merged
+pub fn repoint_sheet_music(remapped: &HashMap<String, String>) -> Result<(), String> {
+    update(|calendar| {
+        for event in calendar.events.iter_mut() {
+            if let Some(target) = event.sheet_music_id.as_ref().and_then(|id| remapped.get(id)) {
+                event.sheet_music_id = Some(target.clone());
+            }
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn list_events() -> Result<Vec<PracticeEvent>, String> {
+    read(|calendar| calendar.events.clone())
+}
+
+// Backs the month grid, which asks for the whole visible range including days from neighbouring months;
+// recurring events come back as one entry per occurrence
+#[tauri::command]
+pub fn list_events_in_range(start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<PracticeEvent>, String> {
+    if end < start {
+        return Err("Range end is before its start".to_string());
+    }
+    read(|calendar| calendar.in_range(start, end))
+}
+
+// Same as list_events_in_range with each event's times also given in the zone the user views the calendar in,
+// e.g. a tour date in Tokyo shown at home; without a zone the machine's is used
+#[tauri::command]
+pub fn list_events_for_display(start: DateTime<Utc>, end: DateTime<Utc>, display_zone: Option<String>) -> Result<Vec<DisplayEvent>, String> {
+    if end < start {
+        return Err("Range end is before its start".to_string());
+    }
+    let zone = display_zone.as_deref().map(parse_zone).transpose()?;
+    let events = read(|calendar| calendar.in_range(start, end))?;
+    Ok(events.into_iter().map(|event| event.display(zone.as_ref())).collect())
+}
+
+// Turns a wall-clock time picked in a zone into an instant, saying when DST made that time skipped or doubled
+#[tauri::command]
+pub fn resolve_zoned_time(local: NaiveDateTime, time_zone: String) -> Result<ZonedTime, String> {
+    let tz = parse_zone(&time_zone)?;
+    let (utc, adjustment, later) = match tz.from_local_datetime(&local) {
+        LocalResult::Single(time) => (time.with_timezone(&Utc), None, None),
+        LocalResult::Ambiguous(first, second) => {
+            let (first, second) = (first.with_timezone(&Utc), second.with_timezone(&Utc));
+            (first.min(second), Some("ambiguous".to_string()), Some(first.max(second)))
+        }
+        LocalResult::None => (recurrence::resolve_local(&tz, local), Some("gap".to_string()), None),
+    };
+    Ok(ZonedTime {
+        utc,
+        local: local_string(utc, &tz),
+        adjustment,
+        later,
+    })
+}
+
+#[tauri::command]
+pub fn list_time_zones() -> Vec<String> {
+    chrono_tz::TZ_VARIANTS.iter().map(|tz| tz.name().to_string()).collect()
+}
+
+#[tauri::command]
+pub fn list_events_for_sheet_music(sheet_music_id: String) -> Result<Vec<PracticeEvent>, String> {
+    read(|calendar| {
+        let mut events: Vec<PracticeEvent> = calendar
+            .events
+            .iter()
+            .filter(|event| event.sheet_music_id.as_deref() == Some(sheet_music_id.as_str()))
+            .cloned()
+            .collect();
+        events.sort_by_key(|event| event.start_time);
+        events
+    })
+}
+
+#[tauri::command]
+pub fn get_event(id: String) -> Result<PracticeEvent, String> {
+    read(|calendar| calendar.event(&id).cloned())?.ok_or_else(|| format!("Event {} not found", id))
+}
+
+// Overlaps with other events are reported rather than refused; studios double-book on purpose too
+#[tauri::command]
+pub fn create_event(mut event: PracticeEvent) -> Result<SavedEvent, String> {
+    event.normalize();
+    validate(&event)?;
+    if event.id.is_empty() {
+        event.id = library::new_id();
+    }
+    if event.color.is_none() {
+        event.color = Some(DEFAULT_EVENT_COLOR.to_string());
+    }
+    update(|calendar| {
+        if calendar.event(&event.id).is_some() {
+            return Err(format!("Event {} already exists", event.id));
+        }
+        calendar.events.push(event.clone());
+        let conflicts = scheduling::find_conflicts(calendar, &event);
+        Ok(SavedEvent { event, conflicts })
+    })
+}
+
+#[tauri::command]
+pub fn update_event(mut event: PracticeEvent) -> Result<SavedEvent, String> {
+    if event.recurring_event_id.as_deref() == Some(event.id.as_str()) {
+        return Err("Occurrences of a repeating event are changed through update_occurrence".to_string());
+    }
+    event.normalize();
+    validate(&event)?;
+    update(|calendar| {
+        let local = calendar.event_mut(&event.id)?;
+        *local = event.clone();
+        let conflicts = scheduling::find_conflicts(calendar, &event);
+        Ok(SavedEvent { event, conflicts })
+    })
+}
+
+#[tauri::command]
+pub fn delete_event(id: String) -> Result<(), String> {
+    update(|calendar| calendar.delete_event(&id))
+}
+
+// seriesId is the recurringEventId of the occurrence and originalStart the start the series gave it
+#[tauri::command]
+pub fn update_occurrence(
+    series_id: String,
+    original_start: DateTime<Utc>,
+    scope: EditScope,
+    mut event: PracticeEvent,
+) -> Result<PracticeEvent, String> {
+    event.normalize();
+    validate(&event)?;
+    update(|calendar| calendar.update_occurrence(&series_id, original_start, scope, event))
+}
+
+#[tauri::command]
+pub fn delete_occurrence(series_id: String, original_start: DateTime<Utc>, scope: EditScope) -> Result<(), String> {
+    update(|calendar| calendar.delete_occurrence(&series_id, original_start, scope))
+}
+
+#[tauri::command]
+pub fn set_event_completed(id: String, is_completed: bool) -> Result<PracticeEvent, String> {
+    update(|calendar| {
+        let event = calendar.event_mut(&id)?;
+        event.is_completed = is_completed;
+        Ok(event.clone())
+    })
+}
+
+// An event the way the frontend creates one: no zone, no recurrence; shared by the sync and CalDAV tests
+#[cfg(test)]
+pub fn test_event(title: &str) -> PracticeEvent {
+    serde_json::from_value(serde_json::json!({
+        "title": title,
+        "startTime": "2026-03-04T17:00:00Z",
+        "endTime": "2026-03-04T18:00:00Z",
+        "sheetMusicId": null,
+        "color": null,
+    }))
+    .unwrap()
+}
+
+// Empties the shared calendar without recording the removals for sync
+#[cfg(test)]
+pub fn clear_for_test() {
+    apply_remote(|calendar| {
+        calendar.events.clear();
+        Ok(())
+    })
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use chrono_tz::Europe::Berlin;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().with_timezone(&Utc)
+    }
+
+    fn naive(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M").unwrap()
+    }
+
+    fn series(id: &str, start: &str, end: &str, rrule: &str) -> PracticeEvent {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "title": id,
+            "startTime": start,
+            "endTime": end,
+            "timeZone": "Europe/Berlin",
+            "sheetMusicId": null,
+            "color": null,
+            "rrule": rrule,
+        }))
+        .unwrap()
+    }
+
+    fn starts(events: &[PracticeEvent]) -> Vec<DateTime<Utc>> {
+        events.iter().map(|event| event.start_time).collect()
+    }
+
+    #[test]
+    fn wall_times_in_the_spring_gap_move_forward() {
+        // 02:30 doesn't exist in Berlin on 29 March 2026; clocks jump from 02:00 to 03:00
+        assert_eq!(recurrence::resolve_local(&Berlin, naive("2026-03-29T02:30")), utc("2026-03-29T01:30:00Z"));
+        let resolved = resolve_zoned_time(naive("2026-03-29T02:30"), "Europe/Berlin".to_string()).unwrap();
+        assert_eq!(resolved.utc, utc("2026-03-29T01:30:00Z"));
+        assert_eq!(resolved.local, "2026-03-29T03:30:00+02:00");
+        assert_eq!((resolved.adjustment.as_deref(), resolved.later), (Some("gap"), None));
+    }
+
+    #[test]
+    fn wall_times_in_the_fall_overlap_take_the_first_instant() {
+        // 02:30 happens twice in Berlin on 25 October 2026, first in summer time
+        assert_eq!(recurrence::resolve_local(&Berlin, naive("2026-10-25T02:30")), utc("2026-10-25T00:30:00Z"));
+        let resolved = resolve_zoned_time(naive("2026-10-25T02:30"), "Europe/Berlin".to_string()).unwrap();
+        assert_eq!(resolved.utc, utc("2026-10-25T00:30:00Z"));
+        assert_eq!(resolved.local, "2026-10-25T02:30:00+02:00");
+        assert_eq!((resolved.adjustment.as_deref(), resolved.later), (Some("ambiguous"), Some(utc("2026-10-25T01:30:00Z"))));
+
+        let plain = resolve_zoned_time(naive("2026-10-26T02:30"), "Europe/Berlin".to_string()).unwrap();
+        assert_eq!((plain.utc, plain.adjustment), (utc("2026-10-26T01:30:00Z"), None));
+    }
+
+    #[test]
+    fn zoned_series_expand_through_the_gap_and_the_overlap() {
+        let calendar = Calendar {
+            events: vec![
+                series("spring", "2026-03-28T01:30:00Z", "2026-03-28T02:00:00Z", "FREQ=DAILY;COUNT=3"),
+                series("fall", "2026-10-24T00:30:00Z", "2026-10-24T01:00:00Z", "FREQ=DAILY;COUNT=3"),
+            ],
+        };
+
+        let spring = calendar.in_range(utc("2026-03-01T00:00:00Z"), utc("2026-04-01T00:00:00Z"));
+        assert_eq!(starts(&spring), vec![utc("2026-03-28T01:30:00Z"), utc("2026-03-29T01:30:00Z"), utc("2026-03-30T00:30:00Z")]);
+        // Occurrences keep the series' length, even the one the gap moved
+        assert!(spring.iter().all(|event| event.end_time - event.start_time == Duration::minutes(30)));
+        assert_eq!(spring[1].original_start, Some(utc("2026-03-29T01:30:00Z")));
+
+        let fall = calendar.in_range(utc("2026-10-01T00:00:00Z"), utc("2026-11-01T00:00:00Z"));
+        assert_eq!(starts(&fall), vec![utc("2026-10-24T00:30:00Z"), utc("2026-10-25T00:30:00Z"), utc("2026-10-26T01:30:00Z")]);
+    }
+
+    #[test]
+    fn all_day_series_expand_up_to_the_latest_instant() {
+        let mut event = series("recital", "2026-06-20T00:00:00Z", "2026-06-21T00:00:00Z", "FREQ=YEARLY;COUNT=3");
+        event.all_day = true;
+        let calendar = Calendar { events: vec![event] };
+        let occurrences = calendar.in_range(utc("2026-01-01T00:00:00Z"), DateTime::<Utc>::MAX_UTC);
+        let dates: Vec<NaiveDate> = occurrences.iter().map(|event| event.start_time.date_naive()).collect();
+        assert_eq!(dates.len(), 3);
+        assert_eq!(dates[2], NaiveDate::from_ymd_opt(2028, 6, 20).unwrap());
+    }
+
+    #[test]
+    fn deleting_an_edited_occurrence_leaves_a_gap_in_the_series() {
+        let mut calendar = Calendar {
+            events: vec![series("lesson", "2026-05-04T15:00:00Z", "2026-05-04T16:00:00Z", "FREQ=WEEKLY;COUNT=3")],
+        };
+        let mut moved = calendar.event("lesson").unwrap().instance(utc("2026-05-11T15:00:00Z"));
+        moved.start_time = utc("2026-05-12T15:00:00Z");
+        moved.end_time = utc("2026-05-12T16:00:00Z");
+        let moved = calendar.update_occurrence("lesson", utc("2026-05-11T15:00:00Z"), EditScope::This, moved).unwrap();
+
+        calendar.delete_event(&moved.id).unwrap();
+        let occurrences = calendar.in_range(utc("2026-05-01T00:00:00Z"), utc("2026-06-01T00:00:00Z"));
+        assert_eq!(starts(&occurrences), vec![utc("2026-05-04T15:00:00Z"), utc("2026-05-18T15:00:00Z")]);
+        assert_eq!(calendar.event("lesson").unwrap().exdates, vec![utc("2026-05-11T15:00:00Z")]);
+    }
+}
@@ -0,0 +1,347 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::library::{self, SheetMusicItem};
+use crate::{calendar, goals, metadata, practice, recordings, store, watcher};
+
+// Minimum normalized title similarity for two pieces to count as likely duplicates
+const TITLE_THRESHOLD: f64 = 0.85;
+// Without page counts to compare, titles have to be close to identical
+const TITLE_THRESHOLD_WITHOUT_PAGES: f64 = 0.95;
+const COMPOSER_THRESHOLD: f64 = 0.8;
+
+// Words scanners and file managers add to names that say nothing about the piece
+const NOISE_WORDS: [&str; 7] = ["copy", "scan", "scanned", "final", "new", "pdf", "draft"];
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateCluster {
+    pub kind: String,
+    pub score: f64,
+    pub items: Vec<SheetMusicItem>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeResult {
+    pub kept: SheetMusicItem,
+    pub removed: Vec<String>,
+    pub deleted_files: Vec<String>,
+    // Maps each removed ID to the kept one, for references the backend doesn't know about
+    pub remapped: HashMap<String, String>,
+}
+
+struct FileFingerprint {
+    len: u64,
+    modified: Option<SystemTime>,
+    hash: String,
+    page_count: usize,
+}
+
+struct Fingerprint {
+    hash: Option<String>,
+    page_count: Option<usize>,
+    title: String,
+    composer: String,
+}
+
+// Hashing large scores is slow, so results are reused until the file changes
+static FINGERPRINTS: Lazy<Mutex<HashMap<String, FileFingerprint>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn file_fingerprint(path: &str) -> Option<(String, usize)> {
+    let meta = fs::metadata(path).ok().filter(|meta| meta.is_file())?;
+    let modified = meta.modified().ok();
+
+    let mut cache = FINGERPRINTS.lock().unwrap();
+    if let Some(cached) = cache.get(path) {
+        if cached.len == meta.len() && cached.modified == modified {
+            return Some((cached.hash.clone(), cached.page_count));
+        }
+    }
+
+    let data = fs::read(path).ok()?;
+    let hash = format!("{:x}", Sha256::digest(&data));
+    let page_count = metadata::count_pages(&data);
+    cache.insert(
+        path.to_string(),
+        FileFingerprint {
+            len: meta.len(),
+            modified,
+            hash: hash.clone(),
+            page_count,
+        },
+    );
+    Some((hash, page_count))
+}
+
+pub fn content_hash(path: &str) -> Option<String> {
+    file_fingerprint(path).map(|(hash, _)| hash)
+}
+
+// Last hash seen for a path, still there after the file itself is gone
+pub fn cached_hash(path: &str) -> Option<String> {
+    FINGERPRINTS.lock().unwrap().get(path).map(|cached| cached.hash.clone())
+}
+
+fn fold_char(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'č' => 'c',
+        'ď' => 'd',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => 'e',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'ł' | 'ľ' => 'l',
+        'ñ' | 'ń' | 'ň' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ő' => 'o',
+        'ř' => 'r',
+        'ś' | 'š' | 'ş' => 's',
+        'ť' => 't',
+        'ù' | 'ú' | 'û' | 'ü' | 'ů' | 'ű' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        _ => c,
+    }
+}
+
+// Lowercases, folds accents and drops punctuation and noise words, so "Dvořák_Humoresque (copy)" matches "dvorak humoresque"
+pub fn normalize(text: &str) -> String {
+    let cleaned: String = text
+        .to_lowercase()
+        .chars()
+        .map(fold_char)
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    cleaned
+        .split_whitespace()
+        .filter(|word| !NOISE_WORDS.contains(word))
+        .filter(|word| !(word.len() > 1 && word.starts_with('v') && word[1..].chars().all(|c| c.is_ascii_digit())))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+// 1.0 for identical strings, falling towards 0.0 as the edit distance grows
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(&a, &b) as f64 / longest as f64
+}
+
+fn likely_score(a: &Fingerprint, b: &Fingerprint) -> Option<f64> {
+    if a.title.is_empty() || b.title.is_empty() {
+        return None;
+    }
+    let title_score = similarity(&a.title, &b.title);
+    let threshold = match (a.page_count, b.page_count) {
+        (Some(pages_a), Some(pages_b)) if pages_a != pages_b => return None,
+        (Some(_), Some(_)) => TITLE_THRESHOLD,
+        _ => TITLE_THRESHOLD_WITHOUT_PAGES,
+    };
+    if title_score < threshold {
+        return None;
+    }
+
+    // An unknown composer on either side shouldn't keep two scans apart
+    if a.composer.is_empty() || b.composer.is_empty() {
+        return Some(title_score);
+    }
+    let composer_score = similarity(&a.composer, &b.composer);
+    if composer_score < COMPOSER_THRESHOLD {
+        return None;
+    }
+    Some((title_score + composer_score) / 2.0)
+}
+
+pub fn detect(items: &[SheetMusicItem]) -> Vec<DuplicateCluster> {
+    let fingerprints: Vec<Fingerprint> = items
+        .iter()
+        .map(|item| {
+            let file = file_fingerprint(&item.pdf_path);
+            Fingerprint {
+                hash: file.as_ref().map(|(hash, _)| hash.clone()),
+                page_count: file.map(|(_, pages)| pages).filter(|pages| *pages > 0),
+                title: normalize(&item.title),
+                composer: normalize(&item.composer),
+            }
+        })
+        .collect();
+
+    let mut clusters = Vec::new();
+
+    let mut by_hash: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, fingerprint) in fingerprints.iter().enumerate() {
+        if let Some(hash) = &fingerprint.hash {
+            by_hash.entry(hash.as_str()).or_default().push(index);
+        }
+    }
+    let mut exact_groups: Vec<Vec<usize>> = by_hash.into_values().filter(|group| group.len() > 1).collect();
+    exact_groups.sort();
+
+    // Pieces in an exact group are already offered for merging, so likely matching leaves them out
+    let mut candidates: Vec<usize> = (0..items.len()).collect();
+    for group in &exact_groups {
+        candidates.retain(|index| !group.contains(index));
+        clusters.push(DuplicateCluster {
+            kind: "exact".to_string(),
+            score: 1.0,
+            items: group.iter().map(|index| items[*index].clone()).collect(),
+        });
+    }
+
+    let mut pair_scores: HashMap<(usize, usize), f64> = HashMap::new();
+    for (position, i) in candidates.iter().enumerate() {
+        for j in &candidates[position + 1..] {
+            if let Some(score) = likely_score(&fingerprints[*i], &fingerprints[*j]) {
+                pair_scores.insert((*i, *j), score);
+            }
+        }
+    }
+
+    // Complete linkage: best pairs first, and two groups only join when every piece in one is similar to
+    // every piece in the other, so A~B and B~C don't pull a dissimilar A and C together
+    let mut pairs: Vec<((usize, usize), f64)> = pair_scores.iter().map(|(pair, score)| (*pair, *score)).collect();
+    pairs.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    let mut group_of: Vec<usize> = (0..items.len()).collect();
+    let mut groups: HashMap<usize, Vec<usize>> = candidates.iter().map(|index| (*index, vec![*index])).collect();
+    let similar = |a: usize, b: usize| pair_scores.contains_key(&(a.min(b), a.max(b)));
+    for ((i, j), _) in pairs {
+        let (group_i, group_j) = (group_of[i], group_of[j]);
+        if group_i == group_j || !groups[&group_i].iter().all(|a| groups[&group_j].iter().all(|b| similar(*a, *b))) {
+            continue;
+        }
+        let moved = groups.remove(&group_j).unwrap();
+        for index in &moved {
+            group_of[*index] = group_i;
+        }
+        groups.get_mut(&group_i).unwrap().extend(moved);
+    }
+
+    let mut likely: Vec<Vec<usize>> = groups.into_values().filter(|group| group.len() > 1).collect();
+    for group in &mut likely {
+        group.sort();
+    }
+    likely.sort();
+    for group in likely {
+        let mut score: f64 = 1.0;
+        for (position, a) in group.iter().enumerate() {
+            for b in &group[position + 1..] {
+                score = score.min(pair_scores[&(*a, *b)]);
+            }
+        }
+        clusters.push(DuplicateCluster {
+            kind: "likely".to_string(),
+            score,
+            items: group.iter().map(|index| items[*index].clone()).collect(),
+        });
+    }
+
+    clusters
+}
+
+#[tauri::command]
+pub async fn find_duplicates() -> Result<Vec<DuplicateCluster>, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let items = library::read(|library| library.items.clone())?;
+        let clusters = detect(&items);
+        println!("Found {} duplicate clusters among {} pieces", clusters.len(), items.len());
+        Ok(clusters)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// Keeps one piece, moves tags, collections and setlist entries over from the duplicates and removes them
+#[tauri::command]
+pub fn merge_duplicates(keep_id: String, duplicate_ids: Vec<String>) -> Result<MergeResult, String> {
+    if duplicate_ids.contains(&keep_id) {
+        return Err("The piece to keep can't also be merged away".to_string());
+    }
+
+    let mut duplicate_ids = duplicate_ids;
+    duplicate_ids.sort();
+    duplicate_ids.dedup();
+
+    // Every ID is checked before anything changes, so a bad one can't leave events pointing at the kept piece
+    let missing = library::read(|library| {
+        std::iter::once(&keep_id)
+            .chain(&duplicate_ids)
+            .find(|id| library.item(id).is_none())
+            .cloned()
+    })?;
+    if let Some(id) = missing {
+        return Err(format!("Sheet music {} not found", id));
+    }
+
+    // Events, recordings, practice sessions and goals move over first, otherwise removing the duplicates would unlink or delete them
+    let remapped: HashMap<String, String> = duplicate_ids.iter().map(|id| (id.clone(), keep_id.clone())).collect();
+    calendar::repoint_sheet_music(&remapped)?;
+    recordings::repoint_sheet_music(&remapped)?;
+    practice::repoint_sheet_music(&remapped)?;
+    goals::repoint_sheet_music(&remapped)?;
+
+    let (kept, removed_items) = library::update(|library| {
+        if library.item(&keep_id).is_none() {
+            return Err(format!("Sheet music {} not found", keep_id));
+        }
+        let mut removed_items = Vec::new();
+        for duplicate_id in &duplicate_ids {
+            library.repoint_item(duplicate_id, &keep_id);
+            let removed = library
+                .remove_item(duplicate_id)
+                .ok_or_else(|| format!("Sheet music {} not found", duplicate_id))?;
+            removed_items.push(removed);
+        }
+        let kept = library.items.iter_mut().find(|item| item.id == keep_id).unwrap();
+        kept.is_favorite |= removed_items.iter().any(|item| item.is_favorite);
+        Ok((kept.clone(), removed_items))
+    })?;
+
+    // Copies we manage are deleted; files in watched folders are left alone but no longer imported
+    let managed_dir = store::sub_dir("pdfs")?;
+    let mut deleted_files = Vec::new();
+    for item in &removed_items {
+        if item.pdf_path == kept.pdf_path || !Path::new(&item.pdf_path).is_file() {
+            continue;
+        }
+        if Path::new(&item.pdf_path).starts_with(&managed_dir) {
+            match fs::remove_file(&item.pdf_path) {
+                Ok(()) => deleted_files.push(item.pdf_path.clone()),
+                Err(e) => println!("Failed to delete {}: {}", item.pdf_path, e),
+            }
+        } else if let Err(e) = watcher::ignore_file(&item.pdf_path) {
+            println!("Failed to ignore {}: {}", item.pdf_path, e);
+        }
+    }
+
+    let removed: Vec<String> = removed_items.iter().map(|item| item.id.clone()).collect();
+    println!("Merged {} duplicates into {}", removed.len(), keep_id);
+    Ok(MergeResult {
+        kept,
+        removed,
+        deleted_files,
+        remapped,
+    })
+}
@@ -0,0 +1,210 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use crate::calendar::{self, EventType};
+use crate::{library, stats, store};
+
+const GOALS_DOCUMENT: &str = "practice_goals";
+// Performances further out than this don't change what to practise today
+pub const PERFORMANCE_HORIZON_DAYS: i64 = 90;
+
+// A piece being worked on: how much time it should get and, optionally, when it has to be ready
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PieceGoal {
+    pub sheet_music_id: String,
+    #[serde(default)]
+    pub minutes_per_week: Option<u32>,
+    #[serde(default)]
+    pub deadline: Option<NaiveDate>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PracticeGoals {
+    pub daily_minutes: Option<u32>,
+    pub weekly_minutes: Option<u32>,
+    pub pieces: Vec<PieceGoal>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DeadlineSource {
+    Goal,
+    Performance,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Deadline {
+    pub sheet_music_id: String,
+    pub date: NaiveDate,
+    pub source: DeadlineSource,
+    // The concert or recital for deadlines taken from the calendar
+    pub event_id: Option<String>,
+    pub title: Option<String>,
+}
+
+static GOALS: Lazy<Mutex<Option<PracticeGoals>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn current() -> Result<PracticeGoals, String> {
+    let mut guard = GOALS.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(GOALS_DOCUMENT)?);
+    }
+    Ok(guard.as_ref().unwrap().clone())
+}
+
+// For cleanups after library changes that usually find nothing to do; saves only when the closure reports a change
+fn update_goals_if_changed(f: impl FnOnce(&mut PracticeGoals) -> bool) -> Result<(), String> {
+    let mut guard = GOALS.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(GOALS_DOCUMENT)?);
+    }
+    let goals = guard.as_mut().unwrap();
+    if f(goals) {
+        store::save(GOALS_DOCUMENT, goals)?;
+    }
+    Ok(())
+}
+
+// Goals of pieces that left the library go with them
+pub fn unlink_sheet_music(removed: &[String]) -> Result<(), String> {
+    if removed.is_empty() {
+        return Ok(());
+    }
+    update_goals_if_changed(|goals| {
+        let before = goals.pieces.len();
+        goals.pieces.retain(|goal| !removed.contains(&goal.sheet_music_id));
+        goals.pieces.len() != before
+    })
+}
+
+// Merged duplicates hand their goals to the kept piece, unless it already has one of its own
+pub fn repoint_sheet_music(remapped: &HashMap<String, String>) -> Result<(), String> {
+    update_goals_if_changed(|goals| {
+        let mut changed = false;
+        let mut targeted: HashSet<String> = goals
+            .pieces
+            .iter()
+            .filter(|goal| !remapped.contains_key(&goal.sheet_music_id))
+            .map(|goal| goal.sheet_music_id.clone())
+            .collect();
+        goals.pieces.retain_mut(|goal| match remapped.get(&goal.sheet_music_id) {
+            Some(target) => {
+                changed = true;
+                if !targeted.insert(target.clone()) {
+                    return false;
+                }
+                goal.sheet_music_id = target.clone();
+                true
+            }
+            None => true,
+        });
+        changed
+    })
+}
+
+// Upcoming deadlines from piece goals and from concerts and recitals linked to a piece, soonest first
+pub fn deadlines(goals: &PracticeGoals, from: DateTime<Utc>) -> Result<Vec<Deadline>, String> {
+    let today = stats::local_date(from);
+    let mut deadlines: Vec<Deadline> = goals
+        .pieces
+        .iter()
+        .filter_map(|goal| goal.deadline.filter(|date| *date >= today).map(|date| (goal, date)))
+        .map(|(goal, date)| Deadline {
+            sheet_music_id: goal.sheet_music_id.clone(),
+            date,
+            source: DeadlineSource::Goal,
+            event_id: None,
+            title: None,
+        })
+        .collect();
+    let performances = calendar::read(|calendar| calendar.in_range(from, from + Duration::days(PERFORMANCE_HORIZON_DAYS)))?;
+    for event in performances {
+        if !matches!(event.event_type, EventType::Concert | EventType::Recital) || event.start_time < from {
+            continue;
+        }
+        let date = stats::local_date(event.span().0);
+        if let Some(sheet_music_id) = event.sheet_music_id {
+            deadlines.push(Deadline {
+                sheet_music_id,
+                date,
+                source: DeadlineSource::Performance,
+                event_id: Some(event.recurring_event_id.unwrap_or(event.id)),
+                title: Some(event.title),
+            });
+        }
+    }
+    deadlines.sort_by_key(|deadline| deadline.date);
+    Ok(deadlines)
+}
+
+#[tauri::command]
+pub fn get_practice_goals() -> Result<PracticeGoals, String> {
+    current()
+}
+
+#[tauri::command]
+pub fn set_practice_goals(goals: PracticeGoals) -> Result<PracticeGoals, String> {
+    if goals.daily_minutes.is_some_and(|minutes| minutes == 0 || minutes > 24 * 60) {
+        return Err("A daily goal has to be between 1 minute and 24 hours".to_string());
+    }
+    if goals.weekly_minutes.is_some_and(|minutes| minutes == 0 || minutes > 7 * 24 * 60) {
+        return Err("A weekly goal has to be between 1 minute and 7 days".to_string());
+    }
+    let saved: HashSet<String> = current()?.pieces.into_iter().map(|goal| goal.sheet_music_id).collect();
+    let mut seen = HashSet::new();
+    for goal in &goals.pieces {
+        if !seen.insert(goal.sheet_music_id.as_str()) {
+            return Err(format!("Sheet music {} has more than one goal", goal.sheet_music_id));
+        }
+        if goal.minutes_per_week == Some(0) {
+            return Err("A piece's weekly goal has to be at least a minute".to_string());
+        }
+        // Only newly added pieces are checked, so a goal left behind by an older version can't block saving
+        if !saved.contains(&goal.sheet_music_id) {
+            library::read(|library| library.require_item(&goal.sheet_music_id))??;
+        }
+    }
+    store::save(GOALS_DOCUMENT, &goals)?;
+    *GOALS.lock().unwrap() = Some(goals.clone());
+    Ok(goals)
+}
+
+#[tauri::command]
+pub fn list_practice_deadlines() -> Result<Vec<Deadline>, String> {
+    deadlines(&current()?, Utc::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn goal(sheet_music_id: &str, minutes_per_week: u32) -> PieceGoal {
+        PieceGoal { sheet_music_id: sheet_music_id.to_string(), minutes_per_week: Some(minutes_per_week), deadline: None }
+    }
+
+    #[test]
+    fn goals_follow_merged_and_removed_pieces() {
+        let _lock = store::lock_for_test();
+        *GOALS.lock().unwrap() = Some(PracticeGoals {
+            pieces: vec![goal("kept", 60), goal("copy", 30), goal("other-copy", 45), goal("gone", 20)],
+            ..Default::default()
+        });
+
+        let remapped = HashMap::from([
+            ("copy".to_string(), "kept".to_string()),
+            ("other-copy".to_string(), "merged".to_string()),
+        ]);
+        repoint_sheet_music(&remapped).unwrap();
+        unlink_sheet_music(&["gone".to_string()]).unwrap();
+
+        // The kept piece's own goal wins over the duplicate's
+        assert_eq!(current().unwrap().pieces, vec![goal("kept", 60), goal("merged", 45)]);
+        assert_eq!(store::load::<PracticeGoals>(GOALS_DOCUMENT).unwrap().pieces, vec![goal("kept", 60), goal("merged", 45)]);
+    }
+}
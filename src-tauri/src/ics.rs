@@ -0,0 +1,808 @@
+use chrono::{DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveTime, Offset, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+
+use crate::calendar::{self, EventType, EventZone, PracticeEvent};
+use crate::library;
+use crate::recurrence::{self, RecurrenceRule, Until};
+
+const PRODUCT_ID: &str = "-//Partitura//Partitura Desktop//EN";
+// Years of VTIMEZONE transitions written past the last stored time of a zone with repeating events
+const VTIMEZONE_SERIES_YEARS: i32 = 10;
+// RFC 5545 limits content lines to 75 octets before they have to be folded
+const MAX_LINE_OCTETS: usize = 75;
+
+// Fields iCalendar has no standard property for, so they survive a round trip through our own exports
+const X_COMPLETED: &str = "X-PARTITURA-COMPLETED";
+const X_SHEET_MUSIC_ID: &str = "X-PARTITURA-SHEET-MUSIC-ID";
+const X_COLOR: &str = "X-PARTITURA-COLOR";
+
+const EVENT_TYPES: [(EventType, &str); 5] = [
+    (EventType::Practice, "Practice"),
+    (EventType::Concert, "Concert"),
+    (EventType::Lesson, "Lesson"),
+    (EventType::Rehearsal, "Rehearsal"),
+    (EventType::Recital, "Recital"),
+];
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IcsImportReport {
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IcsExportSummary {
+    pub path: String,
+    pub events: usize,
+}
+
+struct Property {
+    name: String,
+    params: HashMap<String, String>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+}
+
+pub fn event_type_name(event_type: EventType) -> &'static str {
+    EVENT_TYPES.iter().find(|(kind, _)| *kind == event_type).map(|(_, name)| *name).unwrap()
+}
+
+// Also accepts plurals like "Lessons", which some calendar apps use for category names
+pub fn event_type_from_category(category: &str) -> Option<EventType> {
+    let category = category.trim().to_lowercase();
+    let singular = category.strip_suffix('s').unwrap_or(&category);
+    EVENT_TYPES
+        .iter()
+        .find(|(_, name)| name.to_lowercase() == category || name.to_lowercase() == singular)
+        .map(|(kind, _)| *kind)
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+// Splits on commas that aren't escaped, for multi-valued properties like CATEGORIES
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut escaped = false;
+    for c in value.chars() {
+        if escaped {
+            current.push('\\');
+            current.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == ',' {
+            items.push(unescape(&current));
+            current.clear();
+        } else {
+            current.push(c);
+        }
+    }
+    items.push(unescape(&current));
+    items
+}
+
+// Joins folded lines back together; continuation lines start with a space or tab
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn parse_line(line: &str) -> Option<Property> {
+    // The value starts at the first colon outside a quoted parameter value
+    let mut in_quotes = false;
+    let split = line.char_indices().find(|(_, c)| {
+        if *c == '"' {
+            in_quotes = !in_quotes;
+        }
+        *c == ':' && !in_quotes
+    })?;
+    let (head, value) = (&line[..split.0], &line[split.0 + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.trim().to_ascii_uppercase(), value.trim_matches('"').to_string()))
+        .collect();
+    Some(Property {
+        name,
+        params,
+        value: value.to_string(),
+    })
+}
+
+struct ParsedTime {
+    time: DateTime<Utc>,
+    all_day: bool,
+    zone: Option<String>,
+}
+
+// DATE values float, so they're kept as midnight UTC like all-day events; TZID times are read in that zone.
+// Floating times and zone names chrono-tz doesn't know (e.g. Windows ones) fall back to this machine's zone
+fn parse_time(property: &Property) -> Result<ParsedTime, String> {
+    let value = property.value.trim();
+    let is_date = property.param("VALUE") == Some("DATE") || (value.len() == 8 && !value.contains('T'));
+    if is_date {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|_| format!("Invalid date {}", value))?;
+        return Ok(ParsedTime {
+            time: date.and_time(NaiveTime::MIN).and_utc(),
+            all_day: true,
+            zone: None,
+        });
+    }
+    let (time, zone) = match recurrence::parse_ical_datetime(value)? {
+        Until::Utc(time) => (time, None),
+        Until::Floating(naive) => match property.param("TZID").and_then(|tzid| calendar::parse_zone(tzid.trim_start_matches('/')).ok()) {
+            Some(tz) => (recurrence::resolve_local(&tz, naive), Some(tz.name().to_string())),
+            None => (recurrence::resolve_local(&Local, naive), None),
+        },
+    };
+    Ok(ParsedTime { time, all_day: false, zone })
+}
+
+// Supports the week/day/hour/minute/second forms, e.g. PT1H30M or P1D
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("Invalid duration {}", value);
+    let (negative, rest) = match value.trim().strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.trim().trim_start_matches('+')),
+    };
+    let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+    let mut seconds = 0i64;
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' => number.push(c),
+            _ => {
+                let amount: i64 = number.parse().map_err(|_| invalid())?;
+                number.clear();
+                seconds += amount
+                    * match (c, in_time) {
+                        ('W', false) => 7 * 86400,
+                        ('D', false) => 86400,
+                        ('H', true) => 3600,
+                        ('M', true) => 60,
+                        ('S', true) => 1,
+                        _ => return Err(invalid()),
+                    };
+            }
+        }
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Duration::seconds(if negative { -seconds } else { seconds }))
+}
+
+fn parse_event(properties: &[Property], default_type: EventType) -> Result<(PracticeEvent, Option<String>, bool), String> {
+    let get = |name: &str| properties.iter().find(|property| property.name == name);
+    let uid = get("UID").map(|property| property.value.trim().to_string()).filter(|uid| !uid.is_empty());
+
+    let start_property = get("DTSTART").ok_or_else(|| "Event has no DTSTART".to_string())?;
+    let start = parse_time(start_property)?;
+    let (start_time, all_day) = (start.time, start.all_day);
+    let end_time = match (get("DTEND"), get("DURATION")) {
+        (Some(end), _) => parse_time(end)?.time,
+        (None, Some(duration)) => start_time + parse_duration(&duration.value)?,
+        // RFC 5545: a date-only event without an end lasts the day, a timed one is an instant
+        (None, None) if all_day => start_time + Duration::days(1),
+        (None, None) => start_time,
+    };
+
+    let event_type = properties
+        .iter()
+        .filter(|property| property.name == "CATEGORIES")
+        .flat_map(|property| split_list(&property.value))
+        .find_map(|category| event_type_from_category(&category))
+        .unwrap_or(default_type);
+
+    let mut exdates = Vec::new();
+    for property in properties.iter().filter(|property| property.name == "EXDATE") {
+        for value in property.value.split(',') {
+            let single = Property {
+                name: property.name.clone(),
+                params: property.params.clone(),
+                value: value.to_string(),
+            };
+            exdates.push(parse_time(&single)?.time);
+        }
+    }
+
+    let rrule = get("RRULE").map(|property| property.value.trim().to_string());
+    if let Some(rrule) = &rrule {
+        rrule.parse::<RecurrenceRule>()?;
+    }
+    let original_start = get("RECURRENCE-ID").map(parse_time).transpose()?.map(|parsed| parsed.time);
+    let cancelled = get("STATUS").map(|property| property.value.trim().eq_ignore_ascii_case("CANCELLED")).unwrap_or(false);
+
+    let event = PracticeEvent {
+        id: if original_start.is_some() { library::new_id() } else { uid.clone().unwrap_or_else(library::new_id) },
+        event_type,
+        title: get("SUMMARY").map(|property| unescape(&property.value)).unwrap_or_default(),
+        description: get("DESCRIPTION").map(|property| unescape(&property.value)).unwrap_or_default(),
+        start_time,
+        end_time: end_time.max(start_time),
+        time_zone: start.zone,
+        all_day,
+        is_completed: get(X_COMPLETED).map(|property| property.value.trim().eq_ignore_ascii_case("TRUE")).unwrap_or(false),
+        sheet_music_id: get(X_SHEET_MUSIC_ID).map(|property| property.value.trim().to_string()),
+        color: get(X_COLOR).map(|property| property.value.trim().to_string()),
+        rrule,
+        exdates,
+        recurring_event_id: original_start.and(uid.clone()),
+        original_start,
+    };
+    Ok((event, uid, cancelled))
+}
+
+// Returns the events in the file plus a warning for each one that couldn't be read
+pub fn parse_ics(text: &str, default_type: EventType) -> (Vec<PracticeEvent>, Vec<String>) {
+    let mut events: Vec<PracticeEvent> = Vec::new();
+    let mut cancelled: Vec<(String, DateTime<Utc>)> = Vec::new();
+    let mut warnings = Vec::new();
+    let mut current: Option<Vec<Property>> = None;
+    // Alarms and other components nested in an event have their own DTSTART etc.
+    let mut nested = 0;
+
+    for line in unfold(text) {
+        let property = match parse_line(&line) {
+            Some(property) => property,
+            None => continue,
+        };
+        let value = property.value.trim().to_ascii_uppercase();
+        match (property.name.as_str(), value.as_str()) {
+            ("BEGIN", "VEVENT") if current.is_none() => current = Some(Vec::new()),
+            ("BEGIN", _) if current.is_some() => nested += 1,
+            ("END", "VEVENT") if nested == 0 => {
+                let properties = match current.take() {
+                    Some(properties) => properties,
+                    None => continue,
+                };
+                match parse_event(&properties, default_type) {
+                    Ok((mut event, _, true)) if event.recurring_event_id.is_some() => {
+                        cancelled.push((event.recurring_event_id.take().unwrap(), event.original_start.unwrap()));
+                    }
+                    Ok((_, uid, true)) => warnings.push(format!("Skipped cancelled event {}", uid.unwrap_or_default())),
+                    Ok((mut event, _, false)) => {
+                        if event.title.trim().is_empty() {
+                            event.title = event_type_name(event.event_type).to_string();
+                        }
+                        events.push(event);
+                    }
+                    Err(e) => warnings.push(e),
+                }
+            }
+            ("END", _) if current.is_some() => nested -= 1,
+            _ if nested == 0 => {
+                if let Some(properties) = current.as_mut() {
+                    properties.push(property);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Cancelled occurrences are exceptions of their series rather than events of their own
+    for (series_id, original_start) in cancelled {
+        match events.iter_mut().find(|event| event.id == series_id && event.rrule.is_some()) {
+            Some(series) => series.exdates.push(original_start),
+            None => warnings.push(format!("Cancelled occurrence of unknown event {}", series_id)),
+        }
+    }
+    (events, warnings)
+}
+
+// Folds at 75 octets without splitting a UTF-8 character
+fn push_line(output: &mut String, line: &str) {
+    let mut remaining = line;
+    let mut limit = MAX_LINE_OCTETS;
+    while remaining.len() > limit {
+        let mut cut = limit;
+        while !remaining.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        output.push_str(&remaining[..cut]);
+        output.push_str("\r\n ");
+        remaining = &remaining[cut..];
+        // The leading space of a continuation line counts towards its length
+        limit = MAX_LINE_OCTETS - 1;
+    }
+    output.push_str(remaining);
+    output.push_str("\r\n");
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+// Zoned events keep their TZID, described by a VTIMEZONE, so other calendars repeat them on the right wall clock;
+// all-day ones are plain dates. Events without a zone repeat on this machine's wall clock, so they're written as
+// floating local times, which other calendars read in their own zone the same way
+fn time_params(event: &PracticeEvent) -> String {
+    match event.zone() {
+        EventZone::Floating => ";VALUE=DATE".to_string(),
+        EventZone::Named(tz) => format!(";TZID={}", tz.name()),
+        EventZone::Local => String::new(),
+    }
+}
+
+fn time_value(event: &PracticeEvent, time: DateTime<Utc>) -> String {
+    match event.zone() {
+        EventZone::Floating => time.format("%Y%m%d").to_string(),
+        EventZone::Named(tz) => time.with_timezone(&tz).format("%Y%m%dT%H%M%S").to_string(),
+        EventZone::Local => time.with_timezone(&Local).format("%Y%m%dT%H%M%S").to_string(),
+    }
+}
+
+fn format_offset(offset: FixedOffset) -> String {
+    let seconds = offset.local_minus_utc();
+    let (sign, seconds) = if seconds < 0 { ('-', -seconds) } else { ('+', seconds) };
+    let mut text = format!("{}{:02}{:02}", sign, seconds / 3600, seconds / 60 % 60);
+    if seconds % 60 != 0 {
+        text.push_str(&format!("{:02}", seconds % 60));
+    }
+    text
+}
+
+// Instants in [from, until) where the zone's offset changes, found day by day and then to the minute
+fn transitions(tz: Tz, from: DateTime<Utc>, until: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let offset_at = |time: DateTime<Utc>| tz.offset_from_utc_datetime(&time.naive_utc()).fix();
+    let mut found = Vec::new();
+    let mut day = from;
+    while day < until {
+        let next = day + Duration::days(1);
+        if offset_at(day) != offset_at(next) {
+            let (mut low, mut high) = (0, 24 * 60);
+            while high - low > 1 {
+                let middle = (low + high) / 2;
+                if offset_at(day + Duration::minutes(middle)) == offset_at(day) {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            found.push(day + Duration::minutes(high));
+        }
+        day = next;
+    }
+    found
+}
+
+fn push_observance(output: &mut String, tz: Tz, onset: DateTime<Utc>, from: FixedOffset) {
+    let offset = tz.offset_from_utc_datetime(&onset.naive_utc());
+    let kind = if offset.dst_offset().is_zero() { "STANDARD" } else { "DAYLIGHT" };
+    push_line(output, &format!("BEGIN:{}", kind));
+    // The onset is written in the wall clock that was in effect before it
+    push_line(output, &format!("DTSTART:{}", (onset.naive_utc() + Duration::seconds(from.local_minus_utc() as i64)).format("%Y%m%dT%H%M%S")));
+    push_line(output, &format!("TZOFFSETFROM:{}", format_offset(from)));
+    push_line(output, &format!("TZOFFSETTO:{}", format_offset(offset.fix())));
+    if let Some(abbreviation) = offset.abbreviation() {
+        push_line(output, &format!("TZNAME:{}", escape(abbreviation)));
+    }
+    push_line(output, &format!("END:{}", kind));
+}
+
+// Describes every transition between the first and last times written in the zone, so clients that don't know the
+// TZID still resolve the wall clock; series get years of transitions beyond that, since they keep repeating
+fn push_vtimezone(output: &mut String, tz: Tz, events: &[&PracticeEvent]) {
+    let times = events
+        .iter()
+        .flat_map(|event| [event.start_time, event.end_time].into_iter().chain(event.original_start).chain(event.exdates.iter().copied()));
+    let (first, last) = match (times.clone().min(), times.max()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return,
+    };
+    let years_ahead = if events.iter().any(|event| event.rrule.is_some()) { VTIMEZONE_SERIES_YEARS } else { 1 };
+    let year_start = |year: i32| NaiveDate::from_ymd_opt(year, 1, 1).map(|date| date.and_time(NaiveTime::MIN).and_utc());
+    let (from, until) = match (year_start(first.year()), year_start(last.year() + years_ahead)) {
+        (Some(from), Some(until)) => (from, until),
+        _ => return,
+    };
+
+    push_line(output, "BEGIN:VTIMEZONE");
+    push_line(output, &format!("TZID:{}", tz.name()));
+    let mut offset = tz.offset_from_utc_datetime(&from.naive_utc()).fix();
+    push_observance(output, tz, from, offset);
+    for transition in transitions(tz, from, until) {
+        push_observance(output, tz, transition, offset);
+        offset = tz.offset_from_utc_datetime(&transition.naive_utc()).fix();
+    }
+    push_line(output, "END:VTIMEZONE");
+}
+
+// Without a calendar name this writes a single CalDAV object resource, which mustn't carry METHOD
+pub fn write_ics(events: &[PracticeEvent], calendar_name: Option<&str>) -> String {
+    let now = format_time(Utc::now());
+    let mut output = String::new();
+    push_line(&mut output, "BEGIN:VCALENDAR");
+    push_line(&mut output, "VERSION:2.0");
+    push_line(&mut output, &format!("PRODID:{}", PRODUCT_ID));
+    push_line(&mut output, "CALSCALE:GREGORIAN");
+    if let Some(calendar_name) = calendar_name {
+        push_line(&mut output, "METHOD:PUBLISH");
+        push_line(&mut output, &format!("X-WR-CALNAME:{}", escape(calendar_name)));
+    }
+
+    let mut zones: BTreeMap<&str, (Tz, Vec<&PracticeEvent>)> = BTreeMap::new();
+    for event in events {
+        if let EventZone::Named(tz) = event.zone() {
+            zones.entry(tz.name()).or_insert_with(|| (tz, Vec::new())).1.push(event);
+        }
+    }
+    for (tz, zone_events) in zones.values() {
+        push_vtimezone(&mut output, *tz, zone_events);
+    }
+
+    for event in events {
+        push_line(&mut output, "BEGIN:VEVENT");
+        // Edited occurrences share the UID of their series and say which occurrence they replace
+        let uid = event.recurring_event_id.as_deref().filter(|_| event.original_start.is_some()).unwrap_or(&event.id);
+        push_line(&mut output, &format!("UID:{}", uid));
+        push_line(&mut output, &format!("DTSTAMP:{}", now));
+        let params = time_params(event);
+        if let (Some(original_start), true) = (event.original_start, event.rrule.is_none()) {
+            push_line(&mut output, &format!("RECURRENCE-ID{}:{}", params, time_value(event, original_start)));
+        }
+        push_line(&mut output, &format!("DTSTART{}:{}", params, time_value(event, event.start_time)));
+        push_line(&mut output, &format!("DTEND{}:{}", params, time_value(event, event.end_time)));
+        push_line(&mut output, &format!("SUMMARY:{}", escape(&event.title)));
+        if !event.description.is_empty() {
+            push_line(&mut output, &format!("DESCRIPTION:{}", escape(&event.description)));
+        }
+        push_line(&mut output, &format!("CATEGORIES:{}", event_type_name(event.event_type)));
+        if let Some(rrule) = &event.rrule {
+            push_line(&mut output, &format!("RRULE:{}", rrule));
+        }
+        if !event.exdates.is_empty() {
+            let exdates: Vec<String> = event.exdates.iter().map(|exdate| time_value(event, *exdate)).collect();
+            push_line(&mut output, &format!("EXDATE{}:{}", params, exdates.join(",")));
+        }
+        if event.is_completed {
+            push_line(&mut output, &format!("{}:TRUE", X_COMPLETED));
+        }
+        if let Some(sheet_music_id) = &event.sheet_music_id {
+            push_line(&mut output, &format!("{}:{}", X_SHEET_MUSIC_ID, sheet_music_id));
+        }
+        if let Some(color) = &event.color {
+            push_line(&mut output, &format!("{}:{}", X_COLOR, color));
+        }
+        push_line(&mut output, "END:VEVENT");
+    }
+
+    push_line(&mut output, "END:VCALENDAR");
+    output
+}
+
+// Series without COUNT or UNTIL have occurrences after any start, so only bounded ones are expanded to check
+fn series_reaches(event: &PracticeEvent, start: DateTime<Utc>) -> bool {
+    let rule = match event.recurrence() {
+        Ok(Some(rule)) => rule,
+        _ => return false,
+    };
+    if rule.count.is_none() && rule.until.is_none() {
+        return true;
+    }
+    event
+        .occurrence_starts(&rule, DateTime::<Utc>::MAX_UTC)
+        .into_iter()
+        .filter(|occurrence| !event.exdates.contains(occurrence))
+        .any(|occurrence| event.instance(occurrence).overlaps(start, DateTime::<Utc>::MAX_UTC))
+}
+
+// Stored events to export: whole series whenever one of their occurrences is in range, plus edited occurrences.
+// Either end of the range may be open; an open end never expands a series past its last occurrence
+pub fn select_events(
+    events: &[PracticeEvent],
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    event_types: Option<&[EventType]>,
+) -> Vec<PracticeEvent> {
+    let touching: Option<Vec<PracticeEvent>> = match (start, end) {
+        (None, None) => None,
+        (start, Some(end)) => {
+            let calendar = calendar::Calendar { events: events.to_vec() };
+            Some(calendar.in_range(start.unwrap_or(DateTime::<Utc>::MIN_UTC), end))
+        }
+        (Some(start), None) => Some(
+            events
+                .iter()
+                .filter(|event| match event.rrule {
+                    Some(_) => series_reaches(event, start),
+                    None => event.overlaps(start, DateTime::<Utc>::MAX_UTC),
+                })
+                .cloned()
+                .collect(),
+        ),
+    };
+    let in_range: Option<HashSet<String>> = touching.map(|touching| {
+        touching
+            .into_iter()
+            .map(|event| event.recurring_event_id.filter(|_| event.rrule.is_none() && event.original_start.is_some()).unwrap_or(event.id))
+            .collect()
+    });
+    events
+        .iter()
+        .filter(|event| event_types.map(|types| types.contains(&event.event_type)).unwrap_or(true))
+        .filter(|event| match &in_range {
+            None => true,
+            Some(ids) => ids.contains(&event.id) || event.recurring_event_id.as_ref().is_some_and(|id| ids.contains(id)),
+        })
+        .cloned()
+        .collect()
+}
+
+// Re-importing the same file updates events instead of duplicating them, since UIDs become event IDs
+#[tauri::command]
+pub fn import_ics(path: String, default_type: Option<EventType>) -> Result<IcsImportReport, String> {
+    let text = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let (mut events, warnings) = parse_ics(&text, default_type.unwrap_or_default());
+    let mut report = IcsImportReport {
+        warnings,
+        ..Default::default()
+    };
+
+    let sheet_music_ids: HashSet<String> = library::read(|library| library.items.iter().map(|item| item.id.clone()).collect())?;
+    for event in events.iter_mut() {
+        if event.sheet_music_id.as_ref().is_some_and(|id| !sheet_music_ids.contains(id)) {
+            event.sheet_music_id = None;
+        }
+    }
+
+    calendar::update(|calendar| {
+        for mut event in events {
+            // Edited occurrences are matched on their series and original start, not on their generated ID
+            let existing = match (&event.recurring_event_id, event.original_start) {
+                (Some(series_id), Some(original_start)) => calendar.events.iter_mut().find(|other| {
+                    other.rrule.is_none() && other.recurring_event_id.as_ref() == Some(series_id) && other.original_start == Some(original_start)
+                }),
+                _ => calendar.events.iter_mut().find(|other| other.id == event.id),
+            };
+            match existing {
+                Some(existing) => {
+                    event.id = existing.id.clone();
+                    if *existing == event {
+                        report.unchanged += 1;
+                    } else {
+                        *existing = event;
+                        report.updated += 1;
+                    }
+                }
+                None => {
+                    calendar.events.push(event);
+                    report.added += 1;
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    println!(
+        "Imported {}: {} added, {} updated, {} unchanged, {} warnings",
+        path,
+        report.added,
+        report.updated,
+        report.unchanged,
+        report.warnings.len()
+    );
+    Ok(report)
+}
+
+// Without a range the whole calendar is exported; event types narrow it down further
+#[tauri::command]
+pub fn export_ics(
+    path: String,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    event_types: Option<Vec<EventType>>,
+) -> Result<IcsExportSummary, String> {
+    if let (Some(start), Some(end)) = (start, end) {
+        if end < start {
+            return Err("Range end is before its start".to_string());
+        }
+    }
+    let events = calendar::read(|calendar| select_events(&calendar.events, start, end, event_types.as_deref()))?;
+    fs::write(&path, write_ics(&events, Some("Partitura"))).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    println!("Exported {} events to {}", events.len(), path);
+    Ok(IcsExportSummary { path, events: events.len() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    const SERIES: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+UID:lesson\r
+DTSTART;TZID=Europe/Berlin:20260323T090000\r
+DTEND;TZID=Europe/Berlin:20260323T100000\r
+SUMMARY:Piano lesson\r
+CATEGORIES:Lessons\r
+RRULE:FREQ=WEEKLY;COUNT=10\r
+EXDATE;TZID=Europe/Berlin:20260406T090000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:lesson\r
+RECURRENCE-ID;TZID=Europe/Berlin:20260413T090000\r
+DTSTART;TZID=Europe/Berlin:20260414T170000\r
+DTEND;TZID=Europe/Berlin:20260414T180000\r
+SUMMARY:Piano lesson\\, moved\r
+CATEGORIES:Lesson\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:recital\r
+DTSTART;VALUE=DATE:20260620\r
+SUMMARY:Spring recital\r
+CATEGORIES:Recital\r
+X-PARTITURA-COMPLETED:TRUE\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().with_timezone(&Utc)
+    }
+
+    fn parse(text: &str) -> Vec<PracticeEvent> {
+        let (mut events, warnings) = parse_ics(text, EventType::Practice);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        // Edited occurrences get a fresh ID on every import
+        for event in events.iter_mut().filter(|event| event.is_override()) {
+            event.id.clear();
+        }
+        events
+    }
+
+    #[test]
+    fn parses_series_exceptions_and_all_day_events() {
+        let events = parse(SERIES);
+        assert_eq!(events.len(), 3);
+
+        let series = &events[0];
+        assert_eq!(series.time_zone.as_deref(), Some("Europe/Berlin"));
+        assert_eq!(series.start_time, utc("2026-03-23T08:00:00Z"));
+        assert_eq!(series.exdates, vec![utc("2026-04-06T07:00:00Z")]);
+
+        let moved = &events[1];
+        assert_eq!(moved.recurring_event_id.as_deref(), Some("lesson"));
+        assert_eq!(moved.original_start, Some(utc("2026-04-13T07:00:00Z")));
+        assert_eq!(moved.title, "Piano lesson, moved");
+
+        let recital = &events[2];
+        assert!(recital.all_day && recital.is_completed);
+        assert_eq!((recital.start_time, recital.end_time), (utc("2026-06-20T00:00:00Z"), utc("2026-06-21T00:00:00Z")));
+    }
+
+    #[test]
+    fn written_calendars_read_back_unchanged() {
+        let events = parse(SERIES);
+        let written = write_ics(&events, Some("Partitura"));
+        assert_eq!(parse(&written), events);
+        // Writing is stable too, apart from DTSTAMP
+        let strip = |text: &str| text.lines().filter(|line| !line.starts_with("DTSTAMP")).collect::<Vec<_>>().join("\n");
+        assert_eq!(strip(&write_ics(&parse(&written), Some("Partitura"))), strip(&written));
+    }
+
+    #[test]
+    fn zoned_times_come_with_their_vtimezone() {
+        let written = write_ics(&parse(SERIES), None);
+        assert_eq!(written.matches("BEGIN:VTIMEZONE").count(), 1);
+        assert!(written.contains("TZID:Europe/Berlin\r\n"));
+        // Summer time starts at 02:00 winter time and ends at 03:00 summer time
+        assert!(written.contains("BEGIN:DAYLIGHT\r\nDTSTART:20260329T020000\r\nTZOFFSETFROM:+0100\r\nTZOFFSETTO:+0200\r\nTZNAME:CEST\r\n"));
+        assert!(written.contains("BEGIN:STANDARD\r\nDTSTART:20261025T030000\r\nTZOFFSETFROM:+0200\r\nTZOFFSETTO:+0100\r\nTZNAME:CET\r\n"));
+        // A repeating event's zone is described for years after its last stored time
+        assert!(written.contains("DTSTART:20351028T030000"));
+        assert!(written.find("END:VTIMEZONE").unwrap() < written.find("BEGIN:VEVENT").unwrap());
+    }
+
+    #[test]
+    fn open_ended_ranges_keep_series_whole() {
+        let text = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:scales\r
+DTSTART;VALUE=DATE:20260101\r
+RRULE:FREQ=WEEKLY\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:etudes\r
+DTSTART:20260105T170000Z\r
+DTEND:20260105T180000Z\r
+RRULE:FREQ=DAILY;COUNT=2\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:audition\r
+DTSTART:20250310T170000Z\r
+DTEND:20250310T180000Z\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+        let events = parse(text);
+        let ids = |selected: Vec<PracticeEvent>| selected.into_iter().map(|event| event.id).collect::<Vec<_>>();
+
+        assert_eq!(ids(select_events(&events, Some(utc("2026-06-01T00:00:00Z")), None, None)), vec!["scales"]);
+        assert_eq!(ids(select_events(&events, Some(utc("2026-01-06T00:00:00Z")), None, None)), vec!["scales", "etudes"]);
+        assert_eq!(ids(select_events(&events, None, Some(utc("2025-12-01T00:00:00Z")), None)), vec!["audition"]);
+        assert_eq!(select_events(&events, None, None, None).len(), 3);
+
+        let written = write_ics(&select_events(&events, Some(utc("2026-06-01T00:00:00Z")), None, None), None);
+        assert_eq!(written.matches("BEGIN:VEVENT").count(), 1);
+        assert!(written.contains("RRULE:FREQ=WEEKLY\r\n"));
+    }
+
+    #[test]
+    fn local_series_stay_on_the_wall_clock_across_dst() {
+        // Weekly across the March change in both Europe and North America
+        let local = |text: &str| recurrence::resolve_local(&Local, NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M").unwrap());
+        let mut series = parse(SERIES).remove(0);
+        series.time_zone = None;
+        series.start_time = local("2026-03-02T18:00");
+        series.end_time = local("2026-03-02T19:00");
+        series.rrule = Some("FREQ=WEEKLY;COUNT=6".to_string());
+        series.exdates = vec![local("2026-03-16T18:00")];
+
+        let written = write_ics(std::slice::from_ref(&series), None);
+        assert!(written.contains("DTSTART:20260302T180000\r\n"));
+        assert!(written.contains("EXDATE:20260316T180000\r\n"));
+        assert!(!written.contains("BEGIN:VTIMEZONE"));
+
+        let read_back = parse(&written).remove(0);
+        assert_eq!(read_back, series);
+        let expand = |event: &PracticeEvent| {
+            let rule = event.recurrence().unwrap().unwrap();
+            let starts = event.occurrence_starts(&rule, DateTime::<Utc>::MAX_UTC);
+            starts.into_iter().filter(|start| !event.exdates.contains(start)).collect::<Vec<_>>()
+        };
+        let occurrences = expand(&read_back);
+        assert_eq!(occurrences, expand(&series));
+        let wall_clock: Vec<String> = occurrences.iter().map(|start| start.with_timezone(&Local).format("%H:%M").to_string()).collect();
+        assert_eq!(wall_clock, vec!["18:00"; 5]);
+    }
+}
@@ -0,0 +1,388 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::UdpSocket;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::calendar::{self, EventType};
+use crate::{ics, library, store};
+
+const FEEDS_DOCUMENT: &str = "ics_feeds";
+// Next to AUTH_CALLBACK_PORT so both are easy to allow through a firewall
+const DEFAULT_FEED_PORT: u16 = 43124;
+const TOKEN_BYTES: usize = 32;
+const FEED_WORKERS: usize = 4;
+
+fn default_port() -> u16 {
+    DEFAULT_FEED_PORT
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Feed {
+    pub id: String,
+    pub name: String,
+    pub token: String,
+    pub event_types: Vec<EventType>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FeedSettings {
+    // Off until the user turns it on; nothing listens before that
+    enabled: bool,
+    // Only loopback unless other devices on the network should reach the feeds
+    allow_lan: bool,
+    #[serde(default = "default_port")]
+    port: u16,
+    feeds: Vec<Feed>,
+}
+
+impl Default for FeedSettings {
+    fn default() -> Self {
+        FeedSettings {
+            enabled: false,
+            allow_lan: false,
+            port: DEFAULT_FEED_PORT,
+            feeds: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedInfo {
+    #[serde(flatten)]
+    pub feed: Feed,
+    pub url: String,
+    pub webcal_url: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedServerStatus {
+    pub enabled: bool,
+    pub allow_lan: bool,
+    pub port: u16,
+    pub running: bool,
+    pub error: Option<String>,
+    pub feeds: Vec<FeedInfo>,
+}
+
+struct CachedFeed {
+    revision: u64,
+    event_types: Vec<EventType>,
+    name: String,
+    body: String,
+    etag: String,
+}
+
+struct RunningServer {
+    server: Arc<Server>,
+    allow_lan: bool,
+    port: u16,
+}
+
+static SETTINGS: Lazy<Mutex<Option<FeedSettings>>> = Lazy::new(|| Mutex::new(None));
+static SERVER: Lazy<Mutex<Option<RunningServer>>> = Lazy::new(|| Mutex::new(None));
+static SERVER_ERROR: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+// Rendered feeds keyed by feed ID, rebuilt when the calendar revision moves on
+static RENDERED: Lazy<Mutex<HashMap<String, CachedFeed>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn with_settings<R>(f: impl FnOnce(&mut FeedSettings) -> Result<R, String>) -> Result<R, String> {
+    let mut guard = SETTINGS.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(FEEDS_DOCUMENT)?);
+    }
+    let settings = guard.as_mut().unwrap();
+    let result = f(settings)?;
+    store::save(FEEDS_DOCUMENT, settings)?;
+    Ok(result)
+}
+
+fn read<R>(f: impl FnOnce(&FeedSettings) -> R) -> Result<R, String> {
+    let mut guard = SETTINGS.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(FEEDS_DOCUMENT)?);
+    }
+    Ok(f(guard.as_ref().unwrap()))
+}
+
+fn new_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Compares every byte so response timing doesn't reveal how much of a guessed token was right
+fn tokens_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len() && expected.bytes().zip(given.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// The address other devices would use; found by routing a UDP socket, which sends nothing
+fn lan_address() -> Option<String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:80").ok()?;
+    Some(socket.local_addr().ok()?.ip().to_string())
+}
+
+fn feed_info(feed: &Feed, allow_lan: bool, port: u16) -> FeedInfo {
+    let host = if allow_lan { lan_address().unwrap_or_else(|| "127.0.0.1".to_string()) } else { "127.0.0.1".to_string() };
+    let path = format!("{}:{}/feeds/{}.ics", host, port, feed.token);
+    FeedInfo {
+        feed: feed.clone(),
+        url: format!("http://{}", path),
+        webcal_url: format!("webcal://{}", path),
+    }
+}
+
+fn validate_feed(name: &str, event_types: &[EventType]) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Feed name can't be empty".to_string());
+    }
+    if event_types.is_empty() {
+        return Err("Pick at least one event type for the feed".to_string());
+    }
+    Ok(())
+}
+
+fn render(feed: &Feed) -> Result<(String, String), String> {
+    let revision = calendar::revision();
+    let mut rendered = RENDERED.lock().unwrap();
+    if let Some(cached) = rendered.get(&feed.id) {
+        if cached.revision == revision && cached.event_types == feed.event_types && cached.name == feed.name {
+            return Ok((cached.body.clone(), cached.etag.clone()));
+        }
+    }
+
+    let events = calendar::read(|calendar| ics::select_events(&calendar.events, None, None, Some(&feed.event_types)))?;
+    let body = ics::write_ics(&events, Some(&feed.name));
+    // DTSTAMP changes with every render, so hash the events themselves for a stable ETag
+    let etag = format!("\"{:x}\"", Sha256::digest(serde_json::to_vec(&(&events, &feed.name)).map_err(|e| e.to_string())?));
+    println!("Rendered calendar feed {} with {} events", feed.name, events.len());
+    rendered.insert(
+        feed.id.clone(),
+        CachedFeed {
+            revision,
+            event_types: feed.event_types.clone(),
+            name: feed.name.clone(),
+            body: body.clone(),
+            etag: etag.clone(),
+        },
+    );
+    Ok((body, etag))
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header {
+        field: field.parse().unwrap(),
+        value: value.parse().unwrap(),
+    }
+}
+
+fn handle(request: Request) {
+    let token = request
+        .url()
+        .split('?')
+        .next()
+        .and_then(|path| path.strip_prefix("/feeds/"))
+        .and_then(|file| file.strip_suffix(".ics"))
+        .map(str::to_string);
+    let is_read = matches!(request.method(), Method::Get | Method::Head);
+
+    // Unknown tokens get the same 404 as unknown paths
+    let feed = match token {
+        Some(token) if is_read => read(|settings| settings.feeds.iter().find(|feed| tokens_match(&feed.token, &token)).cloned())
+            .unwrap_or_else(|e| {
+                println!("Failed to read calendar feeds: {}", e);
+                None
+            }),
+        _ => None,
+    };
+    let feed = match feed {
+        Some(feed) => feed,
+        None => {
+            let _ = request.respond(Response::from_string("Not found").with_status_code(404));
+            return;
+        }
+    };
+
+    let (body, etag) = match render(&feed) {
+        Ok(rendered) => rendered,
+        Err(e) => {
+            println!("Failed to render calendar feed {}: {}", feed.name, e);
+            let _ = request.respond(Response::from_string("Calendar unavailable").with_status_code(500));
+            return;
+        }
+    };
+    let not_modified = request
+        .headers()
+        .iter()
+        .any(|h| h.field.equiv("If-None-Match") && h.value.as_str().split(',').any(|value| value.trim() == etag));
+
+    let result = if not_modified {
+        request.respond(Response::empty(304).with_header(header("ETag", &etag)))
+    } else {
+        let response = Response::from_string(body)
+            .with_header(header("Content-Type", "text/calendar; charset=utf-8"))
+            .with_header(header("Content-Disposition", "inline; filename=\"partitura.ics\""))
+            .with_header(header("Cache-Control", "no-cache"))
+            .with_header(header("ETag", &etag));
+        request.respond(response)
+    };
+    if let Err(e) = result {
+        println!("Failed to send calendar feed {}: {}", feed.name, e);
+    }
+}
+
+fn stop_server() {
+    if let Some(running) = SERVER.lock().unwrap().take() {
+        // Each unblock releases one waiting worker
+        for _ in 0..FEED_WORKERS {
+            running.server.unblock();
+        }
+        println!("Stopped calendar feed server on port {}", running.port);
+    }
+}
+
+// Starts, stops or rebinds the server so it matches the saved settings
+fn apply_settings() -> Result<(), String> {
+    let (enabled, allow_lan, port) = read(|settings| (settings.enabled, settings.allow_lan, settings.port))?;
+    {
+        let running = SERVER.lock().unwrap();
+        if let Some(running) = running.as_ref() {
+            if enabled && running.allow_lan == allow_lan && running.port == port {
+                return Ok(());
+            }
+        }
+    }
+    stop_server();
+    *SERVER_ERROR.lock().unwrap() = None;
+    if !enabled {
+        return Ok(());
+    }
+
+    let addr = format!("{}:{}", if allow_lan { "0.0.0.0" } else { "127.0.0.1" }, port);
+    let server = match Server::http(&addr) {
+        Ok(server) => Arc::new(server),
+        Err(e) => {
+            let error = format!("Failed to start calendar feed server on {}: {}", addr, e);
+            println!("{}", error);
+            *SERVER_ERROR.lock().unwrap() = Some(error.clone());
+            return Err(error);
+        }
+    };
+    println!("Calendar feed server started on {}", addr);
+
+    // A few workers share the listener, so a slow client can't hold up the others and a flood can't spawn threads
+    for _ in 0..FEED_WORKERS {
+        let server = Arc::clone(&server);
+        thread::spawn(move || {
+            // Ends once stop_server unblocks it
+            for request in server.incoming_requests() {
+                handle(request);
+            }
+        });
+    }
+    *SERVER.lock().unwrap() = Some(RunningServer { server, allow_lan, port });
+    Ok(())
+}
+
+pub fn start() {
+    if let Err(e) = apply_settings() {
+        println!("Calendar feeds unavailable: {}", e);
+    }
+}
+
+fn status() -> Result<FeedServerStatus, String> {
+    let running = SERVER.lock().unwrap().is_some();
+    let error = SERVER_ERROR.lock().unwrap().clone();
+    read(|settings| FeedServerStatus {
+        enabled: settings.enabled,
+        allow_lan: settings.allow_lan,
+        port: settings.port,
+        running,
+        error,
+        feeds: settings.feeds.iter().map(|feed| feed_info(feed, settings.allow_lan, settings.port)).collect(),
+    })
+}
+
+#[tauri::command]
+pub fn get_calendar_feeds() -> Result<FeedServerStatus, String> {
+    status()
+}
+
+// A failure to bind is reported in the returned status rather than as an error, so the setting still sticks
+#[tauri::command]
+pub fn set_calendar_feed_server(enabled: bool, allow_lan: bool, port: Option<u16>) -> Result<FeedServerStatus, String> {
+    if port == Some(0) {
+        return Err("Feed server port must be between 1 and 65535".to_string());
+    }
+    with_settings(|settings| {
+        settings.enabled = enabled;
+        settings.allow_lan = allow_lan;
+        settings.port = port.unwrap_or(settings.port);
+        Ok(())
+    })?;
+    let _ = apply_settings();
+    status()
+}
+
+#[tauri::command]
+pub fn create_calendar_feed(name: String, event_types: Vec<EventType>) -> Result<FeedInfo, String> {
+    validate_feed(&name, &event_types)?;
+    with_settings(|settings| {
+        let feed = Feed {
+            id: library::new_id(),
+            name: name.trim().to_string(),
+            token: new_token(),
+            event_types,
+            created_at: Utc::now(),
+        };
+        settings.feeds.push(feed.clone());
+        Ok(feed_info(&feed, settings.allow_lan, settings.port))
+    })
+}
+
+#[tauri::command]
+pub fn update_calendar_feed(id: String, name: String, event_types: Vec<EventType>) -> Result<FeedInfo, String> {
+    validate_feed(&name, &event_types)?;
+    with_settings(|settings| {
+        let (allow_lan, port) = (settings.allow_lan, settings.port);
+        let feed = settings.feeds.iter_mut().find(|feed| feed.id == id).ok_or_else(|| format!("Calendar feed {} not found", id))?;
+        feed.name = name.trim().to_string();
+        feed.event_types = event_types;
+        Ok(feed_info(feed, allow_lan, port))
+    })
+}
+
+// Cuts off every app subscribed with the old URL
+#[tauri::command]
+pub fn regenerate_calendar_feed_token(id: String) -> Result<FeedInfo, String> {
+    with_settings(|settings| {
+        let (allow_lan, port) = (settings.allow_lan, settings.port);
+        let feed = settings.feeds.iter_mut().find(|feed| feed.id == id).ok_or_else(|| format!("Calendar feed {} not found", id))?;
+        feed.token = new_token();
+        Ok(feed_info(feed, allow_lan, port))
+    })
+}
+
+#[tauri::command]
+pub fn delete_calendar_feed(id: String) -> Result<(), String> {
+    with_settings(|settings| {
+        let before = settings.feeds.len();
+        settings.feeds.retain(|feed| feed.id != id);
+        if settings.feeds.len() == before {
+            return Err(format!("Calendar feed {} not found", id));
+        }
+        Ok(())
+    })?;
+    RENDERED.lock().unwrap().remove(&id);
+    Ok(())
+}
@@ -0,0 +1,672 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::{calendar, goals, metadata, recordings, store, sync};
+
+const LIBRARY_DOCUMENT: &str = "library";
+
+// Mirrors SheetMusicItem from the frontend types
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SheetMusicItem {
+    pub id: String,
+    pub title: String,
+    pub composer: String,
+    pub pdf_path: String,
+    pub is_favorite: bool,
+    pub date_added: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    pub id: String,
+    pub name: String,
+    pub color: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemTag {
+    pub sheet_music_id: String,
+    pub tag_id: String,
+}
+
+// Collections nest through parent_id, e.g. "Concerts" > "Spring Concert 2026"
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    pub parent_id: Option<String>,
+    pub sheet_music_ids: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SetlistEntry {
+    pub id: String,
+    pub sheet_music_id: String,
+    pub notes: String,
+    pub intended_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Setlist {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub entries: Vec<SetlistEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Library {
+    pub items: Vec<SheetMusicItem>,
+    pub tags: Vec<Tag>,
+    pub item_tags: Vec<ItemTag>,
+    pub collections: Vec<Collection>,
+    pub setlists: Vec<Setlist>,
+}
+
+impl Library {
+    pub fn item(&self, id: &str) -> Option<&SheetMusicItem> {
+        self.items.iter().find(|item| item.id == id)
+    }
+
+    pub fn require_item(&self, id: &str) -> Result<(), String> {
+        self.item(id).map(|_| ()).ok_or_else(|| format!("Sheet music {} not found", id))
+    }
+
+    fn require_tag(&self, id: &str) -> Result<(), String> {
+        if self.tags.iter().any(|tag| tag.id == id) {
+            Ok(())
+        } else {
+            Err(format!("Tag {} not found", id))
+        }
+    }
+
+    // Tag names are unique ignoring case; `except` is the tag being renamed
+    fn tag_name_taken(&self, name: &str, except: Option<&str>) -> bool {
+        self.tags
+            .iter()
+            .any(|tag| tag.name.eq_ignore_ascii_case(name) && Some(tag.id.as_str()) != except)
+    }
+
+    fn collection_mut(&mut self, id: &str) -> Result<&mut Collection, String> {
+        self.collections
+            .iter_mut()
+            .find(|collection| collection.id == id)
+            .ok_or_else(|| format!("Collection {} not found", id))
+    }
+
+    fn setlist_mut(&mut self, id: &str) -> Result<&mut Setlist, String> {
+        self.setlists
+            .iter_mut()
+            .find(|setlist| setlist.id == id)
+            .ok_or_else(|| format!("Setlist {} not found", id))
+    }
+
+    // Removes a piece together with every tag, collection and setlist reference to it
+    pub fn remove_item(&mut self, id: &str) -> Option<SheetMusicItem> {
+        let index = self.items.iter().position(|item| item.id == id)?;
+        let removed = self.items.remove(index);
+        self.item_tags.retain(|link| link.sheet_music_id != id);
+        for collection in &mut self.collections {
+            collection.sheet_music_ids.retain(|item_id| item_id != id);
+        }
+        for setlist in &mut self.setlists {
+            setlist.entries.retain(|entry| entry.sheet_music_id != id);
+        }
+        Some(removed)
+    }
+
+    // Points every tag, collection and setlist reference at another piece
+    pub fn repoint_item(&mut self, from: &str, to: &str) {
+        for link in &mut self.item_tags {
+            if link.sheet_music_id == from {
+                link.sheet_music_id = to.to_string();
+            }
+        }
+        let mut seen = Vec::new();
+        self.item_tags.retain(|link| {
+            let duplicate = seen.contains(link);
+            seen.push(link.clone());
+            !duplicate
+        });
+
+        for collection in &mut self.collections {
+            if collection.sheet_music_ids.iter().any(|id| id == from) {
+                collection.sheet_music_ids.retain(|id| id != from);
+                if !collection.sheet_music_ids.iter().any(|id| id == to) {
+                    collection.sheet_music_ids.push(to.to_string());
+                }
+            }
+        }
+
+        for setlist in &mut self.setlists {
+            for entry in &mut setlist.entries {
+                if entry.sheet_music_id == from {
+                    entry.sheet_music_id = to.to_string();
+                }
+            }
+        }
+    }
+
+    pub fn upsert_item(&mut self, item: SheetMusicItem) {
+        match self.items.iter_mut().find(|existing| existing.id == item.id) {
+            Some(existing) => *existing = item,
+            None => self.items.push(item),
+        }
+    }
+
+    // Walks up from `parent_id` to make sure `id` isn't one of its ancestors
+    fn creates_cycle(&self, id: &str, parent_id: Option<&str>) -> bool {
+        let mut current = parent_id.map(str::to_string);
+        while let Some(current_id) = current {
+            if current_id == id {
+                return true;
+            }
+            current = self
+                .collections
+                .iter()
+                .find(|collection| collection.id == current_id)
+                .and_then(|collection| collection.parent_id.clone());
+        }
+        false
+    }
+}
+
+static LIBRARY: Lazy<Mutex<Option<Library>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn new_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+// Runs a read-only closure against the library, loading it from disk on first use
+pub fn read<R>(f: impl FnOnce(&Library) -> R) -> Result<R, String> {
+    let mut guard = LIBRARY.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(LIBRARY_DOCUMENT)?);
+    }
+    Ok(f(guard.as_ref().unwrap()))
+}
+
+// Applies a change to a copy of the library and only keeps it if both the change and the save succeed
+fn commit<R>(f: impl FnOnce(&mut Library) -> Result<R, String>) -> Result<(Vec<SheetMusicItem>, Vec<SheetMusicItem>, R), String> {
+    let mut guard = LIBRARY.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(LIBRARY_DOCUMENT)?);
+    }
+    let mut next = guard.as_ref().unwrap().clone();
+    let result = f(&mut next)?;
+    store::save(LIBRARY_DOCUMENT, &next)?;
+    let after = next.items.clone();
+    let before = guard.replace(next).map(|previous| previous.items).unwrap_or_default();
+    Ok((before, after, result))
+}
+
+// Local changes go through here so they end up in the sync operation log
+pub fn update<R>(f: impl FnOnce(&mut Library) -> Result<R, String>) -> Result<R, String> {
+    let (before, after, result) = commit(f)?;
+    // Recorded after the library lock is released, the sync engine takes its own lock
+    sync::record_item_changes(&before, &after);
+    unlink_removed_items(&before, &after);
+    Ok(result)
+}
+
+// Used by the sync engine to apply remote changes without logging them as local edits
+pub fn apply_remote<R>(f: impl FnOnce(&mut Library) -> Result<R, String>) -> Result<R, String> {
+    let (before, after, result) = commit(f)?;
+    unlink_removed_items(&before, &after);
+    Ok(result)
+}
+
+// Calendar events keep pointing at deleted pieces otherwise, and their recordings would be left behind
+fn unlink_removed_items(before: &[SheetMusicItem], after: &[SheetMusicItem]) {
+    let removed: Vec<String> = before
+        .iter()
+        .filter(|item| !after.iter().any(|other| other.id == item.id))
+        .map(|item| item.id.clone())
+        .collect();
+    if let Err(e) = calendar::unlink_sheet_music(&removed) {
+        println!("Failed to unlink events from removed sheet music: {}", e);
+    }
+    if let Err(e) = recordings::remove_for_sheet_music(&removed) {
+        println!("Failed to remove recordings of removed sheet music: {}", e);
+    }
+    if let Err(e) = goals::unlink_sheet_music(&removed) {
+        println!("Failed to remove goals of removed sheet music: {}", e);
+    }
+}
+
+// Adds a PDF on disk to the catalog, reading title and composer through the metadata pipeline
+pub fn import_pdf(path: &Path) -> Result<SheetMusicItem, String> {
+    let pdf_path = path.to_string_lossy().to_string();
+    let existing = read(|library| library.items.iter().find(|item| item.pdf_path == pdf_path).cloned())?;
+    if let Some(existing) = existing {
+        return Ok(existing);
+    }
+
+    let metadata = metadata::extract(path)?;
+    let item = SheetMusicItem {
+        id: new_id(),
+        title: metadata.title,
+        composer: metadata.composer,
+        pdf_path,
+        is_favorite: false,
+        date_added: Utc::now(),
+    };
+    update(|library| {
+        library.upsert_item(item.clone());
+        Ok(item)
+    })
+}
+
+#[tauri::command]
+pub fn list_sheet_music() -> Result<Vec<SheetMusicItem>, String> {
+    read(|library| library.items.clone())
+}
+
+#[tauri::command]
+pub fn upsert_sheet_music(item: SheetMusicItem) -> Result<SheetMusicItem, String> {
+    update(|library| {
+        library.upsert_item(item.clone());
+        Ok(item)
+    })
+}
+
+#[tauri::command]
+pub fn delete_sheet_music(id: String) -> Result<(), String> {
+    update(|library| {
+        library
+            .remove_item(&id)
+            .map(|_| ())
+            .ok_or_else(|| format!("Sheet music {} not found", id))
+    })
+}
+
+#[tauri::command]
+pub fn list_tags() -> Result<Vec<Tag>, String> {
+    read(|library| library.tags.clone())
+}
+
+#[tauri::command]
+pub fn create_tag(name: String, color: Option<String>) -> Result<Tag, String> {
+    update(|library| {
+        if library.tag_name_taken(&name, None) {
+            return Err(format!("Tag \"{}\" already exists", name));
+        }
+        let tag = Tag { id: new_id(), name, color };
+        library.tags.push(tag.clone());
+        Ok(tag)
+    })
+}
+
+#[tauri::command]
+pub fn update_tag(tag: Tag) -> Result<Tag, String> {
+    update(|library| {
+        if library.tag_name_taken(&tag.name, Some(&tag.id)) {
+            return Err(format!("Tag \"{}\" already exists", tag.name));
+        }
+        let existing = library
+            .tags
+            .iter_mut()
+            .find(|existing| existing.id == tag.id)
+            .ok_or_else(|| format!("Tag {} not found", tag.id))?;
+        *existing = tag.clone();
+        Ok(tag)
+    })
+}
+
+#[tauri::command]
+pub fn delete_tag(id: String) -> Result<(), String> {
+    update(|library| {
+        library.require_tag(&id)?;
+        library.tags.retain(|tag| tag.id != id);
+        library.item_tags.retain(|link| link.tag_id != id);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn tag_sheet_music(sheet_music_id: String, tag_id: String) -> Result<(), String> {
+    update(|library| {
+        library.require_item(&sheet_music_id)?;
+        library.require_tag(&tag_id)?;
+        let link = ItemTag { sheet_music_id, tag_id };
+        if !library.item_tags.contains(&link) {
+            library.item_tags.push(link);
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn untag_sheet_music(sheet_music_id: String, tag_id: String) -> Result<(), String> {
+    update(|library| {
+        library
+            .item_tags
+            .retain(|link| !(link.sheet_music_id == sheet_music_id && link.tag_id == tag_id));
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn get_sheet_music_tags(sheet_music_id: String) -> Result<Vec<Tag>, String> {
+    read(|library| {
+        library
+            .tags
+            .iter()
+            .filter(|tag| {
+                library
+                    .item_tags
+                    .iter()
+                    .any(|link| link.sheet_music_id == sheet_music_id && link.tag_id == tag.id)
+            })
+            .cloned()
+            .collect()
+    })
+}
+
+#[tauri::command]
+pub fn list_sheet_music_by_tag(tag_id: String) -> Result<Vec<SheetMusicItem>, String> {
+    read(|library| {
+        library
+            .items
+            .iter()
+            .filter(|item| {
+                library
+                    .item_tags
+                    .iter()
+                    .any(|link| link.tag_id == tag_id && link.sheet_music_id == item.id)
+            })
+            .cloned()
+            .collect()
+    })
+}
+
+#[tauri::command]
+pub fn list_collections() -> Result<Vec<Collection>, String> {
+    read(|library| library.collections.clone())
+}
+
+#[tauri::command]
+pub fn create_collection(name: String, parent_id: Option<String>) -> Result<Collection, String> {
+    update(|library| {
+        if let Some(parent_id) = &parent_id {
+            library.collection_mut(parent_id)?;
+        }
+        let collection = Collection {
+            id: new_id(),
+            name,
+            parent_id,
+            sheet_music_ids: Vec::new(),
+        };
+        library.collections.push(collection.clone());
+        Ok(collection)
+    })
+}
+
+#[tauri::command]
+pub fn update_collection(id: String, name: String, parent_id: Option<String>) -> Result<Collection, String> {
+    update(|library| {
+        if let Some(parent_id) = &parent_id {
+            library.collection_mut(parent_id)?;
+        }
+        if library.creates_cycle(&id, parent_id.as_deref()) {
+            return Err("A collection cannot be nested inside itself".to_string());
+        }
+        let collection = library.collection_mut(&id)?;
+        collection.name = name;
+        collection.parent_id = parent_id;
+        Ok(collection.clone())
+    })
+}
+
+// Child collections move up to the deleted collection's parent; pieces stay in the library
+#[tauri::command]
+pub fn delete_collection(id: String) -> Result<(), String> {
+    update(|library| {
+        let parent_id = library.collection_mut(&id)?.parent_id.clone();
+        library.collections.retain(|collection| collection.id != id);
+        for collection in &mut library.collections {
+            if collection.parent_id.as_deref() == Some(id.as_str()) {
+                collection.parent_id = parent_id.clone();
+            }
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn add_to_collection(collection_id: String, sheet_music_id: String) -> Result<(), String> {
+    update(|library| {
+        library.require_item(&sheet_music_id)?;
+        let collection = library.collection_mut(&collection_id)?;
+        if !collection.sheet_music_ids.contains(&sheet_music_id) {
+            collection.sheet_music_ids.push(sheet_music_id);
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn remove_from_collection(collection_id: String, sheet_music_id: String) -> Result<(), String> {
+    update(|library| {
+        let collection = library.collection_mut(&collection_id)?;
+        collection.sheet_music_ids.retain(|id| *id != sheet_music_id);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn list_setlists() -> Result<Vec<Setlist>, String> {
+    read(|library| library.setlists.clone())
+}
+
+#[tauri::command]
+pub fn create_setlist(name: String, description: Option<String>) -> Result<Setlist, String> {
+    update(|library| {
+        let setlist = Setlist {
+            id: new_id(),
+            name,
+            description: description.unwrap_or_default(),
+            entries: Vec::new(),
+        };
+        library.setlists.push(setlist.clone());
+        Ok(setlist)
+    })
+}
+
+#[tauri::command]
+pub fn update_setlist(id: String, name: String, description: String) -> Result<Setlist, String> {
+    update(|library| {
+        let setlist = library.setlist_mut(&id)?;
+        setlist.name = name;
+        setlist.description = description;
+        Ok(setlist.clone())
+    })
+}
+
+#[tauri::command]
+pub fn delete_setlist(id: String) -> Result<(), String> {
+    update(|library| {
+        library.setlist_mut(&id)?;
+        library.setlists.retain(|setlist| setlist.id != id);
+        Ok(())
+    })
+}
+
+// Inserts at `position` when given, otherwise appends to the end of the setlist
+#[tauri::command]
+pub fn add_setlist_entry(
+    setlist_id: String,
+    sheet_music_id: String,
+    notes: Option<String>,
+    intended_key: Option<String>,
+    position: Option<usize>,
+) -> Result<SetlistEntry, String> {
+    update(|library| {
+        library.require_item(&sheet_music_id)?;
+        let setlist = library.setlist_mut(&setlist_id)?;
+        let entry = SetlistEntry {
+            id: new_id(),
+            sheet_music_id,
+            notes: notes.unwrap_or_default(),
+            intended_key,
+        };
+        let index = position.unwrap_or(setlist.entries.len()).min(setlist.entries.len());
+        setlist.entries.insert(index, entry.clone());
+        Ok(entry)
+    })
+}
+
+#[tauri::command]
+pub fn update_setlist_entry(
+    setlist_id: String,
+    entry_id: String,
+    notes: String,
+    intended_key: Option<String>,
+) -> Result<SetlistEntry, String> {
+    update(|library| {
+        let setlist = library.setlist_mut(&setlist_id)?;
+        let entry = setlist
+            .entries
+            .iter_mut()
+            .find(|entry| entry.id == entry_id)
+            .ok_or_else(|| format!("Setlist entry {} not found", entry_id))?;
+        entry.notes = notes;
+        entry.intended_key = intended_key;
+        Ok(entry.clone())
+    })
+}
+
+#[tauri::command]
+pub fn remove_setlist_entry(setlist_id: String, entry_id: String) -> Result<(), String> {
+    update(|library| {
+        let setlist = library.setlist_mut(&setlist_id)?;
+        setlist.entries.retain(|entry| entry.id != entry_id);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn move_setlist_entry(setlist_id: String, entry_id: String, new_index: usize) -> Result<Setlist, String> {
+    update(|library| {
+        let setlist = library.setlist_mut(&setlist_id)?;
+        let index = setlist
+            .entries
+            .iter()
+            .position(|entry| entry.id == entry_id)
+            .ok_or_else(|| format!("Setlist entry {} not found", entry_id))?;
+        let entry = setlist.entries.remove(index);
+        let new_index = new_index.min(setlist.entries.len());
+        setlist.entries.insert(new_index, entry);
+        Ok(setlist.clone())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str) -> SheetMusicItem {
+        SheetMusicItem {
+            id: id.to_string(),
+            title: id.to_string(),
+            composer: String::new(),
+            pdf_path: format!("/music/{}.pdf", id),
+            is_favorite: false,
+            date_added: Utc::now(),
+        }
+    }
+
+    fn tag(id: &str, name: &str) -> Tag {
+        Tag { id: id.to_string(), name: name.to_string(), color: None }
+    }
+
+    fn link(sheet_music_id: &str, tag_id: &str) -> ItemTag {
+        ItemTag { sheet_music_id: sheet_music_id.to_string(), tag_id: tag_id.to_string() }
+    }
+
+    fn collection(id: &str, parent_id: Option<&str>, sheet_music_ids: &[&str]) -> Collection {
+        Collection {
+            id: id.to_string(),
+            name: id.to_string(),
+            parent_id: parent_id.map(str::to_string),
+            sheet_music_ids: sheet_music_ids.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    fn entry(id: &str, sheet_music_id: &str) -> SetlistEntry {
+        SetlistEntry { id: id.to_string(), sheet_music_id: sheet_music_id.to_string(), notes: String::new(), intended_key: None }
+    }
+
+    fn library() -> Library {
+        Library {
+            items: vec![item("bach"), item("chopin"), item("debussy")],
+            tags: vec![tag("baroque", "Baroque"), tag("exam", "Exam")],
+            item_tags: vec![link("bach", "baroque"), link("bach", "exam"), link("chopin", "exam")],
+            collections: vec![
+                collection("concerts", None, &["bach", "chopin"]),
+                collection("spring", Some("concerts"), &["bach"]),
+                collection("encores", Some("spring"), &[]),
+            ],
+            setlists: vec![Setlist {
+                id: "recital".to_string(),
+                name: "Recital".to_string(),
+                description: String::new(),
+                entries: vec![entry("first", "bach"), entry("second", "debussy"), entry("third", "chopin")],
+            }],
+        }
+    }
+
+    #[test]
+    fn removing_a_piece_drops_every_reference_to_it() {
+        let mut library = library();
+        assert_eq!(library.remove_item("bach").map(|item| item.id), Some("bach".to_string()));
+        assert!(library.item("bach").is_none());
+        assert_eq!(library.item_tags, vec![link("chopin", "exam")]);
+        assert_eq!(library.collections[0].sheet_music_ids, vec!["chopin"]);
+        assert!(library.collections[1].sheet_music_ids.is_empty());
+        let entries: Vec<&str> = library.setlists[0].entries.iter().map(|entry| entry.id.as_str()).collect();
+        assert_eq!(entries, vec!["second", "third"]);
+        assert!(library.remove_item("bach").is_none());
+    }
+
+    #[test]
+    fn repointing_merges_references_without_duplicates() {
+        let mut library = library();
+        library.repoint_item("bach", "chopin");
+        assert_eq!(library.item_tags, vec![link("chopin", "baroque"), link("chopin", "exam")]);
+        assert_eq!(library.collections[0].sheet_music_ids, vec!["chopin"]);
+        assert_eq!(library.collections[1].sheet_music_ids, vec!["chopin"]);
+        let pieces: Vec<&str> = library.setlists[0].entries.iter().map(|entry| entry.sheet_music_id.as_str()).collect();
+        assert_eq!(pieces, vec!["chopin", "debussy", "chopin"]);
+    }
+
+    #[test]
+    fn collections_cannot_be_nested_inside_their_descendants() {
+        let library = library();
+        assert!(library.creates_cycle("concerts", Some("concerts")));
+        assert!(library.creates_cycle("concerts", Some("encores")));
+        assert!(library.creates_cycle("spring", Some("encores")));
+        assert!(!library.creates_cycle("encores", Some("concerts")));
+        assert!(!library.creates_cycle("spring", None));
+    }
+
+    #[test]
+    fn tag_names_are_unique_ignoring_case() {
+        let library = library();
+        assert!(library.tag_name_taken("exam", None));
+        assert!(library.tag_name_taken("EXAM", Some("baroque")));
+        // Renaming a tag to a different case of its own name is fine
+        assert!(!library.tag_name_taken("EXAM", Some("exam")));
+        assert!(!library.tag_name_taken("Romantic", None));
+    }
+}
@@ -9,6 +9,36 @@ use url::form_urlencoded;
 use std::io::Read;
 use std::fs::File;
 use std::path::Path;
+use tauri::Manager;
+
+mod api_client;
+mod api_status;
+mod archive;
+mod caldav;
+mod calendar;
+mod duplicates;
+mod goals;
+mod ics;
+mod ics_feed;
+mod library;
+mod metadata;
+mod metronome;
+mod pdf_cache;
+mod pitch;
+mod planner;
+mod practice;
+mod recordings;
+mod recurrence;
+mod reminders;
+mod scheduling;
+mod session;
+mod sightreading;
+mod sightreading_progress;
+mod stats;
+mod store;
+mod sync;
+mod uploads;
+mod watcher;
 
 // State to store the authentication code received by the server
 struct AuthState {
@@ -152,6 +182,7 @@ async fn listen_for_auth_callback(port: u16, timeout: u64) -> Result<String, Str
                                     let mut state = state_clone.lock().unwrap();
                                     state.code = Some(value.to_string());
                                     state.received = true;
+                                    session::update_access_token(value.to_string(), None);
                                     
                                     response = create_success_response();
                                     break;
@@ -181,6 +212,7 @@ async fn listen_for_auth_callback(port: u16, timeout: u64) -> Result<String, Str
                                         let mut state = state_clone.lock().unwrap();
                                         state.code = Some(value.to_string());
                                         state.received = true;
+                                        session::update_access_token(value.to_string(), None);
                                         
                                         response = create_success_response();
                                         break;
@@ -201,6 +233,9 @@ async fn listen_for_auth_callback(port: u16, timeout: u64) -> Result<String, Str
                                 let mut state = state_clone.lock().unwrap();
                                 state.code = Some(value.to_string());
                                 state.received = true;
+                                if key == "access_token" {
+                                    session::update_access_token(value.to_string(), None);
+                                }
                                 
                                 response = create_success_response();
                                 break;
@@ -511,10 +546,165 @@ fn create_success_response() -> Response<std::io::Cursor<Vec<u8>>> {
 fn main() {
     println!("Starting Partitura application...");
     tauri::Builder::default()
+        .plugin(tauri_plugin_notification::init())
+        .setup(|app| {
+            store::init(app.path().app_data_dir()?)?;
+            watcher::start(app.handle().clone())?;
+            sync::start(app.handle().clone());
+            api_status::start(app.handle().clone());
+            ics_feed::start();
+            caldav::start(app.handle().clone());
+            reminders::start(app.handle().clone());
+            practice::start();
+            uploads::start(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_free_port,
             listen_for_auth_callback,
-            open_url_in_browser
+            open_url_in_browser,
+            library::list_sheet_music,
+            library::upsert_sheet_music,
+            library::delete_sheet_music,
+            library::list_tags,
+            library::create_tag,
+            library::update_tag,
+            library::delete_tag,
+            library::tag_sheet_music,
+            library::untag_sheet_music,
+            library::get_sheet_music_tags,
+            library::list_sheet_music_by_tag,
+            library::list_collections,
+            library::create_collection,
+            library::update_collection,
+            library::delete_collection,
+            library::add_to_collection,
+            library::remove_from_collection,
+            library::list_setlists,
+            library::create_setlist,
+            library::update_setlist,
+            library::delete_setlist,
+            library::add_setlist_entry,
+            library::update_setlist_entry,
+            library::remove_setlist_entry,
+            library::move_setlist_entry,
+            watcher::list_watch_folders,
+            watcher::add_watch_folder,
+            watcher::remove_watch_folder,
+            archive::export_library,
+            archive::import_library,
+            archive::inspect_library_archive,
+            duplicates::find_duplicates,
+            duplicates::merge_duplicates,
+            sync::configure_sync,
+            sync::sync_now,
+            sync::get_sync_status,
+            sync::list_sync_conflicts,
+            sync::clear_sync_conflicts,
+            session::set_session,
+            session::clear_session,
+            session::has_session,
+            api_client::configure_api,
+            api_client::api_list_sheet_music,
+            api_client::api_upload_sheet_music,
+            api_client::api_update_sheet_music,
+            api_client::api_delete_sheet_music,
+            api_client::api_list_events,
+            api_client::api_create_event,
+            api_client::api_update_event,
+            api_client::api_delete_event,
+            api_client::api_toggle_event_completion,
+            api_client::api_get_preferences,
+            api_client::api_update_preferences,
+            api_status::get_api_status,
+            api_status::check_api_status,
+            uploads::enqueue_upload,
+            uploads::list_uploads,
+            uploads::pause_upload,
+            uploads::resume_upload,
+            uploads::cancel_upload,
+            uploads::clear_finished_uploads,
+            pdf_cache::get_cached_pdf,
+            pdf_cache::pin_sheet_music,
+            pdf_cache::unpin_sheet_music,
+            pdf_cache::pin_setlist,
+            pdf_cache::unpin_setlist,
+            pdf_cache::get_pdf_cache_status,
+            pdf_cache::set_pdf_cache_limit,
+            pdf_cache::clear_pdf_cache,
+            calendar::list_events,
+            calendar::list_events_in_range,
+            calendar::list_events_for_sheet_music,
+            calendar::get_event,
+            calendar::create_event,
+            calendar::update_event,
+            calendar::delete_event,
+            calendar::set_event_completed,
+            calendar::update_occurrence,
+            calendar::delete_occurrence,
+            calendar::list_events_for_display,
+            calendar::resolve_zoned_time,
+            calendar::list_time_zones,
+            ics::import_ics,
+            ics::export_ics,
+            ics_feed::get_calendar_feeds,
+            ics_feed::set_calendar_feed_server,
+            ics_feed::create_calendar_feed,
+            ics_feed::update_calendar_feed,
+            ics_feed::regenerate_calendar_feed_token,
+            ics_feed::delete_calendar_feed,
+            caldav::configure_caldav,
+            caldav::unlock_caldav,
+            caldav::list_caldav_calendars,
+            caldav::link_caldav_calendar,
+            caldav::unlink_caldav_calendar,
+            caldav::remove_caldav_account,
+            caldav::set_caldav_conflict_strategy,
+            caldav::sync_caldav,
+            caldav::get_caldav_status,
+            caldav::list_caldav_conflicts,
+            caldav::clear_caldav_conflicts,
+            reminders::get_notification_preferences,
+            reminders::set_notification_preferences,
+            reminders::list_upcoming_reminders,
+            scheduling::check_event_conflicts,
+            scheduling::find_free_slots,
+            practice::start_practice_session,
+            practice::get_active_practice_session,
+            practice::pause_practice_session,
+            practice::resume_practice_session,
+            practice::add_practice_tempo_note,
+            practice::stop_practice_session,
+            practice::list_practice_sessions,
+            practice::update_practice_session_notes,
+            practice::delete_practice_session,
+            goals::get_practice_goals,
+            goals::set_practice_goals,
+            goals::list_practice_deadlines,
+            stats::practice_stats,
+            planner::suggest_practice_plan,
+            metronome::start_metronome,
+            metronome::stop_metronome,
+            metronome::set_metronome_tempo,
+            metronome::get_metronome_status,
+            metronome::render_metronome,
+            pitch::analyze_pitch,
+            pitch::start_tuner,
+            pitch::push_tuner_audio,
+            pitch::stop_tuner,
+            recordings::import_recording,
+            recordings::save_recording,
+            recordings::list_recordings,
+            recordings::list_recordings_by_piece,
+            recordings::get_recording_waveform,
+            recordings::update_recording,
+            recordings::delete_recording,
+            recordings::get_recording_storage,
+            recordings::set_recording_quota,
+            sightreading::list_sight_reading_levels,
+            sightreading::generate_sight_reading_exercise,
+            sightreading_progress::get_sight_reading_progress,
+            sightreading_progress::record_sight_reading_attempt
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
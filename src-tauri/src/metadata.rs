@@ -0,0 +1,133 @@
+use std::fs;
+use std::path::Path;
+
+// Metadata we can pull out of a PDF without a full parser
+#[derive(Debug, Clone, Default)]
+pub struct PdfMetadata {
+    pub title: String,
+    pub composer: String,
+}
+
+// A PDF is only considered complete once the header and the trailing %%EOF marker are both present
+pub fn is_complete_pdf(data: &[u8]) -> bool {
+    let tail_start = data.len().saturating_sub(1024);
+    data.starts_with(b"%PDF-") && find(&data[tail_start..], b"%%EOF").is_some()
+}
+
+pub fn extract(path: &Path) -> Result<PdfMetadata, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if !data.starts_with(b"%PDF-") {
+        return Err(format!("{} is not a PDF file", path.display()));
+    }
+
+    // Prefer the document info dictionary, fall back to "Composer - Title.pdf" style file names
+    let (file_title, file_composer) = from_file_name(path);
+    let title = info_string(&data, b"/Title").unwrap_or(file_title);
+    let composer = info_string(&data, b"/Author").unwrap_or(file_composer);
+
+    Ok(PdfMetadata { title, composer })
+}
+
+fn from_file_name(path: &Path) -> (String, String) {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().replace('_', " "))
+        .unwrap_or_default();
+    match stem.split_once(" - ") {
+        Some((composer, title)) => (title.trim().to_string(), composer.trim().to_string()),
+        None => (stem.trim().to_string(), String::new()),
+    }
+}
+
+// Counts page objects, skipping the /Pages tree nodes
+pub fn count_pages(data: &[u8]) -> usize {
+    let mut count = 0;
+    for pattern in [&b"/Type /Page"[..], &b"/Type/Page"[..]] {
+        let mut offset = 0;
+        while let Some(index) = find(&data[offset..], pattern) {
+            let end = offset + index + pattern.len();
+            if data.get(end) != Some(&b's') {
+                count += 1;
+            }
+            offset = end;
+        }
+    }
+    count
+}
+
+fn info_string(data: &[u8], key: &[u8]) -> Option<String> {
+    let start = find(data, key)? + key.len();
+    let rest = &data[start..];
+    let skip = rest.iter().position(|byte| !byte.is_ascii_whitespace())?;
+    let rest = &rest[skip..];
+
+    let bytes = match rest.first()? {
+        b'(' => literal_string(&rest[1..]),
+        b'<' => hex_string(&rest[1..]),
+        _ => return None,
+    };
+    let value = decode_text(&bytes).trim().to_string();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+fn literal_string(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut depth = 0;
+    let mut iter = data.iter();
+    while let Some(&byte) = iter.next() {
+        match byte {
+            b'\\' => match iter.next() {
+                Some(b'n') => out.push(b'\n'),
+                Some(b'r') => out.push(b'\r'),
+                Some(b't') => out.push(b'\t'),
+                Some(&escaped) => out.push(escaped),
+                None => break,
+            },
+            b'(' => {
+                depth += 1;
+                out.push(byte);
+            }
+            b')' if depth == 0 => break,
+            b')' => {
+                depth -= 1;
+                out.push(byte);
+            }
+            _ => out.push(byte),
+        }
+    }
+    out
+}
+
+fn hex_string(data: &[u8]) -> Vec<u8> {
+    let digits: Vec<u8> = data
+        .iter()
+        .take_while(|byte| **byte != b'>')
+        .filter_map(|byte| (*byte as char).to_digit(16).map(|digit| digit as u8))
+        .collect();
+    digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+// PDF text strings are either UTF-16BE with a BOM or PDFDocEncoding, which is close enough to Latin-1
+fn decode_text(bytes: &[u8]) -> String {
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = bytes[2..]
+            .chunks(2)
+            .filter(|pair| pair.len() == 2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        bytes.iter().map(|byte| *byte as char).collect()
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
@@ -0,0 +1,702 @@
+use base64::Engine;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const CLICK_SECONDS: f32 = 0.03;
+// Live playback renders this far ahead of the clock, enough to ride out a busy webview
+const LOOKAHEAD: Duration = Duration::from_millis(150);
+const BLOCK_SECONDS: f64 = 0.05;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Accent {
+    Strong,
+    Normal,
+    Weak,
+    Mute,
+}
+
+// Speed trainer: after every `everyBars` bars the tempo moves `stepBpm` towards `targetBpm`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TempoRamp {
+    pub target_bpm: f64,
+    pub step_bpm: f64,
+    pub every_bars: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MetronomeSettings {
+    // Beats per minute, a beat being one count of the meter, e.g. a dotted quarter in 6/8 counted in two
+    pub bpm: f64,
+    pub beats_per_bar: u32,
+    // Clicks per beat; 2 for eighths in 4/4, 3 for triplets or 6/8 counted in two
+    pub subdivision: u32,
+    // One per beat; missing beats are Normal, and an empty list accents the downbeat
+    pub accents: Vec<Accent>,
+    pub count_in_bars: u32,
+    pub ramp: Option<TempoRamp>,
+    pub volume: f32,
+    pub subdivision_volume: f32,
+    pub sample_rate: u32,
+}
+
+impl Default for MetronomeSettings {
+    fn default() -> Self {
+        MetronomeSettings {
+            bpm: 100.0,
+            beats_per_bar: 4,
+            subdivision: 1,
+            accents: Vec::new(),
+            count_in_bars: 0,
+            ramp: None,
+            volume: 0.8,
+            subdivision_volume: 0.5,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+        }
+    }
+}
+
+impl MetronomeSettings {
+    fn validate(&self) -> Result<(), String> {
+        let valid_bpm = |bpm: f64| (20.0..=400.0).contains(&bpm);
+        if !valid_bpm(self.bpm) {
+            return Err("Tempo has to be between 20 and 400 BPM".to_string());
+        }
+        if !(1..=16).contains(&self.beats_per_bar) {
+            return Err("A bar has to have between 1 and 16 beats".to_string());
+        }
+        if !(1..=8).contains(&self.subdivision) {
+            return Err("Subdivision has to be between 1 and 8".to_string());
+        }
+        if self.accents.len() > self.beats_per_bar as usize {
+            return Err("There are more accents than beats in the bar".to_string());
+        }
+        if !(8_000..=192_000).contains(&self.sample_rate) {
+            return Err("Sample rate has to be between 8 and 192 kHz".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.volume) || !(0.0..=1.0).contains(&self.subdivision_volume) {
+            return Err("Volume has to be between 0 and 1".to_string());
+        }
+        if let Some(ramp) = &self.ramp {
+            if !valid_bpm(ramp.target_bpm) || ramp.step_bpm <= 0.0 || ramp.every_bars == 0 {
+                return Err("A tempo ramp needs a target between 20 and 400 BPM, a step and a bar count".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn accent(&self, beat: u32) -> Accent {
+        match self.accents.get(beat as usize) {
+            Some(accent) => *accent,
+            None if self.accents.is_empty() && beat == 0 && self.beats_per_bar > 1 => Accent::Strong,
+            None => Accent::Normal,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ClickSound {
+    Strong,
+    Normal,
+    Weak,
+    Subdivision,
+    CountIn,
+}
+
+// (pitch in Hz, level) for each sound
+fn click_voice(sound: ClickSound) -> (f32, f32) {
+    match sound {
+        ClickSound::Strong => (1760.0, 1.0),
+        ClickSound::Normal => (1320.0, 0.75),
+        ClickSound::Weak => (1320.0, 0.45),
+        ClickSound::Subdivision => (880.0, 1.0),
+        ClickSound::CountIn => (1100.0, 0.75),
+    }
+}
+
+// A short decaying sine; the 1 ms attack keeps it from popping
+fn click_waveform(sound: ClickSound, sample_rate: u32, gain: f32) -> Vec<f32> {
+    let (frequency, level) = click_voice(sound);
+    let length = (CLICK_SECONDS * sample_rate as f32) as usize;
+    let attack = (0.001 * sample_rate as f32).max(1.0);
+    (0..length)
+        .map(|i| {
+            let t = i as f32 / sample_rate as f32;
+            let envelope = (i as f32 / attack).min(1.0) * (-t / (CLICK_SECONDS / 5.0)).exp();
+            (TAU * frequency * t).sin() * envelope * level * gain
+        })
+        .collect()
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ClickEvent {
+    // Position in the rendered stream; exact to the sample, whatever the block size
+    pub sample: u64,
+    pub seconds: f64,
+    // 1-based; count-in bars are numbered separately with countIn set
+    pub bar: u32,
+    pub beat: u32,
+    pub subdivision: u32,
+    pub accent: Accent,
+    pub count_in: bool,
+    pub bpm: f64,
+}
+
+// Anything that can take the rendered mono samples, like a WAV file or the webview
+pub trait AudioOutput: Send {
+    fn write(&mut self, samples: &[f32], start_sample: u64) -> Result<(), String>;
+
+    fn finish(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+pub struct WavOutput {
+    writer: Option<hound::WavWriter<BufWriter<File>>>,
+}
+
+impl WavOutput {
+    pub fn create(path: &str, sample_rate: u32) -> Result<WavOutput, String> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let writer = hound::WavWriter::create(path, spec).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+        Ok(WavOutput { writer: Some(writer) })
+    }
+}
+
+fn to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+impl AudioOutput for WavOutput {
+    fn write(&mut self, samples: &[f32], _start_sample: u64) -> Result<(), String> {
+        let writer = self.writer.as_mut().ok_or_else(|| "WAV file is already finished".to_string())?;
+        for sample in samples {
+            writer.write_sample(to_i16(*sample)).map_err(|e| format!("Failed to write WAV: {}", e))?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        match self.writer.take() {
+            Some(writer) => writer.finalize().map_err(|e| format!("Failed to finish WAV: {}", e)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct AudioChunk {
+    start_sample: u64,
+    sample_rate: u32,
+    // Little-endian 16-bit mono PCM, base64 encoded
+    pcm: String,
+}
+
+// Sends the stream to the webview, which queues the chunks on an AudioContext at startSample
+pub struct WebviewOutput {
+    app: AppHandle,
+    sample_rate: u32,
+}
+
+impl AudioOutput for WebviewOutput {
+    fn write(&mut self, samples: &[f32], start_sample: u64) -> Result<(), String> {
+        let bytes: Vec<u8> = samples.iter().flat_map(|sample| to_i16(*sample).to_le_bytes()).collect();
+        let chunk = AudioChunk {
+            start_sample,
+            sample_rate: self.sample_rate,
+            pcm: base64::engine::general_purpose::STANDARD.encode(bytes),
+        };
+        self.app.emit("metronome-audio", chunk).map_err(|e| e.to_string())
+    }
+}
+
+struct Voice {
+    sound: ClickSound,
+    start: u64,
+}
+
+pub struct Metronome {
+    settings: MetronomeSettings,
+    waveforms: Vec<(ClickSound, Vec<f32>)>,
+    voices: Vec<Voice>,
+    // Samples rendered so far
+    position: u64,
+    // Exact position of the next click; kept fractional so rounding never accumulates into drift
+    next_tick: f64,
+    bpm: f64,
+    count_in_left: u32,
+    bar: u32,
+    beat: u32,
+    subdivision: u32,
+    bars_since_ramp: u32,
+    stop_after_bars: Option<u32>,
+    end_sample: Option<u64>,
+}
+
+impl Metronome {
+    pub fn new(settings: MetronomeSettings) -> Result<Metronome, String> {
+        settings.validate()?;
+        let sounds = [ClickSound::Strong, ClickSound::Normal, ClickSound::Weak, ClickSound::Subdivision, ClickSound::CountIn];
+        let waveforms = sounds
+            .iter()
+            .map(|sound| {
+                let gain = if *sound == ClickSound::Subdivision { settings.volume * settings.subdivision_volume } else { settings.volume };
+                (*sound, click_waveform(*sound, settings.sample_rate, gain))
+            })
+            .collect();
+        Ok(Metronome {
+            bpm: settings.bpm,
+            count_in_left: settings.count_in_bars,
+            waveforms,
+            voices: Vec::new(),
+            position: 0,
+            next_tick: 0.0,
+            bar: 1,
+            beat: 0,
+            subdivision: 0,
+            bars_since_ramp: 0,
+            stop_after_bars: None,
+            end_sample: None,
+            settings,
+        })
+    }
+
+    // Stops clicking once `bars` bars after the count-in are done
+    pub fn limited_to(mut self, bars: u32) -> Metronome {
+        self.stop_after_bars = Some(bars);
+        self
+    }
+
+    pub fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.end_sample.is_some_and(|end| self.position >= end)
+    }
+
+    // Takes effect from the next click; a running ramp carries on from the new tempo
+    pub fn set_bpm(&mut self, bpm: f64) -> Result<(), String> {
+        if !(20.0..=400.0).contains(&bpm) {
+            return Err("Tempo has to be between 20 and 400 BPM".to_string());
+        }
+        let elapsed = self.tick_length();
+        self.bpm = bpm;
+        // Re-space the pending click from the previous one at the new tempo
+        if self.next_tick > self.position as f64 {
+            let previous = self.next_tick - elapsed;
+            self.next_tick = previous + self.tick_length();
+        }
+        Ok(())
+    }
+
+    fn counting_in(&self) -> bool {
+        self.count_in_left > 0
+    }
+
+    fn ticks_per_beat(&self) -> u32 {
+        if self.counting_in() { 1 } else { self.settings.subdivision }
+    }
+
+    fn tick_length(&self) -> f64 {
+        60.0 * self.settings.sample_rate as f64 / self.bpm / self.ticks_per_beat() as f64
+    }
+
+    fn current_tick(&self, sample: u64) -> (ClickEvent, Option<ClickSound>) {
+        let accent = if self.subdivision == 0 { self.settings.accent(self.beat) } else { Accent::Weak };
+        let sound = match (self.counting_in(), self.subdivision, accent) {
+            (true, _, _) => Some(ClickSound::CountIn),
+            (false, 0, Accent::Strong) => Some(ClickSound::Strong),
+            (false, 0, Accent::Normal) => Some(ClickSound::Normal),
+            (false, 0, Accent::Weak) => Some(ClickSound::Weak),
+            (false, 0, Accent::Mute) => None,
+            (false, _, _) => Some(ClickSound::Subdivision),
+        };
+        let event = ClickEvent {
+            sample,
+            seconds: sample as f64 / self.settings.sample_rate as f64,
+            bar: if self.counting_in() { self.settings.count_in_bars - self.count_in_left + 1 } else { self.bar },
+            beat: self.beat + 1,
+            subdivision: self.subdivision + 1,
+            accent,
+            count_in: self.counting_in(),
+            bpm: self.bpm,
+        };
+        (event, sound)
+    }
+
+    fn advance(&mut self) {
+        self.next_tick += self.tick_length();
+        self.subdivision += 1;
+        if self.subdivision < self.ticks_per_beat() {
+            return;
+        }
+        self.subdivision = 0;
+        self.beat += 1;
+        if self.beat < self.settings.beats_per_bar {
+            return;
+        }
+        self.beat = 0;
+        if self.counting_in() {
+            self.count_in_left -= 1;
+            return;
+        }
+        self.bar += 1;
+        self.bars_since_ramp += 1;
+        if let Some(ramp) = &self.settings.ramp {
+            if self.bars_since_ramp >= ramp.every_bars {
+                self.bars_since_ramp = 0;
+                self.bpm = if ramp.target_bpm >= self.bpm {
+                    (self.bpm + ramp.step_bpm).min(ramp.target_bpm)
+                } else {
+                    (self.bpm - ramp.step_bpm).max(ramp.target_bpm)
+                };
+            }
+        }
+    }
+
+    // Fills `out` with the next samples and returns the clicks that start in them
+    pub fn render(&mut self, out: &mut [f32]) -> Vec<ClickEvent> {
+        let start = self.position;
+        let end = start + out.len() as u64;
+        let mut events = Vec::new();
+        while self.end_sample.is_none() && (self.next_tick.round() as u64) < end {
+            let sample = self.next_tick.round() as u64;
+            if self.stop_after_bars.is_some_and(|bars| !self.counting_in() && self.bar > bars) {
+                let tail = (CLICK_SECONDS * self.settings.sample_rate as f32) as u64;
+                self.end_sample = Some(sample + tail);
+                break;
+            }
+            let (event, sound) = self.current_tick(sample);
+            if let Some(sound) = sound {
+                self.voices.push(Voice { sound, start: sample });
+            }
+            events.push(event);
+            self.advance();
+        }
+
+        out.fill(0.0);
+        for voice in &self.voices {
+            let waveform = &self.waveforms.iter().find(|(sound, _)| *sound == voice.sound).unwrap().1;
+            let from = voice.start.max(start);
+            let to = (voice.start + waveform.len() as u64).min(end);
+            for sample in from..to {
+                out[(sample - start) as usize] += waveform[(sample - voice.start) as usize];
+            }
+        }
+        for sample in out.iter_mut() {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+        let waveforms = &self.waveforms;
+        self.voices.retain(|voice| {
+            let length = waveforms.iter().find(|(sound, _)| *sound == voice.sound).unwrap().1.len() as u64;
+            voice.start + length > end
+        });
+        self.position = end;
+        events
+    }
+
+    // Renders a fixed number of bars in one go, count-in included
+    pub fn render_bars(&mut self, output: &mut dyn AudioOutput) -> Result<Vec<ClickEvent>, String> {
+        if self.stop_after_bars.is_none() {
+            return Err("Only a metronome limited to a number of bars can be rendered in one go".to_string());
+        }
+        let mut block = vec![0.0; (self.settings.sample_rate as f64 * BLOCK_SECONDS) as usize];
+        let mut events = Vec::new();
+        while !self.is_finished() {
+            let start = self.position;
+            events.extend(self.render(&mut block));
+            let length = match self.end_sample {
+                Some(end) => (end.min(self.position) - start) as usize,
+                None => block.len(),
+            };
+            output.write(&block[..length], start)?;
+            if let Some(end) = self.end_sample {
+                self.position = self.position.min(end);
+            }
+        }
+        output.finish()?;
+        Ok(events)
+    }
+}
+
+enum Control {
+    Stop,
+    SetBpm(f64),
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MetronomeStatus {
+    pub running: bool,
+    pub settings: Option<MetronomeSettings>,
+    pub bpm: Option<f64>,
+}
+
+static CONTROL: Lazy<Mutex<Option<Sender<Control>>>> = Lazy::new(|| Mutex::new(None));
+static STATUS: Lazy<Mutex<MetronomeStatus>> = Lazy::new(|| Mutex::new(MetronomeStatus::default()));
+// Bumped for every start, so a run that is still winding down can tell it has been replaced
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+// Only the latest run may touch the status; a stopped one finishing late would otherwise reset its successor's
+fn update_status(generation: u64, f: impl FnOnce(&mut MetronomeStatus)) {
+    let mut status = STATUS.lock().unwrap();
+    if GENERATION.load(Ordering::SeqCst) == generation {
+        f(&mut status);
+    }
+}
+
+// Renders ahead of the wall clock in small blocks, emitting each click as it's scheduled for the visual beat
+fn play(app: AppHandle, mut metronome: Metronome, mut output: Box<dyn AudioOutput>, rx: mpsc::Receiver<Control>, generation: u64) {
+    let sample_rate = metronome.settings.sample_rate;
+    let mut block = vec![0.0; (sample_rate as f64 * BLOCK_SECONDS) as usize];
+    let started = Instant::now();
+    loop {
+        let rendered = Duration::from_secs_f64(metronome.position() as f64 / sample_rate as f64);
+        let wait = rendered.saturating_sub(started.elapsed() + LOOKAHEAD);
+        match rx.recv_timeout(wait) {
+            Ok(Control::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+            Ok(Control::SetBpm(bpm)) => {
+                if let Err(e) = metronome.set_bpm(bpm) {
+                    println!("Ignoring metronome tempo: {}", e);
+                }
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+        let start = metronome.position();
+        let events = metronome.render(&mut block);
+        if let Err(e) = output.write(&block, start) {
+            println!("Metronome output failed: {}", e);
+            break;
+        }
+        for event in events {
+            let _ = app.emit("metronome-click", event);
+        }
+        update_status(generation, |status| status.bpm = Some(metronome.bpm()));
+    }
+    let _ = output.finish();
+    update_status(generation, |status| *status = MetronomeStatus::default());
+}
+
+// Restarts with the new settings if it was already running
+#[tauri::command]
+pub fn start_metronome(app: AppHandle, settings: MetronomeSettings) -> Result<MetronomeStatus, String> {
+    let metronome = Metronome::new(settings.clone())?;
+    stop_metronome();
+    let output = Box::new(WebviewOutput {
+        app: app.clone(),
+        sample_rate: settings.sample_rate,
+    });
+    let (tx, rx) = mpsc::channel();
+    *CONTROL.lock().unwrap() = Some(tx);
+    let status = MetronomeStatus {
+        running: true,
+        bpm: Some(settings.bpm),
+        settings: Some(settings),
+    };
+    let generation = {
+        let mut current = STATUS.lock().unwrap();
+        *current = status.clone();
+        GENERATION.fetch_add(1, Ordering::SeqCst) + 1
+    };
+    thread::spawn(move || play(app, metronome, output, rx, generation));
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn stop_metronome() {
+    if let Some(control) = CONTROL.lock().unwrap().take() {
+        let _ = control.send(Control::Stop);
+    }
+}
+
+#[tauri::command]
+pub fn set_metronome_tempo(bpm: f64) -> Result<(), String> {
+    match CONTROL.lock().unwrap().as_ref() {
+        Some(control) => control.send(Control::SetBpm(bpm)).map_err(|_| "Metronome isn't running".to_string()),
+        None => Err("Metronome isn't running".to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn get_metronome_status() -> MetronomeStatus {
+    STATUS.lock().unwrap().clone()
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderSummary {
+    pub path: String,
+    pub samples: u64,
+    pub seconds: f64,
+    pub clicks: Vec<ClickEvent>,
+}
+
+// Writes `bars` bars (after any count-in) to a 16-bit mono WAV, e.g. to practise along with offline
+#[tauri::command]
+pub async fn render_metronome(settings: MetronomeSettings, bars: u32, path: String) -> Result<RenderSummary, String> {
+    if bars == 0 || bars > 1000 {
+        return Err("Render between 1 and 1000 bars".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut metronome = Metronome::new(settings.clone())?.limited_to(bars);
+        let mut output = WavOutput::create(&path, settings.sample_rate)?;
+        let clicks = metronome.render_bars(&mut output)?;
+        let samples = metronome.position();
+        println!("Rendered {} metronome bars to {}", bars, path);
+        Ok(RenderSummary {
+            path,
+            samples,
+            seconds: samples as f64 / settings.sample_rate as f64,
+            clicks,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(bpm: f64, sample_rate: u32) -> MetronomeSettings {
+        MetronomeSettings {
+            bpm,
+            sample_rate,
+            ..MetronomeSettings::default()
+        }
+    }
+
+    // Renders in blocks of `block` samples until `clicks` clicks came out
+    fn clicks(metronome: &mut Metronome, block: usize, clicks: usize) -> (Vec<ClickEvent>, Vec<f32>) {
+        let (mut events, mut audio) = (Vec::new(), Vec::new());
+        let mut out = vec![0.0; block];
+        while events.len() < clicks {
+            events.extend(metronome.render(&mut out));
+            audio.extend_from_slice(&out);
+        }
+        (events, audio)
+    }
+
+    fn samples(events: &[ClickEvent]) -> Vec<u64> {
+        events.iter().map(|event| event.sample).collect()
+    }
+
+    #[test]
+    fn clicks_land_on_the_exact_sample_whatever_the_block_size() {
+        let (events, audio) = clicks(&mut Metronome::new(settings(120.0, 48_000)).unwrap(), 4800, 4);
+        assert_eq!(samples(&events[..4]), vec![0, 24_000, 48_000, 72_000]);
+        assert_eq!(events[1].seconds, 0.5);
+
+        // 130 BPM is 22153.85 samples a beat; rounding each click on its own never drifts
+        let (events, audio_130) = clicks(&mut Metronome::new(settings(130.0, 48_000)).unwrap(), 48_000, 5);
+        assert_eq!(samples(&events[..5]), vec![0, 22_154, 44_308, 66_462, 88_615]);
+        let (small_blocks, _) = clicks(&mut Metronome::new(settings(130.0, 48_000)).unwrap(), 317, 5);
+        assert_eq!(samples(&small_blocks[..5]), samples(&events[..5]));
+
+        // Silence right up to each click, sound right after it
+        for event in &events[1..5] {
+            let at = event.sample as usize;
+            assert_eq!(audio_130[at - 1], 0.0);
+            assert!(audio_130[at + 10..at + 40].iter().any(|sample| sample.abs() > 0.05));
+        }
+        assert!(audio[30_000..40_000].iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn count_in_clicks_every_beat_before_the_first_bar() {
+        let mut settings = settings(120.0, 48_000);
+        settings.beats_per_bar = 3;
+        settings.subdivision = 2;
+        settings.count_in_bars = 1;
+        let (events, _) = clicks(&mut Metronome::new(settings).unwrap(), 4800, 5);
+
+        assert_eq!(samples(&events[..5]), vec![0, 24_000, 48_000, 72_000, 84_000]);
+        assert!(events[..3].iter().all(|event| event.count_in && event.bar == 1 && event.subdivision == 1));
+        assert_eq!(events.iter().take(3).map(|event| event.beat).collect::<Vec<_>>(), vec![1, 2, 3]);
+        // The bar proper starts with its accented downbeat, then its subdivisions
+        assert_eq!((events[3].count_in, events[3].bar, events[3].beat, events[3].accent), (false, 1, 1, Accent::Strong));
+        assert_eq!((events[4].beat, events[4].subdivision, events[4].accent), (1, 2, Accent::Weak));
+    }
+
+    #[test]
+    fn accents_pick_the_click_and_mute_leaves_silence() {
+        let (events, _) = clicks(&mut Metronome::new(settings(120.0, 48_000)).unwrap(), 4800, 4);
+        let accents: Vec<Accent> = events.iter().take(4).map(|event| event.accent).collect();
+        assert_eq!(accents, vec![Accent::Strong, Accent::Normal, Accent::Normal, Accent::Normal]);
+
+        let mut custom = settings(120.0, 48_000);
+        custom.beats_per_bar = 3;
+        custom.accents = vec![Accent::Weak, Accent::Mute];
+        let (events, audio) = clicks(&mut Metronome::new(custom).unwrap(), 4800, 4);
+        let accents: Vec<Accent> = events.iter().take(4).map(|event| event.accent).collect();
+        assert_eq!(accents, vec![Accent::Weak, Accent::Mute, Accent::Normal, Accent::Weak]);
+
+        let peak = |at: u64| audio[at as usize..at as usize + 1440].iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert_eq!(peak(events[1].sample), 0.0);
+        assert!(peak(events[2].sample) > peak(events[0].sample));
+    }
+
+    #[test]
+    fn ramp_steps_the_tempo_every_few_bars_up_to_its_target() {
+        let mut settings = settings(100.0, 8_000);
+        settings.beats_per_bar = 1;
+        settings.ramp = Some(TempoRamp {
+            target_bpm: 110.0,
+            step_bpm: 4.0,
+            every_bars: 2,
+        });
+        let (events, _) = clicks(&mut Metronome::new(settings).unwrap(), 800, 8);
+
+        let tempos: Vec<f64> = events.iter().take(8).map(|event| event.bpm).collect();
+        assert_eq!(tempos, vec![100.0, 100.0, 104.0, 104.0, 108.0, 108.0, 110.0, 110.0]);
+        // Each click is one beat of the tempo it follows
+        assert_eq!(samples(&events[..4]), vec![0, 4800, 9600, 14_215]);
+    }
+
+    #[test]
+    fn render_bars_stops_after_the_last_click() {
+        struct Collect(Vec<f32>);
+        impl AudioOutput for Collect {
+            fn write(&mut self, samples: &[f32], start_sample: u64) -> Result<(), String> {
+                assert_eq!(start_sample, self.0.len() as u64);
+                self.0.extend_from_slice(samples);
+                Ok(())
+            }
+        }
+
+        let mut settings = settings(120.0, 48_000);
+        settings.count_in_bars = 1;
+        let mut metronome = Metronome::new(settings).unwrap().limited_to(2);
+        let mut output = Collect(Vec::new());
+        let events = metronome.render_bars(&mut output).unwrap();
+        assert_eq!(events.len(), 12);
+        assert_eq!(events.last().unwrap().bar, 2);
+        // The last beat lasts its full half second, plus one click length of tail
+        assert_eq!(output.0.len(), 6 * 48_000 + 1440);
+    }
+}
@@ -0,0 +1,405 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::api_client::{self, ApiError, FileFetch};
+use crate::api_status::{self, ApiState};
+use crate::library::{self, SheetMusicItem};
+use crate::{metadata, store};
+
+const CACHE_DOCUMENT: &str = "pdf_cache";
+const CACHE_DIR: &str = "pdf_cache";
+const DEFAULT_MAX_BYTES: u64 = 1024 * 1024 * 1024;
+// Opening the same score twice in a row shouldn't hit the network twice
+const REVALIDATE_AFTER_SECONDS: i64 = 60;
+
+fn default_max_bytes() -> u64 {
+    DEFAULT_MAX_BYTES
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntry {
+    pub url: String,
+    pub file_name: String,
+    pub size: u64,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+    pub last_accessed: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheIndex {
+    #[serde(default = "default_max_bytes")]
+    max_bytes: u64,
+    entries: HashMap<String, CacheEntry>,
+    pinned_items: Vec<String>,
+    pinned_setlists: Vec<String>,
+}
+
+impl Default for CacheIndex {
+    fn default() -> Self {
+        CacheIndex {
+            max_bytes: DEFAULT_MAX_BYTES,
+            entries: HashMap::new(),
+            pinned_items: Vec::new(),
+            pinned_setlists: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedPdf {
+    pub url: String,
+    // Local file the viewer can open through convertFileSrc
+    pub path: String,
+    pub size: u64,
+    // True when the copy couldn't be checked against the server
+    pub offline: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PinReport {
+    pub cached: Vec<String>,
+    pub local: Vec<String>,
+    pub failed: HashMap<String, String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStatus {
+    pub used_bytes: u64,
+    pub max_bytes: u64,
+    pub entries: usize,
+    pub pinned_bytes: u64,
+    pub pinned_items: Vec<String>,
+    pub pinned_setlists: Vec<String>,
+}
+
+static CACHE_INDEX: Lazy<Mutex<Option<CacheIndex>>> = Lazy::new(|| Mutex::new(None));
+
+fn with_index<R>(f: impl FnOnce(&mut CacheIndex) -> Result<R, String>) -> Result<R, String> {
+    let mut guard = CACHE_INDEX.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(CACHE_DOCUMENT)?);
+    }
+    let index = guard.as_mut().unwrap();
+    let result = f(index)?;
+    store::save(CACHE_DOCUMENT, index)?;
+    Ok(result)
+}
+
+// For lookups, which shouldn't rewrite the index on every PDF open
+fn read<R>(f: impl FnOnce(&CacheIndex) -> R) -> Result<R, String> {
+    let mut guard = CACHE_INDEX.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(CACHE_DOCUMENT)?);
+    }
+    Ok(f(guard.as_ref().unwrap()))
+}
+
+fn is_remote(pdf_path: &str) -> bool {
+    pdf_path.starts_with("http://") || pdf_path.starts_with("https://")
+}
+
+fn cache_path(file_name: &str) -> Result<PathBuf, String> {
+    Ok(store::sub_dir(CACHE_DIR)?.join(file_name))
+}
+
+fn file_name_for(url: &str) -> String {
+    format!("{:x}.pdf", Sha256::digest(url.as_bytes()))
+}
+
+// Pieces pinned directly or through a setlist; read before taking the cache lock
+fn pinned_pieces(pinned_items: &[String], pinned_setlists: &[String]) -> Result<Vec<SheetMusicItem>, String> {
+    library::read(|library| {
+        let mut ids: HashSet<&str> = pinned_items.iter().map(String::as_str).collect();
+        for setlist in library.setlists.iter().filter(|setlist| pinned_setlists.contains(&setlist.id)) {
+            ids.extend(setlist.entries.iter().map(|entry| entry.sheet_music_id.as_str()));
+        }
+        library.items.iter().filter(|item| ids.contains(item.id.as_str())).cloned().collect()
+    })
+}
+
+fn pinned_urls(pinned_items: &[String], pinned_setlists: &[String]) -> Result<HashSet<String>, String> {
+    Ok(pinned_pieces(pinned_items, pinned_setlists)?
+        .into_iter()
+        .filter(|item| is_remote(&item.pdf_path))
+        .map(|item| item.pdf_path)
+        .collect())
+}
+
+fn current_pinned_urls() -> Result<HashSet<String>, String> {
+    let (items, setlists) = read(|index| (index.pinned_items.clone(), index.pinned_setlists.clone()))?;
+    pinned_urls(&items, &setlists)
+}
+
+// Drops least recently used copies until the cache fits; pinned copies stay even if that means going over
+fn evict(index: &mut CacheIndex, pinned: &HashSet<String>) {
+    let mut used: u64 = index.entries.values().map(|entry| entry.size).sum();
+    if used <= index.max_bytes {
+        return;
+    }
+    let mut candidates: Vec<(DateTime<Utc>, String)> = index
+        .entries
+        .values()
+        .filter(|entry| !pinned.contains(&entry.url))
+        .map(|entry| (entry.last_accessed, entry.url.clone()))
+        .collect();
+    candidates.sort();
+
+    for (_, url) in candidates {
+        if used <= index.max_bytes {
+            break;
+        }
+        if let Some(entry) = index.entries.remove(&url) {
+            used -= entry.size;
+            if let Ok(path) = cache_path(&entry.file_name) {
+                let _ = fs::remove_file(path);
+            }
+            println!("Evicted cached PDF {}", url);
+        }
+    }
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<(), String> {
+    let tmp_path = path.with_extension("pdf.tmp");
+    fs::write(&tmp_path, data).map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+    fs::rename(&tmp_path, path).map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+fn served(entry: &CacheEntry, offline: bool) -> Result<CachedPdf, String> {
+    Ok(CachedPdf {
+        url: entry.url.clone(),
+        path: cache_path(&entry.file_name)?.to_string_lossy().to_string(),
+        size: entry.size,
+        offline,
+    })
+}
+
+// Serves the cached copy when it's still current, refreshing or downloading it otherwise
+pub fn fetch(url: &str, force_revalidate: bool) -> Result<CachedPdf, String> {
+    let now = Utc::now();
+    let cached = read(|index| index.entries.get(url).cloned())?
+        .filter(|entry| cache_path(&entry.file_name).map(|path| path.is_file()).unwrap_or(false));
+
+    if let Some(entry) = &cached {
+        let fresh = now - entry.fetched_at < ChronoDuration::seconds(REVALIDATE_AFTER_SECONDS);
+        let offline = api_status::get_api_status().status == ApiState::Offline;
+        if (fresh && !force_revalidate) || offline {
+            touch(url, now)?;
+            return served(entry, offline);
+        }
+    }
+
+    let api = api_client::client();
+    let result = match &cached {
+        Some(entry) => api.fetch_file(url, entry.etag.as_deref(), entry.last_modified.as_deref()),
+        None => api.fetch_file(url, None, None),
+    };
+
+    match (result, cached) {
+        (Ok(FileFetch::NotModified), Some(entry)) => {
+            with_index(|index| {
+                if let Some(entry) = index.entries.get_mut(url) {
+                    entry.fetched_at = now;
+                    entry.last_accessed = now;
+                }
+                Ok(())
+            })?;
+            served(&entry, false)
+        }
+        (Ok(FileFetch::Modified { data, etag, last_modified }), _) => {
+            if !metadata::is_complete_pdf(&data) {
+                return Err(format!("{} did not return a complete PDF", url));
+            }
+            let entry = CacheEntry {
+                url: url.to_string(),
+                file_name: file_name_for(url),
+                size: data.len() as u64,
+                etag,
+                last_modified,
+                fetched_at: now,
+                last_accessed: now,
+            };
+            write_file(&cache_path(&entry.file_name)?, &data)?;
+            let pinned = current_pinned_urls()?;
+            with_index(|index| {
+                index.entries.insert(url.to_string(), entry.clone());
+                evict(index, &pinned);
+                Ok(())
+            })?;
+            println!("Cached {} ({} bytes)", url, entry.size);
+            served(&entry, false)
+        }
+        // Without a connection the last known copy is better than nothing
+        (Err(e), Some(entry)) if e.is_connection_error() || matches!(e, ApiError::Server { .. }) => {
+            touch(url, now)?;
+            served(&entry, true)
+        }
+        (Err(e), _) => Err(e.to_string()),
+        (Ok(FileFetch::NotModified), None) => Err(format!("{} answered 304 without a cached copy", url)),
+    }
+}
+
+fn touch(url: &str, now: DateTime<Utc>) -> Result<(), String> {
+    with_index(|index| {
+        if let Some(entry) = index.entries.get_mut(url) {
+            entry.last_accessed = now;
+        }
+        Ok(())
+    })
+}
+
+// Downloads the given pins; pieces with a local file are available without caching
+fn cache_pinned(ids: &[String], setlists: &[String]) -> Result<PinReport, String> {
+    let mut report = PinReport {
+        cached: Vec::new(),
+        local: Vec::new(),
+        failed: HashMap::new(),
+    };
+    let mut urls = Vec::new();
+    for item in pinned_pieces(ids, setlists)? {
+        if !is_remote(&item.pdf_path) {
+            report.local.push(item.id);
+        } else if !urls.contains(&item.pdf_path) {
+            urls.push(item.pdf_path);
+        }
+    }
+    for url in urls {
+        match fetch(&url, false) {
+            Ok(_) => report.cached.push(url),
+            Err(e) => {
+                report.failed.insert(url, e);
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn set_pin(item_id: Option<&str>, setlist_id: Option<&str>, pinned: bool) -> Result<(Vec<String>, Vec<String>), String> {
+    if let Some(id) = item_id {
+        library::read(|library| library.require_item(id))??;
+    }
+    if let Some(id) = setlist_id {
+        let exists = library::read(|library| library.setlists.iter().any(|setlist| setlist.id == id))?;
+        if !exists {
+            return Err(format!("Setlist {} not found", id));
+        }
+    }
+    with_index(|index| {
+        for (id, list) in [(item_id, &mut index.pinned_items), (setlist_id, &mut index.pinned_setlists)] {
+            if let Some(id) = id {
+                list.retain(|other| other != id);
+                if pinned {
+                    list.push(id.to_string());
+                }
+            }
+        }
+        Ok((index.pinned_items.clone(), index.pinned_setlists.clone()))
+    })
+}
+
+// Pinned copies become regular LRU entries again, and the limit applies to them once more
+fn unpin(item_id: Option<&str>, setlist_id: Option<&str>) -> Result<(), String> {
+    let (items, setlists) = set_pin(item_id, setlist_id, false)?;
+    let pinned = pinned_urls(&items, &setlists)?;
+    with_index(|index| {
+        evict(index, &pinned);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn get_cached_pdf(url: String, revalidate: Option<bool>) -> Result<CachedPdf, String> {
+    tauri::async_runtime::spawn_blocking(move || fetch(&url, revalidate.unwrap_or(false)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn pin_sheet_music(id: String) -> Result<PinReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        set_pin(Some(&id), None, true)?;
+        cache_pinned(&[id], &[])
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn unpin_sheet_music(id: String) -> Result<(), String> {
+    unpin(Some(&id), None)
+}
+
+// Makes sure every piece in a setlist can be opened without a connection, e.g. before a gig
+#[tauri::command]
+pub async fn pin_setlist(id: String) -> Result<PinReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        set_pin(None, Some(&id), true)?;
+        let report = cache_pinned(&[], std::slice::from_ref(&id))?;
+        println!("Pinned setlist {}: {} cached, {} failed", id, report.cached.len(), report.failed.len());
+        Ok(report)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn unpin_setlist(id: String) -> Result<(), String> {
+    unpin(None, Some(&id))
+}
+
+#[tauri::command]
+pub fn get_pdf_cache_status() -> Result<CacheStatus, String> {
+    let pinned = current_pinned_urls()?;
+    read(|index| {
+        let used_bytes = index.entries.values().map(|entry| entry.size).sum();
+        let pinned_bytes = index
+            .entries
+            .values()
+            .filter(|entry| pinned.contains(&entry.url))
+            .map(|entry| entry.size)
+            .sum();
+        CacheStatus {
+            used_bytes,
+            max_bytes: index.max_bytes,
+            entries: index.entries.len(),
+            pinned_bytes,
+            pinned_items: index.pinned_items.clone(),
+            pinned_setlists: index.pinned_setlists.clone(),
+        }
+    })
+}
+
+#[tauri::command]
+pub fn set_pdf_cache_limit(max_bytes: u64) -> Result<(), String> {
+    let pinned = current_pinned_urls()?;
+    with_index(|index| {
+        index.max_bytes = max_bytes;
+        evict(index, &pinned);
+        Ok(())
+    })
+}
+
+// Removes everything that isn't pinned
+#[tauri::command]
+pub fn clear_pdf_cache() -> Result<(), String> {
+    let pinned = current_pinned_urls()?;
+    with_index(|index| {
+        let max_bytes = index.max_bytes;
+        index.max_bytes = 0;
+        evict(index, &pinned);
+        index.max_bytes = max_bytes;
+        Ok(())
+    })
+}
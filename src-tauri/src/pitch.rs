@@ -0,0 +1,534 @@
+use base64::Engine;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+// Stability is the spread of the last this many seconds of readings on the same note
+const STABILITY_SECONDS: f64 = 0.5;
+// Notes held for less than this are left out of the summary as transients
+const MIN_NOTE_SECONDS: f64 = 0.1;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Temperament {
+    Equal,
+    Pythagorean,
+    // Five-limit just intonation on the tonic
+    Just,
+    QuarterCommaMeantone,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TunerSettings {
+    pub a4: f64,
+    pub temperament: Temperament,
+    // Pitch class the unequal temperaments are built on, 0 = C
+    pub tonic: u8,
+    pub min_frequency: f64,
+    pub max_frequency: f64,
+    // YIN's aperiodicity threshold; lower is stricter about what counts as a pitch
+    pub threshold: f64,
+    // Quieter frames (RMS, 0..1) are reported as silence
+    pub silence_level: f64,
+    pub hop_ms: f64,
+}
+
+impl Default for TunerSettings {
+    fn default() -> Self {
+        TunerSettings {
+            a4: 440.0,
+            temperament: Temperament::Equal,
+            tonic: 0,
+            // Low C of a cello to well above the top of a violin
+            min_frequency: 60.0,
+            max_frequency: 4200.0,
+            threshold: 0.15,
+            silence_level: 0.005,
+            hop_ms: 20.0,
+        }
+    }
+}
+
+impl TunerSettings {
+    fn validate(&self) -> Result<(), String> {
+        if !(400.0..=480.0).contains(&self.a4) {
+            return Err("A4 has to be between 400 and 480 Hz".to_string());
+        }
+        if self.tonic > 11 {
+            return Err("Tonic has to be a pitch class from 0 (C) to 11 (B)".to_string());
+        }
+        if self.min_frequency < 20.0 || self.max_frequency <= self.min_frequency {
+            return Err("The frequency range has to start at 20 Hz or above and not be empty".to_string());
+        }
+        if !(0.01..=0.5).contains(&self.threshold) {
+            return Err("Threshold has to be between 0.01 and 0.5".to_string());
+        }
+        if !(1.0..=200.0).contains(&self.hop_ms) {
+            return Err("Hop has to be between 1 and 200 ms".to_string());
+        }
+        Ok(())
+    }
+
+    // How far each pitch class sits from equal temperament, in cents, with A kept at the reference
+    fn offsets(&self) -> [f64; 12] {
+        let from_tonic: [f64; 12] = match self.temperament {
+            Temperament::Equal => [0.0; 12],
+            Temperament::Pythagorean => fifths_offsets(1200.0 * 1.5f64.log2()),
+            Temperament::QuarterCommaMeantone => fifths_offsets(1200.0 * 5f64.powf(0.25).log2()),
+            Temperament::Just => {
+                let ratios = [
+                    1.0,
+                    16.0 / 15.0,
+                    9.0 / 8.0,
+                    6.0 / 5.0,
+                    5.0 / 4.0,
+                    4.0 / 3.0,
+                    45.0 / 32.0,
+                    3.0 / 2.0,
+                    8.0 / 5.0,
+                    5.0 / 3.0,
+                    9.0 / 5.0,
+                    15.0 / 8.0,
+                ];
+                std::array::from_fn(|interval| 1200.0 * f64::log2(ratios[interval]) - 100.0 * interval as f64)
+            }
+        };
+        let mut offsets: [f64; 12] = std::array::from_fn(|pitch_class| from_tonic[(pitch_class + 12 - self.tonic as usize) % 12]);
+        // Strings tune their A to the fork, so shift the whole scale to keep it there
+        let a = offsets[9];
+        for offset in offsets.iter_mut() {
+            *offset -= a;
+        }
+        offsets
+    }
+}
+
+// A chain of equal fifths from the minor third below the tonic to the augmented fifth, the usual keyboard layout
+fn fifths_offsets(fifth: f64) -> [f64; 12] {
+    let mut offsets = [0.0; 12];
+    for step in -3i32..=8 {
+        let interval = (step * 7).rem_euclid(12) as usize;
+        let cents = (step as f64 * fifth).rem_euclid(1200.0);
+        offsets[interval] = cents - 100.0 * interval as f64;
+    }
+    offsets
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteReading {
+    // Scientific pitch notation, e.g. "A4"
+    pub name: String,
+    pub midi: i32,
+    pub target_frequency: f64,
+    // Positive is sharp
+    pub cents: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PitchFrame {
+    pub seconds: f64,
+    // None for silence or noise without a clear pitch
+    pub frequency: Option<f64>,
+    // 1 is perfectly periodic
+    pub clarity: f64,
+    pub note: Option<NoteReading>,
+    // Standard deviation in cents over the recent readings on this note; small is steady
+    pub stability_cents: Option<f64>,
+}
+
+fn nearest_note(frequency: f64, settings: &TunerSettings, offsets: &[f64; 12]) -> NoteReading {
+    let midi = 69.0 + 12.0 * (frequency / settings.a4).log2();
+    let rounded = midi.round() as i32;
+    // With an unequal temperament a neighbour can be the closer note
+    (rounded - 1..=rounded + 1)
+        .map(|midi| {
+            let pitch_class = midi.rem_euclid(12) as usize;
+            let target = settings.a4 * 2f64.powf((midi - 69) as f64 / 12.0 + offsets[pitch_class] / 1200.0);
+            NoteReading {
+                name: format!("{}{}", NOTE_NAMES[pitch_class], midi.div_euclid(12) - 1),
+                midi,
+                target_frequency: target,
+                cents: 1200.0 * (frequency / target).log2(),
+            }
+        })
+        .min_by(|a, b| a.cents.abs().total_cmp(&b.cents.abs()))
+        .unwrap()
+}
+
+// YIN (de Cheveigné & Kawahara 2002): the lag where the cumulative mean normalised difference first dips
+// under the threshold, refined by parabolic interpolation. Returns (frequency, clarity).
+fn yin(window: &[f32], sample_rate: u32, settings: &TunerSettings) -> Option<(f64, f64)> {
+    let max_lag = window.len() / 2;
+    let min_lag = ((sample_rate as f64 / settings.max_frequency).floor() as usize).max(2);
+    if min_lag + 2 >= max_lag {
+        return None;
+    }
+    let mut difference = vec![0.0f64; max_lag + 1];
+    for (lag, value) in difference.iter_mut().enumerate().skip(1) {
+        *value = (0..max_lag)
+            .map(|i| {
+                let delta = window[i] as f64 - window[i + lag] as f64;
+                delta * delta
+            })
+            .sum();
+    }
+    let mut normalised = vec![1.0f64; max_lag + 1];
+    let mut running = 0.0;
+    for lag in 1..=max_lag {
+        running += difference[lag];
+        normalised[lag] = if running > 0.0 { difference[lag] * lag as f64 / running } else { 1.0 };
+    }
+
+    let mut lag = min_lag;
+    let found = loop {
+        if lag >= max_lag {
+            break None;
+        }
+        if normalised[lag] < settings.threshold {
+            // Walk down to the bottom of this dip
+            while lag + 1 < max_lag && normalised[lag + 1] < normalised[lag] {
+                lag += 1;
+            }
+            break Some(lag);
+        }
+        lag += 1;
+    }?;
+
+    let (before, at, after) = (normalised[found - 1], normalised[found], normalised[found + 1]);
+    let curvature = before + after - 2.0 * at;
+    let shift = if curvature.abs() > f64::EPSILON { (before - after) / (2.0 * curvature) } else { 0.0 };
+    let frequency = sample_rate as f64 / (found as f64 + shift.clamp(-1.0, 1.0));
+    (frequency >= settings.min_frequency && frequency <= settings.max_frequency).then_some((frequency, 1.0 - at.clamp(0.0, 1.0)))
+}
+
+// Incremental tracker: feed it audio in whatever chunks arrive and it hands back a frame per hop
+pub struct PitchTracker {
+    settings: TunerSettings,
+    offsets: [f64; 12],
+    sample_rate: u32,
+    window: usize,
+    hop: usize,
+    buffer: Vec<f32>,
+    // Samples dropped from the front of the buffer so far
+    consumed: u64,
+    recent: VecDeque<(f64, i32, f64)>,
+}
+
+impl PitchTracker {
+    pub fn new(settings: TunerSettings, sample_rate: u32) -> Result<PitchTracker, String> {
+        settings.validate()?;
+        if !(8_000..=192_000).contains(&sample_rate) {
+            return Err("Sample rate has to be between 8 and 192 kHz".to_string());
+        }
+        // Two periods of the lowest note fit in the window
+        let window = (2.0 * sample_rate as f64 / settings.min_frequency).ceil() as usize + 2;
+        let hop = ((settings.hop_ms / 1000.0 * sample_rate as f64) as usize).max(1);
+        Ok(PitchTracker {
+            offsets: settings.offsets(),
+            settings,
+            sample_rate,
+            window,
+            hop,
+            buffer: Vec::new(),
+            consumed: 0,
+            recent: VecDeque::new(),
+        })
+    }
+
+    pub fn push(&mut self, samples: &[f32]) -> Vec<PitchFrame> {
+        self.buffer.extend_from_slice(samples);
+        let mut frames = Vec::new();
+        let mut start = 0;
+        while start + self.window <= self.buffer.len() {
+            let frame = self.analyse(start);
+            frames.push(frame);
+            start += self.hop;
+        }
+        self.buffer.drain(..start);
+        self.consumed += start as u64;
+        frames
+    }
+
+    fn analyse(&mut self, start: usize) -> PitchFrame {
+        let window = &self.buffer[start..start + self.window];
+        // Time stamps are the middle of the window
+        let seconds = (self.consumed + start as u64) as f64 / self.sample_rate as f64 + self.window as f64 / 2.0 / self.sample_rate as f64;
+        let rms = (window.iter().map(|sample| (*sample as f64).powi(2)).sum::<f64>() / window.len() as f64).sqrt();
+        let detected = if rms >= self.settings.silence_level { yin(window, self.sample_rate, &self.settings) } else { None };
+        let Some((frequency, clarity)) = detected else {
+            self.recent.clear();
+            return PitchFrame {
+                seconds,
+                frequency: None,
+                clarity: 0.0,
+                note: None,
+                stability_cents: None,
+            };
+        };
+
+        let note = nearest_note(frequency, &self.settings, &self.offsets);
+        if self.recent.back().is_some_and(|(_, midi, _)| *midi != note.midi) {
+            self.recent.clear();
+        }
+        self.recent.push_back((seconds, note.midi, note.cents));
+        while self.recent.front().is_some_and(|(time, _, _)| seconds - time > STABILITY_SECONDS) {
+            self.recent.pop_front();
+        }
+        let cents: Vec<f64> = self.recent.iter().map(|(_, _, cents)| *cents).collect();
+        PitchFrame {
+            seconds,
+            frequency: Some(frequency),
+            clarity,
+            stability_cents: (cents.len() > 1).then(|| standard_deviation(&cents)),
+            note: Some(note),
+        }
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn standard_deviation(values: &[f64]) -> f64 {
+    let mean = mean(values);
+    (values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / values.len() as f64).sqrt()
+}
+
+// A held note: consecutive frames on the same pitch
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteSegment {
+    pub name: String,
+    pub midi: i32,
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+    pub mean_frequency: f64,
+    pub mean_cents: f64,
+    pub stability_cents: f64,
+    // How far the pitch moved from the start of the note to its end, in cents per second
+    pub drift_cents_per_second: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PitchAnalysis {
+    pub sample_rate: u32,
+    pub duration_seconds: f64,
+    pub settings: TunerSettings,
+    pub frames: Vec<PitchFrame>,
+    pub notes: Vec<NoteSegment>,
+    // Over all notes, weighted by how long each was held
+    pub mean_abs_cents: Option<f64>,
+}
+
+fn segment(frames: &[PitchFrame], hop_seconds: f64) -> Vec<NoteSegment> {
+    let mut segments = Vec::new();
+    let mut run: Vec<&PitchFrame> = Vec::new();
+    let mut flush = |run: &mut Vec<&PitchFrame>| {
+        if run.len() as f64 * hop_seconds >= MIN_NOTE_SECONDS {
+            let note = run[0].note.as_ref().unwrap();
+            let times: Vec<f64> = run.iter().map(|frame| frame.seconds).collect();
+            let cents: Vec<f64> = run.iter().map(|frame| frame.note.as_ref().unwrap().cents).collect();
+            let frequencies: Vec<f64> = run.iter().filter_map(|frame| frame.frequency).collect();
+            // Least-squares slope of cents over time
+            let (mean_time, mean_cents) = (mean(&times), mean(&cents));
+            let spread: f64 = times.iter().map(|time| (time - mean_time).powi(2)).sum();
+            let covariance: f64 = times.iter().zip(&cents).map(|(time, cents)| (time - mean_time) * (cents - mean_cents)).sum();
+            segments.push(NoteSegment {
+                name: note.name.clone(),
+                midi: note.midi,
+                start_seconds: times[0],
+                end_seconds: times[times.len() - 1] + hop_seconds,
+                mean_frequency: mean(&frequencies),
+                mean_cents,
+                stability_cents: standard_deviation(&cents),
+                drift_cents_per_second: if spread > 0.0 { covariance / spread } else { 0.0 },
+            });
+        }
+        run.clear();
+    };
+    for frame in frames {
+        match &frame.note {
+            Some(note) => {
+                if run.last().is_some_and(|last| last.note.as_ref().unwrap().midi != note.midi) {
+                    flush(&mut run);
+                }
+                run.push(frame);
+            }
+            None => flush(&mut run),
+        }
+    }
+    flush(&mut run);
+    segments
+}
+
+pub fn analyse(samples: &[f32], sample_rate: u32, settings: TunerSettings) -> Result<PitchAnalysis, String> {
+    let mut tracker = PitchTracker::new(settings.clone(), sample_rate)?;
+    let frames = tracker.push(samples);
+    let hop_seconds = tracker.hop as f64 / sample_rate as f64;
+    let notes = segment(&frames, hop_seconds);
+    let held: f64 = notes.iter().map(|note| note.end_seconds - note.start_seconds).sum();
+    let mean_abs_cents = (held > 0.0).then(|| {
+        notes.iter().map(|note| note.mean_cents.abs() * (note.end_seconds - note.start_seconds)).sum::<f64>() / held
+    });
+    Ok(PitchAnalysis {
+        sample_rate,
+        duration_seconds: samples.len() as f64 / sample_rate as f64,
+        settings,
+        frames,
+        notes,
+        mean_abs_cents,
+    })
+}
+
+// Any PCM WAV, mixed down to mono in -1..1
+pub fn read_wav(path: &str) -> Result<(Vec<f32>, u32), String> {
+    let mut reader = hound::WavReader::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let spec = reader.spec();
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>(),
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader.samples::<i32>().map(|sample| sample.map(|sample| sample as f32 / scale)).collect()
+        }
+    }
+    .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let channels = spec.channels.max(1) as usize;
+    let mono = interleaved.chunks(channels).map(|frame| frame.iter().sum::<f32>() / channels as f32).collect();
+    Ok((mono, spec.sample_rate))
+}
+
+#[tauri::command]
+pub async fn analyze_pitch(path: String, settings: Option<TunerSettings>) -> Result<PitchAnalysis, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let (samples, sample_rate) = read_wav(&path)?;
+        let analysis = analyse(&samples, sample_rate, settings.unwrap_or_default())?;
+        println!("Analysed pitch of {}: {} notes", path, analysis.notes.len());
+        Ok(analysis)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+static TUNER: Lazy<Mutex<Option<PitchTracker>>> = Lazy::new(|| Mutex::new(None));
+
+// Live tuning from the microphone: the webview captures the audio and streams it in with push_tuner_audio
+#[tauri::command]
+pub fn start_tuner(settings: Option<TunerSettings>, sample_rate: u32) -> Result<(), String> {
+    *TUNER.lock().unwrap() = Some(PitchTracker::new(settings.unwrap_or_default(), sample_rate)?);
+    Ok(())
+}
+
+// `pcm` is little-endian 16-bit mono, base64 encoded like the metronome's audio chunks
+#[tauri::command]
+pub fn push_tuner_audio(pcm: String) -> Result<Vec<PitchFrame>, String> {
+    let bytes = base64::engine::general_purpose::STANDARD.decode(pcm).map_err(|e| format!("Invalid audio chunk: {}", e))?;
+    let samples: Vec<f32> = bytes
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]) as f32 / 32768.0)
+        .collect();
+    match TUNER.lock().unwrap().as_mut() {
+        Some(tracker) => Ok(tracker.push(&samples)),
+        None => Err("Tuner isn't running".to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn stop_tuner() {
+    *TUNER.lock().unwrap() = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 44_100;
+
+    fn sine(frequency: f64, seconds: f64) -> Vec<f32> {
+        let length = (seconds * SAMPLE_RATE as f64) as usize;
+        (0..length)
+            .map(|i| (0.5 * (std::f64::consts::TAU * frequency * i as f64 / SAMPLE_RATE as f64).sin()) as f32)
+            .collect()
+    }
+
+    // The one held note in a second of a sine
+    fn held_note(frequency: f64, settings: TunerSettings) -> NoteSegment {
+        let analysis = analyse(&sine(frequency, 1.0), SAMPLE_RATE, settings).unwrap();
+        assert_eq!(analysis.notes.len(), 1, "{:?}", analysis.notes);
+        analysis.notes[0].clone()
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() <= tolerance, "{} isn't within {} of {}", actual, tolerance, expected);
+    }
+
+    #[test]
+    fn reads_a4_and_middle_c_in_tune() {
+        let a4 = held_note(440.0, TunerSettings::default());
+        assert_eq!((a4.name.as_str(), a4.midi), ("A4", 69));
+        assert_close(a4.mean_frequency, 440.0, 0.2);
+        assert_close(a4.mean_cents, 0.0, 1.0);
+        assert!(a4.stability_cents < 0.5);
+
+        let c4 = held_note(261.63, TunerSettings::default());
+        assert_eq!((c4.name.as_str(), c4.midi), ("C4", 60));
+        assert_close(c4.mean_frequency, 261.63, 0.2);
+        assert_close(c4.mean_cents, 0.0, 1.0);
+    }
+
+    #[test]
+    fn reports_cents_against_the_reference_pitch() {
+        // 445 Hz is 19.6 cents sharp of A440
+        let sharp = held_note(445.0, TunerSettings::default());
+        assert_eq!(sharp.name, "A4");
+        assert_close(sharp.mean_cents, 19.56, 1.0);
+
+        // The same 440 Hz is 7.9 cents flat when the orchestra tunes to 442
+        let settings = TunerSettings {
+            a4: 442.0,
+            ..TunerSettings::default()
+        };
+        let flat = held_note(440.0, settings);
+        assert_eq!(flat.name, "A4");
+        assert_close(flat.mean_cents, -7.85, 1.0);
+    }
+
+    #[test]
+    fn unequal_temperaments_move_the_targets_but_keep_a() {
+        let just = TunerSettings {
+            temperament: Temperament::Just,
+            ..TunerSettings::default()
+        };
+        let offsets = just.offsets();
+        // A stays at the fork, so C (a just major sixth below it) moves up by 15.6 cents and E by 2.0
+        assert_close(offsets[9], 0.0, 1e-9);
+        assert_close(offsets[0], 15.64, 0.01);
+        assert_close(offsets[4], 1.96, 0.01);
+
+        // A just C against A440 is 264 Hz: in tune in just intonation, sharp in equal temperament
+        let c_just = held_note(264.0, just);
+        assert_eq!(c_just.name, "C4");
+        assert_close(c_just.mean_cents, 0.0, 1.0);
+        assert_close(held_note(264.0, TunerSettings::default()).mean_cents, 15.64, 1.0);
+
+        let pythagorean = TunerSettings {
+            temperament: Temperament::Pythagorean,
+            tonic: 2,
+            ..TunerSettings::default()
+        };
+        // Built on D, A is a pure fifth above the tonic, so the tonic sits 1.96 cents under equal
+        assert_close(pythagorean.offsets()[2], -1.96, 0.01);
+    }
+
+    #[test]
+    fn silence_has_no_pitch() {
+        let analysis = analyse(&vec![0.0; SAMPLE_RATE as usize], SAMPLE_RATE, TunerSettings::default()).unwrap();
+        assert!(analysis.frames.iter().all(|frame| frame.frequency.is_none() && frame.note.is_none()));
+        assert!(analysis.notes.is_empty() && analysis.mean_abs_cents.is_none());
+    }
+}
@@ -0,0 +1,229 @@
+use chrono::{Duration, Local, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::goals::{self, Deadline, DeadlineSource, PERFORMANCE_HORIZON_DAYS};
+use crate::library::{self, SheetMusicItem};
+use crate::{practice, recurrence, stats};
+
+// Pieces not touched for longer than this drop out of the rotation unless a goal or concert brings them back
+const NEGLECT_WINDOW_DAYS: i64 = 60;
+const MAX_INTERVAL_DAYS: i64 = 14;
+const DEFAULT_DAILY_MINUTES: u32 = 30;
+const MIN_ITEM_MINUTES: u32 = 10;
+const MAX_ITEMS: usize = 6;
+// Long-neglected pieces stop gaining priority past this, so they can't crowd out a concert programme
+const MAX_OVERDUE: f64 = 3.0;
+// Pieces scoring below this aren't due; one still gets suggested when nothing is
+const MIN_SCORE: f64 = 0.5;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanItem {
+    pub sheet_music_id: String,
+    pub title: String,
+    pub composer: String,
+    pub minutes: u32,
+    pub score: f64,
+    pub last_practiced: Option<NaiveDate>,
+    // How far apart practice on this piece can be, grown by keeping up and shrunk by lapses or a close deadline
+    pub review_interval_days: i64,
+    pub next_deadline: Option<Deadline>,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PracticePlan {
+    pub date: NaiveDate,
+    pub budget_minutes: u32,
+    pub total_minutes: u32,
+    pub items: Vec<PlanItem>,
+}
+
+#[derive(Default)]
+struct PieceHistory {
+    days: BTreeSet<NaiveDate>,
+    week_seconds: i64,
+}
+
+// Leitner-style: practising again within the interval doubles it, letting it slip halves it
+fn review_interval(days: &BTreeSet<NaiveDate>) -> i64 {
+    let mut interval = 1;
+    let mut previous: Option<NaiveDate> = None;
+    for day in days {
+        if let Some(previous) = previous {
+            let gap = (*day - previous).num_days();
+            interval = if gap <= interval + 1 { (interval * 2).min(MAX_INTERVAL_DAYS) } else { (interval / 2).max(1) };
+        }
+        previous = Some(*day);
+    }
+    interval
+}
+
+fn plural(count: i64, word: &str) -> String {
+    if count == 1 {
+        format!("1 {}", word)
+    } else {
+        format!("{} {}s", count, word)
+    }
+}
+
+fn score_piece(
+    date: NaiveDate,
+    history: Option<&PieceHistory>,
+    weekly_target: Option<u32>,
+    deadline: Option<&Deadline>,
+) -> (f64, i64, Vec<String>) {
+    let mut reasons = Vec::new();
+    let mut interval = history.map(|history| review_interval(&history.days)).unwrap_or(1);
+
+    let mut urgency = 1.0;
+    if let Some(deadline) = deadline {
+        let days_left = (deadline.date - date).num_days();
+        // A close performance keeps the piece in frequent rotation whatever its interval has grown to
+        interval = interval.min((days_left / 3).max(1));
+        urgency += 2.0 * (1.0 - days_left as f64 / PERFORMANCE_HORIZON_DAYS as f64).max(0.0);
+        let what = match (&deadline.source, &deadline.title) {
+            (DeadlineSource::Performance, Some(title)) => title.clone(),
+            (DeadlineSource::Performance, None) => "Performance".to_string(),
+            (DeadlineSource::Goal, _) => "Deadline".to_string(),
+        };
+        reasons.push(match days_left {
+            0 => format!("{} today", what),
+            _ => format!("{} in {}", what, plural(days_left, "day")),
+        });
+    }
+
+    let overdue = match history.and_then(|history| history.days.last()) {
+        Some(last) => {
+            let days_since = (date - *last).num_days();
+            if days_since > interval {
+                reasons.push(format!("Not practised for {}", plural(days_since, "day")));
+            } else if days_since == interval {
+                reasons.push("Due for review".to_string());
+            }
+            (days_since as f64 / interval as f64).min(MAX_OVERDUE)
+        }
+        None => {
+            reasons.push("Not practised yet".to_string());
+            2.0
+        }
+    };
+
+    let mut balance = 1.0;
+    if let Some(target) = weekly_target {
+        let done = history.map(|history| history.week_seconds).unwrap_or(0) / 60;
+        let remaining = target as i64 - done;
+        if remaining > 0 {
+            balance += remaining as f64 / target as f64;
+            reasons.push(format!("{} of {} minutes this week to go", remaining, target));
+        } else {
+            balance = 0.5;
+        }
+    }
+    (overdue * urgency * balance, interval, reasons)
+}
+
+// Splits the day's budget by score in steps of five minutes, giving every piece at least MIN_ITEM_MINUTES;
+// there are never more pieces than the budget has room for at that minimum
+fn allocate(budget: u32, scores: &[f64]) -> Vec<u32> {
+    let total: f64 = scores.iter().sum();
+    let minimum = MIN_ITEM_MINUTES.min(budget);
+    let mut minutes: Vec<u32> = scores
+        .iter()
+        .map(|score| (((budget as f64 * score / total / 5.0).round() as u32) * 5).max(minimum))
+        .collect();
+    // Rounding and the minimum can overshoot; take it back from the biggest share
+    while minutes.iter().sum::<u32>() > budget {
+        let largest = (0..minutes.len()).max_by_key(|index| minutes[*index]).unwrap();
+        minutes[largest] -= (minutes.iter().sum::<u32>() - budget).min(5).min(minutes[largest] - minimum);
+        if minutes[largest] == minimum {
+            break;
+        }
+    }
+    minutes
+}
+
+// Suggests which pieces to practise on `date` and for how long: pieces with goals, upcoming concerts or
+// recitals, and anything practised in the last two months, weighted by how overdue each is for review
+#[tauri::command]
+pub fn suggest_practice_plan(date: NaiveDate) -> Result<PracticePlan, String> {
+    let goals = goals::current()?;
+    let day_start = recurrence::resolve_local(&Local, date.and_hms_opt(0, 0, 0).unwrap());
+    let deadlines = goals::deadlines(&goals, day_start)?;
+    let items: HashMap<String, SheetMusicItem> = library::read(|library| {
+        library.items.iter().map(|item| (item.id.clone(), item.clone())).collect()
+    })?;
+
+    let now = Utc::now();
+    let week_start = stats::week_start(date);
+    let mut histories: HashMap<String, PieceHistory> = HashMap::new();
+    practice::read(|log| {
+        for session in &log.sessions {
+            let day = stats::local_date(session.started_at);
+            let seconds = session.active_duration(now).num_seconds();
+            if day > date || seconds <= 0 {
+                continue;
+            }
+            let history = histories.entry(session.sheet_music_id.clone()).or_default();
+            history.days.insert(day);
+            if day >= week_start {
+                history.week_seconds += seconds;
+            }
+        }
+    })?;
+
+    let mut candidates: BTreeSet<&str> = goals.pieces.iter().map(|goal| goal.sheet_music_id.as_str()).collect();
+    candidates.extend(deadlines.iter().map(|deadline| deadline.sheet_music_id.as_str()));
+    let recent = date - Duration::days(NEGLECT_WINDOW_DAYS);
+    candidates.extend(
+        histories
+            .iter()
+            .filter(|(_, history)| history.days.last().is_some_and(|last| *last >= recent))
+            .map(|(id, _)| id.as_str()),
+    );
+
+    let mut scored: Vec<PlanItem> = candidates
+        .into_iter()
+        .filter_map(|id| items.get(id))
+        .map(|item| {
+            let history = histories.get(&item.id);
+            let weekly_target = goals.pieces.iter().find(|goal| goal.sheet_music_id == item.id).and_then(|goal| goal.minutes_per_week);
+            let deadline = deadlines.iter().find(|deadline| deadline.sheet_music_id == item.id);
+            let (score, interval, reasons) = score_piece(date, history, weekly_target, deadline);
+            PlanItem {
+                sheet_music_id: item.id.clone(),
+                title: item.title.clone(),
+                composer: item.composer.clone(),
+                minutes: 0,
+                score: (score * 100.0).round() / 100.0,
+                last_practiced: history.and_then(|history| history.days.last().copied()),
+                review_interval_days: interval,
+                next_deadline: deadline.cloned(),
+                reasons,
+            }
+        })
+        .collect();
+    scored.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.title.cmp(&b.title)));
+
+    let budget = goals
+        .daily_minutes
+        .or(goals.weekly_minutes.map(|minutes| minutes.div_ceil(7)))
+        .unwrap_or(DEFAULT_DAILY_MINUTES);
+    let slots = ((budget / MIN_ITEM_MINUTES) as usize).clamp(1, MAX_ITEMS);
+    let due = scored.iter().filter(|item| item.score >= MIN_SCORE).count().max(1);
+    scored.truncate(slots.min(due));
+
+    let minutes = allocate(budget, &scored.iter().map(|item| item.score.max(0.01)).collect::<Vec<_>>());
+    for (item, minutes) in scored.iter_mut().zip(minutes) {
+        item.minutes = minutes;
+    }
+    Ok(PracticePlan {
+        date,
+        budget_minutes: budget,
+        total_minutes: scored.iter().map(|item| item.minutes).sum(),
+        items: scored,
+    })
+}
+
@@ -0,0 +1,350 @@
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use tauri::{AppHandle, Emitter};
+
+use crate::{calendar, library, recordings, store};
+
+const PRACTICE_DOCUMENT: &str = "practice_sessions";
+// A running session is stamped this often so a crash or quit doesn't count the time the app was closed
+const HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SessionStatus {
+    Running,
+    Paused,
+    Completed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TempoNote {
+    pub bpm: u32,
+    // e.g. "bars 12-24" or "coda"
+    #[serde(default)]
+    pub passage: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PracticeSession {
+    pub id: String,
+    pub sheet_music_id: String,
+    // The calendar event this session fulfils, and for a repeating one which occurrence
+    pub event_id: Option<String>,
+    pub occurrence_start: Option<DateTime<Utc>>,
+    pub status: SessionStatus,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    // Time practised up to running_since; while running the timer shows this plus the time since then
+    pub active_seconds: i64,
+    pub running_since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub tempo_notes: Vec<TempoNote>,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    heartbeat_at: Option<DateTime<Utc>>,
+}
+
+impl PracticeSession {
+    // Includes the time since the last resume for a session that's still running
+    pub fn active_duration(&self, now: DateTime<Utc>) -> Duration {
+        let running = self.running_since.map(|since| now - since).unwrap_or_else(Duration::zero);
+        Duration::seconds(self.active_seconds) + running.max(Duration::zero())
+    }
+
+    fn pause_at(&mut self, time: DateTime<Utc>) {
+        if let Some(since) = self.running_since.take() {
+            self.active_seconds += (time - since).num_seconds().max(0);
+        }
+        self.status = SessionStatus::Paused;
+        self.heartbeat_at = None;
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct PracticeLog {
+    pub sessions: Vec<PracticeSession>,
+}
+
+impl PracticeLog {
+    fn active_mut(&mut self) -> Option<&mut PracticeSession> {
+        self.sessions.iter_mut().find(|session| session.status != SessionStatus::Completed)
+    }
+
+    fn session_mut(&mut self, id: &str) -> Result<&mut PracticeSession, String> {
+        self.sessions
+            .iter_mut()
+            .find(|session| session.id == id)
+            .ok_or_else(|| format!("Practice session {} not found", id))
+    }
+}
+
+static PRACTICE_LOG: Lazy<Mutex<Option<PracticeLog>>> = Lazy::new(|| Mutex::new(None));
+
+fn with_log<R>(f: impl FnOnce(&mut PracticeLog) -> Result<R, String>) -> Result<R, String> {
+    let mut guard = PRACTICE_LOG.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(PRACTICE_DOCUMENT)?);
+    }
+    let log = guard.as_mut().unwrap();
+    let result = f(log)?;
+    store::save(PRACTICE_DOCUMENT, log)?;
+    Ok(result)
+}
+
+// For background updates that usually find nothing to do; saves only when the closure reports a change
+fn update_log_if_changed(f: impl FnOnce(&mut PracticeLog) -> bool) -> Result<bool, String> {
+    let mut guard = PRACTICE_LOG.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(PRACTICE_DOCUMENT)?);
+    }
+    let log = guard.as_mut().unwrap();
+    let changed = f(log);
+    if changed {
+        store::save(PRACTICE_DOCUMENT, log)?;
+    }
+    Ok(changed)
+}
+
+// Read-only access for the statistics and planning built on top of the sessions
+pub fn read<R>(f: impl FnOnce(&PracticeLog) -> R) -> Result<R, String> {
+    let mut guard = PRACTICE_LOG.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(PRACTICE_DOCUMENT)?);
+    }
+    Ok(f(guard.as_ref().unwrap()))
+}
+
+// Merged duplicates hand their practice history to the piece that was kept
+pub fn repoint_sheet_music(remapped: &HashMap<String, String>) -> Result<(), String> {
+    update_log_if_changed(|log| {
+        let mut changed = false;
+        for session in log.sessions.iter_mut() {
+            if let Some(target) = remapped.get(&session.sheet_music_id) {
+                session.sheet_music_id = target.clone();
+                changed = true;
+            }
+        }
+        changed
+    })?;
+    Ok(())
+}
+
+fn emit_session(app: &AppHandle, session: &PracticeSession) {
+    if let Err(e) = app.emit("practice-session", session) {
+        println!("Failed to emit practice session: {}", e);
+    }
+}
+
+fn update_active(app: &AppHandle, f: impl FnOnce(&mut PracticeSession) -> Result<(), String>) -> Result<PracticeSession, String> {
+    let session = with_log(|log| {
+        let session = log.active_mut().ok_or_else(|| "No practice session is running".to_string())?;
+        f(session)?;
+        Ok(session.clone())
+    })?;
+    emit_session(app, &session);
+    Ok(session)
+}
+
+// A session left running when the app went away is paused at its last heartbeat
+pub fn start() {
+    let now = Utc::now();
+    let recovered = with_log(|log| {
+        let stale = Duration::from_std(HEARTBEAT_INTERVAL * 2).unwrap();
+        Ok(match log.active_mut() {
+            Some(session) if session.status == SessionStatus::Running => {
+                let last_seen = session.heartbeat_at.or(session.running_since).unwrap_or(now);
+                if now - last_seen > stale {
+                    session.pause_at(last_seen);
+                    Some(session.id.clone())
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+    });
+    match recovered {
+        Ok(Some(id)) => println!("Paused practice session {} left running by the last run", id),
+        Ok(None) => {}
+        Err(e) => println!("Failed to check for an unfinished practice session: {}", e),
+    }
+
+    thread::spawn(|| loop {
+        thread::sleep(HEARTBEAT_INTERVAL);
+        let result = update_log_if_changed(|log| match log.active_mut().filter(|session| session.status == SessionStatus::Running) {
+            Some(session) => {
+                session.heartbeat_at = Some(Utc::now());
+                true
+            }
+            None => false,
+        });
+        if let Err(e) = result {
+            println!("Failed to record practice heartbeat: {}", e);
+        }
+    });
+}
+
+// Only one session runs at a time; stop the current one before starting another
+#[tauri::command]
+pub fn start_practice_session(
+    app: AppHandle,
+    sheet_music_id: String,
+    event_id: Option<String>,
+    occurrence_start: Option<DateTime<Utc>>,
+) -> Result<PracticeSession, String> {
+    library::read(|library| library.require_item(&sheet_music_id))??;
+    if let Some(event_id) = &event_id {
+        let repeats = calendar::read(|calendar| calendar.event(event_id).map(|event| event.rrule.is_some()))?
+            .ok_or_else(|| format!("Event {} not found", event_id))?;
+        if repeats && occurrence_start.is_none() {
+            return Err(format!("Event {} repeats; say which occurrence this session is for", event_id));
+        }
+    }
+    let now = Utc::now();
+    let session = with_log(|log| {
+        if let Some(active) = log.active_mut() {
+            return Err(format!("Practice session {} is still going", active.id));
+        }
+        let session = PracticeSession {
+            id: library::new_id(),
+            sheet_music_id,
+            event_id,
+            occurrence_start,
+            status: SessionStatus::Running,
+            started_at: now,
+            ended_at: None,
+            active_seconds: 0,
+            running_since: Some(now),
+            tempo_notes: Vec::new(),
+            notes: String::new(),
+            heartbeat_at: Some(now),
+        };
+        log.sessions.push(session.clone());
+        Ok(session)
+    })?;
+    println!("Started practice session {} for {}", session.id, session.sheet_music_id);
+    emit_session(&app, &session);
+    Ok(session)
+}
+
+// What the practice view shows after a reload
+#[tauri::command]
+pub fn get_active_practice_session() -> Result<Option<PracticeSession>, String> {
+    read(|log| log.sessions.iter().find(|session| session.status != SessionStatus::Completed).cloned())
+}
+
+#[tauri::command]
+pub fn pause_practice_session(app: AppHandle) -> Result<PracticeSession, String> {
+    update_active(&app, |session| {
+        if session.status == SessionStatus::Running {
+            session.pause_at(Utc::now());
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn resume_practice_session(app: AppHandle) -> Result<PracticeSession, String> {
+    update_active(&app, |session| {
+        if session.status == SessionStatus::Paused {
+            let now = Utc::now();
+            session.status = SessionStatus::Running;
+            session.running_since = Some(now);
+            session.heartbeat_at = Some(now);
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn add_practice_tempo_note(app: AppHandle, bpm: u32, passage: Option<String>) -> Result<PracticeSession, String> {
+    if bpm == 0 {
+        return Err("Tempo must be above 0 BPM".to_string());
+    }
+    update_active(&app, |session| {
+        session.tempo_notes.push(TempoNote {
+            bpm,
+            passage: passage.filter(|passage| !passage.trim().is_empty()),
+            recorded_at: Utc::now(),
+        });
+        Ok(())
+    })
+}
+
+// Completes the session and, when it was for a calendar event, marks that event done
+#[tauri::command]
+pub fn stop_practice_session(app: AppHandle, notes: Option<String>) -> Result<PracticeSession, String> {
+    let session = update_active(&app, |session| {
+        let now = Utc::now();
+        session.pause_at(now);
+        session.status = SessionStatus::Completed;
+        session.ended_at = Some(now);
+        if let Some(notes) = notes {
+            session.notes = notes;
+        }
+        Ok(())
+    })?;
+    println!("Finished practice session {} after {}s", session.id, session.active_seconds);
+
+    if let Some(event_id) = &session.event_id {
+        if let Err(e) = calendar::update(|calendar| calendar.complete(event_id, session.occurrence_start)) {
+            println!("Failed to mark event {} as completed: {}", event_id, e);
+        }
+    }
+    Ok(session)
+}
+
+#[tauri::command]
+pub fn list_practice_sessions(sheet_music_id: Option<String>) -> Result<Vec<PracticeSession>, String> {
+    read(|log| {
+        let mut sessions: Vec<PracticeSession> = log
+            .sessions
+            .iter()
+            .filter(|session| sheet_music_id.as_ref().map_or(true, |id| &session.sheet_music_id == id))
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.started_at));
+        sessions
+    })
+}
+
+// Edits the notes of any session, finished ones included
+#[tauri::command]
+pub fn update_practice_session_notes(
+    id: String,
+    notes: Option<String>,
+    tempo_notes: Option<Vec<TempoNote>>,
+) -> Result<PracticeSession, String> {
+    with_log(|log| {
+        let session = log.session_mut(&id)?;
+        if let Some(notes) = notes {
+            session.notes = notes;
+        }
+        if let Some(tempo_notes) = tempo_notes {
+            session.tempo_notes = tempo_notes;
+        }
+        Ok(session.clone())
+    })
+}
+
+#[tauri::command]
+pub fn delete_practice_session(id: String) -> Result<(), String> {
+    with_log(|log| {
+        let index = log
+            .sessions
+            .iter()
+            .position(|session| session.id == id)
+            .ok_or_else(|| format!("Practice session {} not found", id))?;
+        log.sessions.remove(index);
+        Ok(())
+    })?;
+    recordings::unlink_session(&id)
+}
@@ -0,0 +1,689 @@
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::scheduling::TimeWindow;
+use crate::{library, practice, store};
+
+const RECORDINGS_DOCUMENT: &str = "recordings";
+const RECORDINGS_DIR: &str = "recordings";
+const DEFAULT_QUOTA_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+// Enough detail to zoom in on a few seconds without the peak files getting big
+const PEAKS_PER_SECOND: u32 = 50;
+
+fn default_quota_bytes() -> u64 {
+    DEFAULT_QUOTA_BYTES
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum AudioFormat {
+    Wav,
+    Flac,
+    Opus,
+}
+
+impl AudioFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "wav",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Opus => "opus",
+        }
+    }
+
+    // From the first bytes of the file rather than its extension, which recorders get wrong
+    fn sniff(header: &[u8]) -> Result<AudioFormat, String> {
+        if header.len() >= 12 && &header[0..4] == b"RIFF" && &header[8..12] == b"WAVE" {
+            Ok(AudioFormat::Wav)
+        } else if header.starts_with(b"fLaC") {
+            Ok(AudioFormat::Flac)
+        } else if header.starts_with(b"OggS") && header.windows(8).any(|window| window == b"OpusHead") {
+            Ok(AudioFormat::Opus)
+        } else {
+            Err("Unsupported audio; recordings have to be WAV, FLAC or Ogg Opus".to_string())
+        }
+    }
+}
+
+// Min and max sample per bucket, the shape a waveform view draws
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Waveform {
+    pub peaks_per_second: f64,
+    pub peaks: Vec<[f32; 2]>,
+}
+
+impl Waveform {
+    // Merges buckets so the result is at most `width` peaks wide
+    fn downsample(&self, width: usize) -> Waveform {
+        if width == 0 || self.peaks.len() <= width {
+            return self.clone();
+        }
+        let per_bucket = self.peaks.len().div_ceil(width);
+        Waveform {
+            peaks_per_second: self.peaks_per_second / per_bucket as f64,
+            peaks: self
+                .peaks
+                .chunks(per_bucket)
+                .map(|chunk| {
+                    chunk.iter().fold([f32::MAX, f32::MIN], |[min, max], [low, high]| [min.min(*low), max.max(*high)])
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Recording {
+    pub id: String,
+    pub sheet_music_id: String,
+    pub session_id: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub notes: String,
+    // Starred recordings are never pruned to make room
+    #[serde(default)]
+    pub starred: bool,
+    pub format: AudioFormat,
+    pub file_name: String,
+    pub size: u64,
+    pub duration_seconds: f64,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub recorded_at: DateTime<Utc>,
+    pub imported_at: DateTime<Utc>,
+    pub has_waveform: bool,
+}
+
+// A recording as the frontend sees it, with the file it can play through convertFileSrc
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingFile {
+    #[serde(flatten)]
+    pub recording: Recording,
+    pub path: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordingIndex {
+    #[serde(default = "default_quota_bytes")]
+    quota_bytes: u64,
+    // When set, the oldest unstarred recordings make way for new ones instead of the new one being refused
+    #[serde(default)]
+    auto_prune: bool,
+    recordings: Vec<Recording>,
+}
+
+impl Default for RecordingIndex {
+    fn default() -> Self {
+        RecordingIndex {
+            quota_bytes: DEFAULT_QUOTA_BYTES,
+            auto_prune: false,
+            recordings: Vec::new(),
+        }
+    }
+}
+
+impl RecordingIndex {
+    fn used_bytes(&self) -> u64 {
+        self.recordings.iter().map(|recording| recording.size).sum()
+    }
+
+    fn recording_mut(&mut self, id: &str) -> Result<&mut Recording, String> {
+        self.recordings
+            .iter_mut()
+            .find(|recording| recording.id == id)
+            .ok_or_else(|| format!("Recording {} not found", id))
+    }
+
+    // Removes the oldest unstarred recordings until `incoming` more bytes fit or nothing prunable is left
+    fn prune(&mut self, incoming: u64) -> Vec<Recording> {
+        let mut used = self.used_bytes();
+        let mut candidates: Vec<(DateTime<Utc>, String)> = self
+            .recordings
+            .iter()
+            .filter(|recording| !recording.starred)
+            .map(|recording| (recording.recorded_at, recording.id.clone()))
+            .collect();
+        candidates.sort();
+        let mut pruned = Vec::new();
+        for (_, id) in candidates {
+            if used + incoming <= self.quota_bytes {
+                break;
+            }
+            let index = self.recordings.iter().position(|recording| recording.id == id).unwrap();
+            let recording = self.recordings.remove(index);
+            used -= recording.size;
+            pruned.push(recording);
+        }
+        pruned
+    }
+
+    // Makes room for `incoming` bytes, pruning if allowed; returns what was pruned
+    fn make_room(&mut self, incoming: u64) -> Result<Vec<Recording>, String> {
+        let used = self.used_bytes();
+        if used + incoming <= self.quota_bytes {
+            return Ok(Vec::new());
+        }
+        if !self.auto_prune {
+            return Err(format!(
+                "Recording storage is full ({:.1} of {:.1} MB used); delete some recordings or raise the limit",
+                used as f64 / 1e6,
+                self.quota_bytes as f64 / 1e6
+            ));
+        }
+        let starred: u64 = self.recordings.iter().filter(|recording| recording.starred).map(|recording| recording.size).sum();
+        if starred + incoming > self.quota_bytes {
+            return Err("Recording storage is full of starred recordings; unstar some or raise the limit".to_string());
+        }
+        Ok(self.prune(incoming))
+    }
+}
+
+static RECORDING_INDEX: Lazy<Mutex<Option<RecordingIndex>>> = Lazy::new(|| Mutex::new(None));
+
+fn with_index<R>(f: impl FnOnce(&mut RecordingIndex) -> Result<R, String>) -> Result<R, String> {
+    let mut guard = RECORDING_INDEX.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(RECORDINGS_DOCUMENT)?);
+    }
+    let index = guard.as_mut().unwrap();
+    let result = f(index)?;
+    store::save(RECORDINGS_DOCUMENT, index)?;
+    Ok(result)
+}
+
+fn read<R>(f: impl FnOnce(&RecordingIndex) -> R) -> Result<R, String> {
+    let mut guard = RECORDING_INDEX.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(RECORDINGS_DOCUMENT)?);
+    }
+    Ok(f(guard.as_ref().unwrap()))
+}
+
+fn recording_path(file_name: &str) -> Result<PathBuf, String> {
+    Ok(store::sub_dir(RECORDINGS_DIR)?.join(file_name))
+}
+
+fn peaks_path(id: &str) -> Result<PathBuf, String> {
+    Ok(store::sub_dir(RECORDINGS_DIR)?.join(format!("{}.peaks.json", id)))
+}
+
+fn remove_files(recording: &Recording) {
+    for path in [recording_path(&recording.file_name), peaks_path(&recording.id)].into_iter().flatten() {
+        if path.exists() {
+            if let Err(e) = fs::remove_file(&path) {
+                println!("Failed to remove {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+fn with_path(recording: Recording) -> Result<RecordingFile, String> {
+    let path = recording_path(&recording.file_name)?.to_string_lossy().to_string();
+    Ok(RecordingFile { recording, path })
+}
+
+struct AudioInfo {
+    duration_seconds: f64,
+    sample_rate: u32,
+    channels: u16,
+    // None when the format can't be decoded here
+    waveform: Option<Waveform>,
+}
+
+// Folds interleaved samples in -1..1 into min/max buckets
+struct PeakBuilder {
+    samples_per_peak: usize,
+    count: usize,
+    current: [f32; 2],
+    peaks: Vec<[f32; 2]>,
+}
+
+impl PeakBuilder {
+    fn new(sample_rate: u32, channels: u16) -> PeakBuilder {
+        PeakBuilder {
+            samples_per_peak: ((sample_rate / PEAKS_PER_SECOND) as usize * channels as usize).max(1),
+            count: 0,
+            current: [0.0, 0.0],
+            peaks: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, sample: f32) {
+        self.current = [self.current[0].min(sample), self.current[1].max(sample)];
+        self.count += 1;
+        if self.count == self.samples_per_peak {
+            self.peaks.push(self.current);
+            self.current = [0.0, 0.0];
+            self.count = 0;
+        }
+    }
+
+    fn finish(mut self) -> Waveform {
+        if self.count > 0 {
+            self.peaks.push(self.current);
+        }
+        Waveform {
+            peaks_per_second: PEAKS_PER_SECOND as f64,
+            peaks: self.peaks,
+        }
+    }
+}
+
+fn read_wav(path: &Path) -> Result<AudioInfo, String> {
+    let mut reader = hound::WavReader::open(path).map_err(|e| format!("Failed to read WAV: {}", e))?;
+    let spec = reader.spec();
+    let mut peaks = PeakBuilder::new(spec.sample_rate, spec.channels);
+    match spec.sample_format {
+        hound::SampleFormat::Float => {
+            for sample in reader.samples::<f32>() {
+                peaks.push(sample.map_err(|e| format!("Failed to read WAV: {}", e))?);
+            }
+        }
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            for sample in reader.samples::<i32>() {
+                peaks.push(sample.map_err(|e| format!("Failed to read WAV: {}", e))? as f32 / scale);
+            }
+        }
+    }
+    Ok(AudioInfo {
+        duration_seconds: reader.duration() as f64 / spec.sample_rate as f64,
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+        waveform: Some(peaks.finish()),
+    })
+}
+
+fn read_flac(path: &Path) -> Result<AudioInfo, String> {
+    let mut reader = claxon::FlacReader::open(path).map_err(|e| format!("Failed to read FLAC: {}", e))?;
+    let info = reader.streaminfo();
+    let scale = (1i64 << (info.bits_per_sample - 1)) as f32;
+    let mut peaks = PeakBuilder::new(info.sample_rate, info.channels as u16);
+    let mut samples: u64 = 0;
+    for sample in reader.samples() {
+        peaks.push(sample.map_err(|e| format!("Failed to read FLAC: {}", e))? as f32 / scale);
+        samples += 1;
+    }
+    Ok(AudioInfo {
+        duration_seconds: info.samples.unwrap_or(samples / info.channels as u64) as f64 / info.sample_rate as f64,
+        sample_rate: info.sample_rate,
+        channels: info.channels as u16,
+        waveform: Some(peaks.finish()),
+    })
+}
+
+// Only the Ogg framing is read: the OpusHead header for the layout and the last granule position for the
+// length. There's no Opus decoder in the backend, so the peaks come from the webview when it has them.
+fn read_opus(path: &Path) -> Result<AudioInfo, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut reader = ogg::PacketReader::new(BufReader::new(file));
+    let head = reader
+        .read_packet()
+        .map_err(|e| format!("Failed to read Ogg: {}", e))?
+        .filter(|packet| packet.data.len() >= 19 && packet.data.starts_with(b"OpusHead"))
+        .ok_or_else(|| "Not an Ogg Opus file".to_string())?;
+    let channels = head.data[9] as u16;
+    let pre_skip = u16::from_le_bytes([head.data[10], head.data[11]]) as u64;
+    let input_rate = u32::from_le_bytes([head.data[12], head.data[13], head.data[14], head.data[15]]);
+    let stream = head.stream_serial();
+    let mut last_granule = 0;
+    while let Some(packet) = reader.read_packet().map_err(|e| format!("Failed to read Ogg: {}", e))? {
+        if packet.stream_serial() == stream && packet.last_in_page() {
+            last_granule = packet.absgp_page();
+        }
+    }
+    Ok(AudioInfo {
+        // Opus granule positions always count at 48 kHz
+        duration_seconds: last_granule.saturating_sub(pre_skip) as f64 / 48_000.0,
+        sample_rate: if input_rate > 0 { input_rate } else { 48_000 },
+        channels,
+        waveform: None,
+    })
+}
+
+fn probe(path: &Path) -> Result<(AudioFormat, AudioInfo), String> {
+    let mut header = [0u8; 64];
+    let read = File::open(path)
+        .and_then(|mut file| file.read(&mut header))
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let format = AudioFormat::sniff(&header[..read])?;
+    let info = match format {
+        AudioFormat::Wav => read_wav(path)?,
+        AudioFormat::Flac => read_flac(path)?,
+        AudioFormat::Opus => read_opus(path)?,
+    };
+    Ok((format, info))
+}
+
+pub struct NewRecording {
+    pub sheet_music_id: String,
+    pub session_id: Option<String>,
+    pub title: Option<String>,
+    pub recorded_at: Option<DateTime<Utc>>,
+    // Peaks worked out by the webview, used for formats the backend can't decode
+    pub waveform: Option<Waveform>,
+}
+
+// Checks the links and picks a default recording time: the session's start, if there is one
+fn check_links(new: &NewRecording) -> Result<Option<DateTime<Utc>>, String> {
+    library::read(|library| library.require_item(&new.sheet_music_id))??;
+    let Some(session_id) = &new.session_id else {
+        return Ok(None);
+    };
+    let session = practice::read(|log| log.sessions.iter().find(|session| &session.id == session_id).cloned())?
+        .ok_or_else(|| format!("Practice session {} not found", session_id))?;
+    if session.sheet_music_id != new.sheet_music_id {
+        return Err(format!("Practice session {} is for a different piece", session_id));
+    }
+    Ok(Some(session.started_at))
+}
+
+// Takes ownership of an audio file already in the recordings directory under a temporary name
+fn add(staged: &Path, new: NewRecording, fallback_time: DateTime<Utc>) -> Result<RecordingFile, String> {
+    let result = (|| {
+        let session_time = check_links(&new)?;
+        let (format, info) = probe(staged)?;
+        let size = fs::metadata(staged).map_err(|e| e.to_string())?.len();
+        let id = library::new_id();
+        let waveform = info.waveform.or(new.waveform);
+        if let Some(waveform) = &waveform {
+            let data = serde_json::to_vec(waveform).map_err(|e| e.to_string())?;
+            fs::write(peaks_path(&id)?, data).map_err(|e| format!("Failed to write waveform: {}", e))?;
+        }
+        let recording = Recording {
+            title: new.title.filter(|title| !title.trim().is_empty()).unwrap_or_else(|| "Recording".to_string()),
+            sheet_music_id: new.sheet_music_id,
+            session_id: new.session_id,
+            notes: String::new(),
+            starred: false,
+            format,
+            file_name: format!("{}.{}", id, format.extension()),
+            size,
+            duration_seconds: info.duration_seconds,
+            sample_rate: info.sample_rate,
+            channels: info.channels,
+            recorded_at: new.recorded_at.or(session_time).unwrap_or(fallback_time),
+            imported_at: Utc::now(),
+            has_waveform: waveform.is_some(),
+            id,
+        };
+        let target = recording_path(&recording.file_name)?;
+        let pruned = with_index(|index| {
+            fs::rename(staged, &target).map_err(|e| format!("Failed to store recording: {}", e))?;
+            let pruned = index.make_room(size).inspect_err(|_| {
+                let _ = fs::remove_file(&target);
+            })?;
+            index.recordings.push(recording.clone());
+            Ok(pruned)
+        });
+        let pruned = match pruned {
+            Ok(pruned) => pruned,
+            Err(e) => {
+                let _ = fs::remove_file(peaks_path(&recording.id)?);
+                return Err(e);
+            }
+        };
+        for old in &pruned {
+            println!("Pruned recording {} to stay within the storage limit", old.id);
+            remove_files(old);
+        }
+        println!("Stored recording {} for {}", recording.id, recording.sheet_music_id);
+        with_path(recording)
+    })();
+    if staged.exists() {
+        let _ = fs::remove_file(staged);
+    }
+    result
+}
+
+fn staging_path() -> Result<PathBuf, String> {
+    Ok(store::sub_dir(RECORDINGS_DIR)?.join(format!("{}.tmp", library::new_id())))
+}
+
+// Copies an audio file from anywhere on disk into the app's storage
+#[tauri::command]
+pub async fn import_recording(
+    path: String,
+    sheet_music_id: String,
+    session_id: Option<String>,
+    title: Option<String>,
+    recorded_at: Option<DateTime<Utc>>,
+) -> Result<RecordingFile, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let source = Path::new(&path);
+        let modified = fs::metadata(source)
+            .and_then(|metadata| metadata.modified())
+            .map(DateTime::<Utc>::from)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let staged = staging_path()?;
+        fs::copy(source, &staged).map_err(|e| format!("Failed to copy {}: {}", path, e))?;
+        let title = title.or_else(|| source.file_stem().map(|stem| stem.to_string_lossy().to_string()));
+        add(
+            &staged,
+            NewRecording {
+                sheet_music_id,
+                session_id,
+                title,
+                recorded_at,
+                waveform: None,
+            },
+            modified,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// Recordings of removed pieces go with them, files included; the library calls this whenever items disappear
+pub fn remove_for_sheet_music(ids: &[String]) -> Result<(), String> {
+    if ids.is_empty() || !read(|index| index.recordings.iter().any(|recording| ids.contains(&recording.sheet_music_id)))? {
+        return Ok(());
+    }
+    let removed: Vec<Recording> = with_index(|index| {
+        let (removed, kept) = std::mem::take(&mut index.recordings)
+            .into_iter()
+            .partition(|recording| ids.contains(&recording.sheet_music_id));
+        index.recordings = kept;
+        Ok(removed)
+    })?;
+    for recording in &removed {
+        println!("Removed recording {} along with its sheet music", recording.id);
+        remove_files(recording);
+    }
+    Ok(())
+}
+
+// Moves recordings over to the piece that replaces another, e.g. when duplicates are merged
+pub fn repoint_sheet_music(remapped: &HashMap<String, String>) -> Result<(), String> {
+    with_index(|index| {
+        for recording in index.recordings.iter_mut() {
+            if let Some(target) = remapped.get(&recording.sheet_music_id) {
+                recording.sheet_music_id = target.clone();
+            }
+        }
+        Ok(())
+    })
+}
+
+// A deleted practice session leaves its recordings with the piece
+pub fn unlink_session(session_id: &str) -> Result<(), String> {
+    if !read(|index| index.recordings.iter().any(|recording| recording.session_id.as_deref() == Some(session_id)))? {
+        return Ok(());
+    }
+    with_index(|index| {
+        for recording in index.recordings.iter_mut().filter(|recording| recording.session_id.as_deref() == Some(session_id)) {
+            recording.session_id = None;
+        }
+        Ok(())
+    })
+}
+
+// Takes a recording made in the app; `data` is the whole file, base64 encoded
+#[tauri::command]
+pub async fn save_recording(
+    data: String,
+    sheet_music_id: String,
+    session_id: Option<String>,
+    title: Option<String>,
+    waveform: Option<Waveform>,
+) -> Result<RecordingFile, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let bytes = base64::engine::general_purpose::STANDARD.decode(data).map_err(|e| format!("Invalid recording data: {}", e))?;
+        let staged = staging_path()?;
+        fs::write(&staged, bytes).map_err(|e| format!("Failed to write recording: {}", e))?;
+        add(
+            &staged,
+            NewRecording {
+                sheet_music_id,
+                session_id,
+                title,
+                recorded_at: None,
+                waveform,
+            },
+            Utc::now(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// Newest first, optionally for one piece and recorded within `range`
+#[tauri::command]
+pub fn list_recordings(sheet_music_id: Option<String>, range: Option<TimeWindow>) -> Result<Vec<RecordingFile>, String> {
+    let mut recordings: Vec<Recording> = read(|index| {
+        index
+            .recordings
+            .iter()
+            .filter(|recording| sheet_music_id.as_ref().map_or(true, |id| &recording.sheet_music_id == id))
+            .filter(|recording| range.as_ref().map_or(true, |range| recording.recorded_at >= range.start && recording.recorded_at < range.end))
+            .cloned()
+            .collect()
+    })?;
+    recordings.sort_by_key(|recording| std::cmp::Reverse(recording.recorded_at));
+    recordings.into_iter().map(with_path).collect()
+}
+
+// Recordings grouped by piece, each group newest first, for the library's recordings tab
+#[tauri::command]
+pub fn list_recordings_by_piece() -> Result<BTreeMap<String, Vec<RecordingFile>>, String> {
+    let mut groups: BTreeMap<String, Vec<RecordingFile>> = BTreeMap::new();
+    for recording in list_recordings(None, None)? {
+        groups.entry(recording.recording.sheet_music_id.clone()).or_default().push(recording);
+    }
+    Ok(groups)
+}
+
+// The stored peaks, merged down to at most `width` buckets when given
+#[tauri::command]
+pub fn get_recording_waveform(id: String, width: Option<usize>) -> Result<Option<Waveform>, String> {
+    let has_waveform = read(|index| {
+        index
+            .recordings
+            .iter()
+            .find(|recording| recording.id == id)
+            .map(|recording| recording.has_waveform)
+            .ok_or_else(|| format!("Recording {} not found", id))
+    })??;
+    if !has_waveform {
+        return Ok(None);
+    }
+    let path = peaks_path(&id)?;
+    let data = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let waveform: Waveform = serde_json::from_slice(&data).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    Ok(Some(match width {
+        Some(width) => waveform.downsample(width),
+        None => waveform,
+    }))
+}
+
+#[tauri::command]
+pub fn update_recording(
+    id: String,
+    title: Option<String>,
+    notes: Option<String>,
+    starred: Option<bool>,
+) -> Result<RecordingFile, String> {
+    let recording = with_index(|index| {
+        let recording = index.recording_mut(&id)?;
+        if let Some(title) = title.filter(|title| !title.trim().is_empty()) {
+            recording.title = title;
+        }
+        if let Some(notes) = notes {
+            recording.notes = notes;
+        }
+        if let Some(starred) = starred {
+            recording.starred = starred;
+        }
+        Ok(recording.clone())
+    })?;
+    with_path(recording)
+}
+
+#[tauri::command]
+pub fn delete_recording(id: String) -> Result<(), String> {
+    let recording = with_index(|index| {
+        let position = index
+            .recordings
+            .iter()
+            .position(|recording| recording.id == id)
+            .ok_or_else(|| format!("Recording {} not found", id))?;
+        Ok(index.recordings.remove(position))
+    })?;
+    remove_files(&recording);
+    Ok(())
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingStorage {
+    pub used_bytes: u64,
+    pub quota_bytes: u64,
+    pub auto_prune: bool,
+    pub recordings: usize,
+    pub starred_bytes: u64,
+    pub bytes_by_piece: BTreeMap<String, u64>,
+}
+
+#[tauri::command]
+pub fn get_recording_storage() -> Result<RecordingStorage, String> {
+    read(|index| {
+        let mut bytes_by_piece: BTreeMap<String, u64> = BTreeMap::new();
+        for recording in &index.recordings {
+            *bytes_by_piece.entry(recording.sheet_music_id.clone()).or_default() += recording.size;
+        }
+        RecordingStorage {
+            used_bytes: index.used_bytes(),
+            quota_bytes: index.quota_bytes,
+            auto_prune: index.auto_prune,
+            recordings: index.recordings.len(),
+            starred_bytes: index.recordings.iter().filter(|recording| recording.starred).map(|recording| recording.size).sum(),
+            bytes_by_piece,
+        }
+    })
+}
+
+// Lowering the limit below what's stored only prunes when auto-prune is on; otherwise it just blocks new recordings
+#[tauri::command]
+pub fn set_recording_quota(quota_bytes: u64, auto_prune: bool) -> Result<RecordingStorage, String> {
+    let pruned = with_index(|index| {
+        index.quota_bytes = quota_bytes;
+        index.auto_prune = auto_prune;
+        Ok(if auto_prune { index.prune(0) } else { Vec::new() })
+    })?;
+    for recording in &pruned {
+        println!("Pruned recording {} to stay within the storage limit", recording.id);
+        remove_files(recording);
+    }
+    get_recording_storage()
+}
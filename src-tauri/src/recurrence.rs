@@ -0,0 +1,405 @@
+use chrono::{DateTime, Datelike, Days, Duration, LocalResult, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use std::fmt;
+use std::str::FromStr;
+
+// Guards against rules that never produce an occurrence, e.g. the 5th Monday every 12 months from a short month
+const MAX_ITERATIONS: usize = 100_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+// A BYDAY entry; the ordinal picks e.g. the 2nd Tuesday (2TU) or last Friday (-1FR) of a month
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ByDay {
+    pub ordinal: Option<i32>,
+    pub weekday: Weekday,
+}
+
+// UNTIL is either an exact instant or, for date-only and floating values, a wall-clock time in the event's zone
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Until {
+    Utc(DateTime<Utc>),
+    Floating(NaiveDateTime),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub by_day: Vec<ByDay>,
+    pub count: Option<u32>,
+    pub until: Option<Until>,
+}
+
+const WEEKDAY_CODES: [(&str, Weekday); 7] = [
+    ("MO", Weekday::Mon),
+    ("TU", Weekday::Tue),
+    ("WE", Weekday::Wed),
+    ("TH", Weekday::Thu),
+    ("FR", Weekday::Fri),
+    ("SA", Weekday::Sat),
+    ("SU", Weekday::Sun),
+];
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    WEEKDAY_CODES.iter().find(|(_, day)| *day == weekday).map(|(code, _)| *code).unwrap()
+}
+
+fn parse_by_day(value: &str) -> Result<ByDay, String> {
+    let value = value.trim().to_ascii_uppercase();
+    if value.len() < 2 {
+        return Err(format!("Invalid BYDAY value {}", value));
+    }
+    let (ordinal, code) = value.split_at(value.len() - 2);
+    let weekday = WEEKDAY_CODES
+        .iter()
+        .find(|(day_code, _)| *day_code == code)
+        .map(|(_, day)| *day)
+        .ok_or_else(|| format!("Invalid BYDAY value {}", value))?;
+    let ordinal = if ordinal.is_empty() {
+        None
+    } else {
+        let ordinal: i32 = ordinal.trim_start_matches('+').parse().map_err(|_| format!("Invalid BYDAY value {}", value))?;
+        if ordinal == 0 || ordinal.abs() > 53 {
+            return Err(format!("Invalid BYDAY value {}", value));
+        }
+        Some(ordinal)
+    };
+    Ok(ByDay { ordinal, weekday })
+}
+
+// Accepts the DATE and DATE-TIME forms RFC 5545 allows for UNTIL and EXDATE
+pub fn parse_ical_datetime(value: &str) -> Result<Until, String> {
+    let value = value.trim();
+    if let Some(utc) = value.strip_suffix('Z') {
+        return NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .map(|naive| Until::Utc(naive.and_utc()))
+            .map_err(|_| format!("Invalid date-time {}", value));
+    }
+    if let Ok(naive) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return Ok(Until::Floating(naive));
+    }
+    // A date-only UNTIL still includes occurrences later that day
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .map(|date| Until::Floating(date.and_time(NaiveTime::from_hms_opt(23, 59, 59).unwrap())))
+        .map_err(|_| format!("Invalid date {}", value))
+}
+
+impl FromStr for RecurrenceRule {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let text = text.strip_prefix("RRULE:").unwrap_or(text);
+        let mut frequency = None;
+        let mut rule = RecurrenceRule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            by_day: Vec::new(),
+            count: None,
+            until: None,
+        };
+
+        for part in text.split(';').filter(|part| !part.trim().is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(|| format!("Invalid RRULE part {}", part))?;
+            match key.trim().to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.trim().to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        other => return Err(format!("Unsupported frequency {}", other)),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value.trim().parse().map_err(|_| format!("Invalid INTERVAL {}", value))?;
+                    if rule.interval == 0 {
+                        return Err("INTERVAL must be at least 1".to_string());
+                    }
+                }
+                "BYDAY" => rule.by_day = value.split(',').map(parse_by_day).collect::<Result<_, _>>()?,
+                "COUNT" => rule.count = Some(value.trim().parse().map_err(|_| format!("Invalid COUNT {}", value))?),
+                "UNTIL" => rule.until = Some(parse_ical_datetime(value)?),
+                // Weeks always start on Monday here, which is also the RFC default
+                "WKST" => {}
+                other => return Err(format!("Unsupported RRULE part {}", other)),
+            }
+        }
+
+        rule.frequency = frequency.ok_or_else(|| "RRULE is missing FREQ".to_string())?;
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err("RRULE can't have both COUNT and UNTIL".to_string());
+        }
+        if rule.frequency == Frequency::Yearly && !rule.by_day.is_empty() {
+            return Err("BYDAY isn't supported for yearly rules".to_string());
+        }
+        if rule.by_day.iter().any(|day| day.ordinal.is_some()) && rule.frequency != Frequency::Monthly {
+            return Err("Numbered BYDAY values only work with monthly rules".to_string());
+        }
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for RecurrenceRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={}", frequency)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self
+                .by_day
+                .iter()
+                .map(|day| match day.ordinal {
+                    Some(ordinal) => format!("{}{}", ordinal, weekday_code(day.weekday)),
+                    None => weekday_code(day.weekday).to_string(),
+                })
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        match self.until {
+            Some(Until::Utc(until)) => write!(f, ";UNTIL={}", until.format("%Y%m%dT%H%M%SZ"))?,
+            Some(Until::Floating(until)) => write!(f, ";UNTIL={}", until.format("%Y%m%dT%H%M%S"))?,
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+// Wall-clock times that fall into a DST gap move forward by the gap, ambiguous ones take the first instant,
+// the same way RFC 5545 resolves them
+pub fn resolve_local<Tz: TimeZone>(tz: &Tz, naive: NaiveDateTime) -> DateTime<Utc> {
+    for shift in 0..=3 {
+        match tz.from_local_datetime(&(naive + Duration::hours(shift))) {
+            LocalResult::Single(time) => return time.with_timezone(&Utc),
+            // Not every zone implementation orders the two candidates, so compare the instants
+            LocalResult::Ambiguous(first, second) => return first.with_timezone(&Utc).min(second.with_timezone(&Utc)),
+            LocalResult::None => continue,
+        }
+    }
+    naive.and_utc()
+}
+
+fn add_months(date: NaiveDate, months: u32) -> Option<NaiveDate> {
+    date.with_day(1)?.checked_add_months(Months::new(months))
+}
+
+// All days in a month matching a BYDAY entry, honouring its ordinal
+fn month_days(first: NaiveDate, by_day: &[ByDay]) -> Vec<NaiveDate> {
+    let days_in_month: Vec<NaiveDate> = first.iter_days().take_while(|day| day.month() == first.month()).collect();
+    let mut days = Vec::new();
+    for entry in by_day {
+        let matching: Vec<NaiveDate> = days_in_month.iter().copied().filter(|day| day.weekday() == entry.weekday).collect();
+        match entry.ordinal {
+            None => days.extend(matching),
+            Some(ordinal) if ordinal > 0 => days.extend(matching.get(ordinal as usize - 1).copied()),
+            Some(ordinal) => days.extend(matching.len().checked_sub(ordinal.unsigned_abs() as usize).and_then(|index| matching.get(index)).copied()),
+        }
+    }
+    days.sort();
+    days.dedup();
+    days
+}
+
+impl RecurrenceRule {
+    // Candidate dates for the n-th period of the rule, in order
+    fn period_dates(&self, start: NaiveDate, period: u32) -> Vec<NaiveDate> {
+        let step = period.saturating_mul(self.interval);
+        match self.frequency {
+            Frequency::Daily => start
+                .checked_add_days(Days::new(step as u64))
+                .filter(|day| self.by_day.is_empty() || self.by_day.iter().any(|entry| entry.weekday == day.weekday()))
+                .into_iter()
+                .collect(),
+            Frequency::Weekly => {
+                let week_start = start - Duration::days(start.weekday().num_days_from_monday() as i64);
+                let week_start = match week_start.checked_add_days(Days::new(step as u64 * 7)) {
+                    Some(day) => day,
+                    None => return Vec::new(),
+                };
+                let mut weekdays: Vec<Weekday> = if self.by_day.is_empty() {
+                    vec![start.weekday()]
+                } else {
+                    self.by_day.iter().map(|entry| entry.weekday).collect()
+                };
+                weekdays.sort_by_key(|day| day.num_days_from_monday());
+                weekdays.dedup();
+                weekdays
+                    .into_iter()
+                    .map(|day| week_start + Duration::days(day.num_days_from_monday() as i64))
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let first = match add_months(start, step) {
+                    Some(first) => first,
+                    None => return Vec::new(),
+                };
+                if self.by_day.is_empty() {
+                    // Months without that day (e.g. the 31st) are skipped, not clamped
+                    first.with_day(start.day()).into_iter().collect()
+                } else {
+                    month_days(first, &self.by_day)
+                }
+            }
+            // Like the 31st for monthly rules, February 29th only recurs in leap years
+            Frequency::Yearly => NaiveDate::from_ymd_opt(start.year() + step as i32, start.month(), start.day())
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    // Start times of every occurrence before `before`, including the first one; recurrence follows the
+    // wall clock in `tz`, so a 9:00 lesson stays at 9:00 across DST changes
+    pub fn occurrences<Tz: TimeZone>(&self, start: DateTime<Utc>, tz: &Tz, before: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        let local_start = start.with_timezone(tz).naive_local();
+        let until = self.until.map(|until| match until {
+            Until::Utc(until) => until,
+            Until::Floating(until) => resolve_local(tz, until),
+        });
+
+        let mut occurrences = Vec::new();
+        let mut produced = 0;
+        let mut iterations = 0;
+        let mut period = 0;
+        loop {
+            let dates = self.period_dates(local_start.date(), period);
+            period += 1;
+            iterations += 1;
+            if iterations > MAX_ITERATIONS {
+                break;
+            }
+            for date in dates {
+                let naive = date.and_time(local_start.time());
+                if naive < local_start {
+                    continue;
+                }
+                // The first occurrence is always the event itself, even when a DST gap would shift it
+                let occurrence = if naive == local_start { start } else { resolve_local(tz, naive) };
+                if occurrence >= before || until.is_some_and(|until| occurrence > until) {
+                    return occurrences;
+                }
+                if self.count.is_some_and(|count| produced >= count) {
+                    return occurrences;
+                }
+                produced += 1;
+                occurrences.push(occurrence);
+            }
+        }
+        occurrences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Europe::Berlin;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().with_timezone(&Utc)
+    }
+
+    fn expand<Tz: TimeZone>(rule: &str, start: &str, tz: &Tz, before: &str) -> Vec<DateTime<Utc>> {
+        rule.parse::<RecurrenceRule>().unwrap().occurrences(utc(start), tz, utc(before))
+    }
+
+    fn times(texts: &[&str]) -> Vec<DateTime<Utc>> {
+        texts.iter().map(|text| utc(text)).collect()
+    }
+
+    #[test]
+    fn weekly_series_keeps_its_wall_clock_across_both_transitions() {
+        // Berlin springs forward on 29 March 2026 and falls back on 25 October 2026
+        let spring = expand("FREQ=WEEKLY", "2026-03-23T08:00:00Z", &Berlin, "2026-04-07T00:00:00Z");
+        assert_eq!(spring, times(&["2026-03-23T08:00:00Z", "2026-03-30T07:00:00Z", "2026-04-06T07:00:00Z"]));
+
+        let fall = expand("FREQ=WEEKLY", "2026-10-19T07:00:00Z", &Berlin, "2026-11-03T00:00:00Z");
+        assert_eq!(fall, times(&["2026-10-19T07:00:00Z", "2026-10-26T08:00:00Z", "2026-11-02T08:00:00Z"]));
+        assert!(fall.iter().all(|time| time.with_timezone(&Berlin).format("%H:%M").to_string() == "09:00"));
+    }
+
+    #[test]
+    fn daily_series_in_the_gap_and_overlap_moves_forward_and_takes_the_first_instant() {
+        let spring = expand("FREQ=DAILY;COUNT=3", "2026-03-28T01:30:00Z", &Berlin, "2026-04-30T00:00:00Z");
+        // 02:30 doesn't exist on the 29th, so that occurrence is at 03:30 summer time
+        assert_eq!(spring, times(&["2026-03-28T01:30:00Z", "2026-03-29T01:30:00Z", "2026-03-30T00:30:00Z"]));
+
+        let fall = expand("FREQ=DAILY;COUNT=3", "2026-10-24T00:30:00Z", &Berlin, "2026-11-30T00:00:00Z");
+        // 02:30 happens twice on the 25th; the summer time one comes first
+        assert_eq!(fall, times(&["2026-10-24T00:30:00Z", "2026-10-25T00:30:00Z", "2026-10-26T01:30:00Z"]));
+    }
+
+    #[test]
+    fn weekly_by_day_starts_from_the_event_and_fills_each_week() {
+        let occurrences = expand("FREQ=WEEKLY;BYDAY=MO,WE,FR;COUNT=4", "2026-01-07T18:00:00Z", &Utc, "2027-01-01T00:00:00Z");
+        // The Monday before the first Wednesday isn't part of the series
+        assert_eq!(
+            occurrences,
+            times(&["2026-01-07T18:00:00Z", "2026-01-09T18:00:00Z", "2026-01-12T18:00:00Z", "2026-01-14T18:00:00Z"])
+        );
+    }
+
+    #[test]
+    fn monthly_by_day_ordinals_pick_numbered_and_last_weekdays() {
+        let occurrences = expand("FREQ=MONTHLY;BYDAY=2TU,-1FR;COUNT=4", "2026-01-13T18:00:00Z", &Utc, "2027-01-01T00:00:00Z");
+        assert_eq!(
+            occurrences,
+            times(&["2026-01-13T18:00:00Z", "2026-01-30T18:00:00Z", "2026-02-10T18:00:00Z", "2026-02-27T18:00:00Z"])
+        );
+    }
+
+    #[test]
+    fn monthly_series_on_the_31st_skips_short_months() {
+        let occurrences = expand("FREQ=MONTHLY;COUNT=3", "2026-01-31T18:00:00Z", &Utc, "2027-01-01T00:00:00Z");
+        assert_eq!(occurrences, times(&["2026-01-31T18:00:00Z", "2026-03-31T18:00:00Z", "2026-05-31T18:00:00Z"]));
+        // The day of month always comes from the event itself
+        assert_eq!("FREQ=MONTHLY;BYMONTHDAY=15".parse::<RecurrenceRule>(), Err("Unsupported RRULE part BYMONTHDAY".to_string()));
+    }
+
+    #[test]
+    fn count_includes_the_first_occurrence() {
+        let once = expand("FREQ=DAILY;COUNT=1", "2026-01-01T09:00:00Z", &Utc, "2027-01-01T00:00:00Z");
+        assert_eq!(once, times(&["2026-01-01T09:00:00Z"]));
+
+        // `before` is exclusive and cuts the series short of its count
+        let cut = expand("FREQ=DAILY;COUNT=5", "2026-01-01T09:00:00Z", &Utc, "2026-01-03T09:00:00Z");
+        assert_eq!(cut, times(&["2026-01-01T09:00:00Z", "2026-01-02T09:00:00Z"]));
+    }
+
+    #[test]
+    fn until_is_inclusive_and_resolved_in_the_event_zone() {
+        let exact = expand("FREQ=DAILY;UNTIL=20260103T090000Z", "2026-01-01T09:00:00Z", &Utc, "2027-01-01T00:00:00Z");
+        assert_eq!(exact.len(), 3);
+        let earlier = expand("FREQ=DAILY;UNTIL=20260103T085959Z", "2026-01-01T09:00:00Z", &Utc, "2027-01-01T00:00:00Z");
+        assert_eq!(earlier.len(), 2);
+
+        // A date-only UNTIL still includes that evening
+        let date_only = expand("FREQ=DAILY;UNTIL=20260103", "2026-01-01T19:00:00Z", &Utc, "2027-01-01T00:00:00Z");
+        assert_eq!(date_only.last(), Some(&utc("2026-01-03T19:00:00Z")));
+
+        // A floating UNTIL of 09:00 means 09:00 in Berlin, which is 07:00 UTC in summer
+        let floating = expand("FREQ=WEEKLY;UNTIL=20260330T090000", "2026-03-23T08:00:00Z", &Berlin, "2027-01-01T00:00:00Z");
+        assert_eq!(floating, times(&["2026-03-23T08:00:00Z", "2026-03-30T07:00:00Z"]));
+    }
+
+    #[test]
+    fn rejects_count_with_until_and_round_trips_through_display() {
+        assert!("FREQ=DAILY;COUNT=2;UNTIL=20260101".parse::<RecurrenceRule>().is_err());
+        let rule: RecurrenceRule = "RRULE:FREQ=MONTHLY;INTERVAL=2;BYDAY=-1FR;UNTIL=20261231T235959Z".parse().unwrap();
+        assert_eq!(rule.to_string(), "FREQ=MONTHLY;INTERVAL=2;BYDAY=-1FR;UNTIL=20261231T235959Z");
+    }
+}
@@ -0,0 +1,363 @@
+use chrono::{DateTime, Duration, Local, NaiveTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::calendar::{self, EventType, PracticeEvent};
+use crate::{ics, recurrence, store};
+
+const NOTIFICATIONS_DOCUMENT: &str = "notifications";
+// Picks up calendar edits within a minute even when no reminder is due sooner
+const RECHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+const MAX_LEAD_MINUTES: u32 = 7 * 24 * 60;
+// Delivery records are kept a little past the event so a restart right after it doesn't fire again
+const SENT_RETENTION_DAYS: i64 = 2;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHours {
+    // Local wall-clock times; a start after the end spans midnight, e.g. 22:00 to 07:00
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    // When the quiet period that `now` falls in is over
+    fn ends_after(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let local = now.with_timezone(&Local);
+        let mut end = local.date_naive().and_time(self.end);
+        if end <= local.naive_local() {
+            end += Duration::days(1);
+        }
+        recurrence::resolve_local(&Local, end)
+    }
+}
+
+// The toggles from NotificationSettings plus the reminder timing they control
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NotificationPreferences {
+    // Stored for the settings page; email is sent by the server, not the app
+    pub email_notifications: bool,
+    // Emits a "reminder" event for the frontend to show
+    pub in_app_notifications: bool,
+    // Covers Practice events
+    pub practice_reminders: bool,
+    // Shows reminders as desktop notifications
+    pub system_notifications: bool,
+    // Covers lessons, rehearsals, concerts and recitals
+    pub calendar_reminders: bool,
+    pub practice_lead_minutes: Vec<u32>,
+    pub calendar_lead_minutes: Vec<u32>,
+    // All-day events get one reminder at this local time on their first day
+    pub all_day_reminder_time: NaiveTime,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        NotificationPreferences {
+            email_notifications: true,
+            in_app_notifications: true,
+            practice_reminders: true,
+            system_notifications: true,
+            calendar_reminders: true,
+            practice_lead_minutes: vec![15],
+            calendar_lead_minutes: vec![60],
+            all_day_reminder_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            quiet_hours: None,
+        }
+    }
+}
+
+impl NotificationPreferences {
+    fn lead_minutes(&self, event_type: EventType) -> &[u32] {
+        match event_type {
+            EventType::Practice if self.practice_reminders => &self.practice_lead_minutes,
+            EventType::Practice => &[],
+            _ if self.calendar_reminders => &self.calendar_lead_minutes,
+            _ => &[],
+        }
+    }
+
+    fn max_lead(&self) -> Duration {
+        let leads = self.practice_lead_minutes.iter().chain(&self.calendar_lead_minutes);
+        Duration::minutes(leads.max().copied().unwrap_or(0) as i64)
+    }
+
+    fn validate(&mut self) -> Result<(), String> {
+        for leads in [&mut self.practice_lead_minutes, &mut self.calendar_lead_minutes] {
+            if leads.iter().any(|lead| *lead > MAX_LEAD_MINUTES) {
+                return Err("Reminders can be at most 7 days before an event".to_string());
+            }
+            leads.sort_unstable_by(|a, b| b.cmp(a));
+            leads.dedup();
+        }
+        if let Some(quiet_hours) = &self.quiet_hours {
+            if quiet_hours.start == quiet_hours.end {
+                return Err("Quiet hours need to start and end at different times".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct NotificationState {
+    #[serde(default)]
+    preferences: NotificationPreferences,
+    // Reminder keys that were delivered, with the start of the occurrence they were for
+    #[serde(default)]
+    sent: HashMap<String, DateTime<Utc>>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Reminder {
+    pub key: String,
+    pub event_id: String,
+    pub event_type: EventType,
+    pub title: String,
+    pub body: String,
+    pub starts_at: DateTime<Utc>,
+    pub fire_at: DateTime<Utc>,
+    // None for the morning reminder of an all-day event
+    pub lead_minutes: Option<u32>,
+    // Lets the frontend open the right occurrence of a series
+    pub recurring_event_id: Option<String>,
+    pub original_start: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    occurrence: String,
+}
+
+static STATE: Lazy<Mutex<Option<NotificationState>>> = Lazy::new(|| Mutex::new(None));
+static CONTROL: Lazy<Mutex<Option<Sender<()>>>> = Lazy::new(|| Mutex::new(None));
+
+fn with_state<R>(f: impl FnOnce(&mut NotificationState) -> R) -> Result<R, String> {
+    let mut guard = STATE.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(NOTIFICATIONS_DOCUMENT)?);
+    }
+    let state = guard.as_mut().unwrap();
+    let result = f(state);
+    store::save(NOTIFICATIONS_DOCUMENT, state)?;
+    Ok(result)
+}
+
+fn read<R>(f: impl FnOnce(&NotificationState) -> R) -> Result<R, String> {
+    let mut guard = STATE.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(NOTIFICATIONS_DOCUMENT)?);
+    }
+    Ok(f(guard.as_ref().unwrap()))
+}
+
+fn preferences() -> Result<NotificationPreferences, String> {
+    read(|state| state.preferences.clone())
+}
+
+fn sent_keys() -> Result<HashSet<String>, String> {
+    read(|state| state.sent.keys().cloned().collect())
+}
+
+fn describe_until(start: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (start - now).num_minutes().max(0);
+    let local = start.with_timezone(&Local);
+    let at = if local.date_naive() == now.with_timezone(&Local).date_naive() {
+        local.format("%H:%M").to_string()
+    } else {
+        local.format("%a %-d %b, %H:%M").to_string()
+    };
+    match minutes {
+        0 => format!("Starting now ({})", at),
+        1 => format!("Starts in 1 minute ({})", at),
+        2..=89 => format!("Starts in {} minutes ({})", minutes, at),
+        90..=2159 => format!("Starts in about {} hours ({})", (minutes + 30) / 60, at),
+        _ => format!("Starts in {} days ({})", (minutes + 720) / 1440, at),
+    }
+}
+
+fn describe(reminder: &Reminder, now: DateTime<Utc>) -> String {
+    match reminder.lead_minutes {
+        Some(_) => describe_until(reminder.starts_at, now),
+        None => format!("All day, {}", reminder.starts_at.with_timezone(&Local).format("%a %-d %b")),
+    }
+}
+
+// Every reminder the preferences ask for on events that haven't started yet, delivered or not
+fn plan(preferences: &NotificationPreferences, events: &[PracticeEvent], now: DateTime<Utc>) -> Vec<Reminder> {
+    let mut reminders = Vec::new();
+    for event in events.iter().filter(|event| !event.is_completed) {
+        let leads = preferences.lead_minutes(event.event_type);
+        if leads.is_empty() {
+            continue;
+        }
+        let (start, end) = event.span();
+        let series = event.recurring_event_id.as_deref().unwrap_or(&event.id);
+        // Keyed on the actual start so moving an event schedules its reminders again
+        let occurrence = format!("{}@{}", series, start.to_rfc3339());
+        let title = format!("{}: {}", ics::event_type_name(event.event_type), event.title);
+        // Reminders are only worth showing until the start, or the end for all-day events
+        let mut push = |lead_minutes: Option<u32>, fire_at: DateTime<Utc>, deadline: DateTime<Utc>| {
+            if deadline <= now {
+                return;
+            }
+            let key = match lead_minutes {
+                Some(lead) => format!("{}#{}", occurrence, lead),
+                None => format!("{}#day", occurrence),
+            };
+            reminders.push(Reminder {
+                key,
+                event_id: event.id.clone(),
+                event_type: event.event_type,
+                title: title.clone(),
+                body: String::new(),
+                starts_at: start,
+                fire_at,
+                lead_minutes,
+                recurring_event_id: event.recurring_event_id.clone(),
+                original_start: event.original_start,
+                occurrence: occurrence.clone(),
+            });
+        };
+        if event.all_day {
+            let morning = start.with_timezone(&Local).date_naive().and_time(preferences.all_day_reminder_time);
+            push(None, recurrence::resolve_local(&Local, morning), end);
+        } else {
+            for lead in leads {
+                push(Some(*lead), start - Duration::minutes(*lead as i64), start);
+            }
+        }
+    }
+    reminders.sort_by_key(|reminder| reminder.fire_at);
+    reminders
+}
+
+fn upcoming(preferences: &NotificationPreferences, now: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<Reminder>, String> {
+    // All-day events started at local midnight, so look back a day for ones still running
+    let events = calendar::read(|calendar| calendar.in_range(now - Duration::days(1), until))?;
+    Ok(plan(preferences, &events, now))
+}
+
+fn deliver(app: &AppHandle, preferences: &NotificationPreferences, reminder: &Reminder) {
+    println!("Sending reminder for {} ({})", reminder.title, reminder.key);
+    if preferences.system_notifications {
+        if let Err(e) = app.notification().builder().title(&reminder.title).body(&reminder.body).show() {
+            println!("Failed to show notification: {}", e);
+        }
+    }
+    if preferences.in_app_notifications {
+        if let Err(e) = app.emit("reminder", reminder.clone()) {
+            println!("Failed to emit reminder: {}", e);
+        }
+    }
+}
+
+// Sends whatever is due and returns how long the scheduler can sleep
+fn tick(app: &AppHandle) -> Result<std::time::Duration, String> {
+    let now = Utc::now();
+    let preferences = preferences()?;
+    let reminders = upcoming(&preferences, now, now + preferences.max_lead() + Duration::days(1))?;
+    let sent = sent_keys()?;
+    let pending: Vec<&Reminder> = reminders.iter().filter(|reminder| !sent.contains(&reminder.key)).collect();
+
+    let mut next = now + Duration::from_std(RECHECK_INTERVAL).unwrap();
+    let quiet_until = preferences
+        .quiet_hours
+        .filter(|quiet_hours| quiet_hours.contains(now.with_timezone(&Local).time()))
+        .map(|quiet_hours| quiet_hours.ends_after(now));
+
+    let due: Vec<&Reminder> = pending.iter().copied().filter(|reminder| reminder.fire_at <= now).collect();
+    if let Some(quiet_until) = quiet_until {
+        // Held until quiet hours are over; anything whose event has started by then is dropped
+        if !due.is_empty() {
+            next = next.min(quiet_until);
+        }
+    } else if !due.is_empty() {
+        // After a restart several leads of one occurrence can be due at once; only the latest is worth showing
+        let mut latest: HashMap<&str, &Reminder> = HashMap::new();
+        for reminder in &due {
+            latest.insert(&reminder.occurrence, reminder);
+        }
+        for reminder in latest.values() {
+            let mut reminder = (*reminder).clone();
+            reminder.body = describe(&reminder, now);
+            deliver(app, &preferences, &reminder);
+        }
+        with_state(|state| {
+            state.sent.retain(|_, start| *start > now - Duration::days(SENT_RETENTION_DAYS));
+            for reminder in &due {
+                state.sent.insert(reminder.key.clone(), reminder.starts_at);
+            }
+        })?;
+    }
+    if let Some(reminder) = pending.iter().find(|reminder| reminder.fire_at > now) {
+        next = next.min(reminder.fire_at);
+    }
+    Ok((next - now).to_std().unwrap_or_default())
+}
+
+// Recomputes everything from the calendar on each wake, so nothing has to be rescheduled by hand
+pub fn start(app: AppHandle) {
+    let (tx, rx) = mpsc::channel();
+    *CONTROL.lock().unwrap() = Some(tx);
+
+    thread::spawn(move || loop {
+        let wait = tick(&app).unwrap_or_else(|e| {
+            println!("Reminder check failed: {}", e);
+            RECHECK_INTERVAL
+        });
+        match rx.recv_timeout(wait) {
+            Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    });
+}
+
+fn reschedule() {
+    if let Some(control) = CONTROL.lock().unwrap().as_ref() {
+        let _ = control.send(());
+    }
+}
+
+#[tauri::command]
+pub fn get_notification_preferences() -> Result<NotificationPreferences, String> {
+    preferences()
+}
+
+#[tauri::command]
+pub fn set_notification_preferences(mut preferences: NotificationPreferences) -> Result<NotificationPreferences, String> {
+    preferences.validate()?;
+    with_state(|state| state.preferences = preferences.clone())?;
+    reschedule();
+    Ok(preferences)
+}
+
+// What the scheduler will send in the next `hours` (default 24), for the settings page to preview
+#[tauri::command]
+pub fn list_upcoming_reminders(hours: Option<u32>) -> Result<Vec<Reminder>, String> {
+    let now = Utc::now();
+    let until = now + Duration::hours(hours.unwrap_or(24) as i64);
+    let preferences = preferences()?;
+    let sent = sent_keys()?;
+    let mut reminders = upcoming(&preferences, now, until + preferences.max_lead())?;
+    reminders.retain(|reminder| !sent.contains(&reminder.key) && reminder.fire_at <= until);
+    for reminder in &mut reminders {
+        reminder.body = describe(reminder, reminder.fire_at.max(now));
+    }
+    Ok(reminders)
+}
@@ -0,0 +1,278 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::{self, Calendar, EventType, PracticeEvent};
+use crate::recurrence;
+
+// How far ahead a repeating event is checked; open-ended series would otherwise never finish
+const CONFLICT_HORIZON_DAYS: i64 = 365;
+const MAX_CONFLICTS: usize = 200;
+const DEFAULT_STEP_MINUTES: u32 = 15;
+const DEFAULT_MAX_SLOTS: usize = 20;
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+    // The occurrence of the checked event that clashes; the event's own times unless it repeats
+    pub occurrence_start: DateTime<Utc>,
+    pub occurrence_end: DateTime<Utc>,
+    // What it clashes with, expanded to the single occurrence when that one repeats
+    pub event: PracticeEvent,
+    pub overlap_minutes: i64,
+}
+
+// What create_event and update_event return: the saved event with any clashes it now has
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedEvent {
+    #[serde(flatten)]
+    pub event: PracticeEvent,
+    pub conflicts: Vec<Conflict>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SlotConstraints {
+    // Wall-clock hours practice can happen in, read in time_zone
+    pub day_start: NaiveTime,
+    pub day_end: NaiveTime,
+    // Empty means every day
+    pub weekdays: Vec<Weekday>,
+    // Kept free before and after existing events, e.g. for travelling to a lesson
+    pub buffer_minutes: u32,
+    // Event types that don't block time, e.g. Practice when moving practice blocks around
+    pub ignore_types: Vec<EventType>,
+    // Proposed blocks start on multiples of this many minutes past the hour
+    pub step_minutes: u32,
+    pub max_slots: usize,
+    // Defaults to the machine's zone
+    pub time_zone: Option<String>,
+}
+
+impl Default for SlotConstraints {
+    fn default() -> Self {
+        SlotConstraints {
+            day_start: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            day_end: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            weekdays: Vec::new(),
+            buffer_minutes: 0,
+            ignore_types: Vec::new(),
+            step_minutes: DEFAULT_STEP_MINUTES,
+            max_slots: DEFAULT_MAX_SLOTS,
+            time_zone: None,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FreeSlot {
+    // A proposed block of the requested length
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    // How long the free time around the proposal lasts, so the UI can offer to stretch it
+    pub free_until: DateTime<Utc>,
+}
+
+// All-day events mark dates rather than taking up hours, so like most calendar apps they don't block time
+fn blocks_time(event: &PracticeEvent) -> bool {
+    !event.all_day
+}
+
+// Instant events still occupy the minute they happen in
+fn busy_span(event: &PracticeEvent) -> (DateTime<Utc>, DateTime<Utc>) {
+    let (start, end) = event.span();
+    (start, end.max(start + Duration::minutes(1)))
+}
+
+fn belongs_to(event: &PracticeEvent, id: &str) -> bool {
+    event.id == id || event.recurring_event_id.as_deref() == Some(id)
+}
+
+// Clashes of an event that is already in the calendar, its occurrences and edited occurrences included
+pub fn find_conflicts(calendar: &Calendar, event: &PracticeEvent) -> Vec<Conflict> {
+    if !blocks_time(event) {
+        return Vec::new();
+    }
+    let (start, end) = busy_span(event);
+    // A series that started long ago is checked for the year ahead, not for its past occurrences
+    let (start, end) = if event.rrule.is_some() {
+        let from = start.max(Utc::now());
+        (from, from + Duration::days(CONFLICT_HORIZON_DAYS))
+    } else {
+        (start, end)
+    };
+    let (own, others): (Vec<PracticeEvent>, Vec<PracticeEvent>) = calendar
+        .in_range(start, end)
+        .into_iter()
+        .filter(blocks_time)
+        .partition(|other| belongs_to(other, &event.id));
+
+    let mut conflicts = Vec::new();
+    for occurrence in &own {
+        let (occurrence_start, occurrence_end) = busy_span(occurrence);
+        for other in &others {
+            let (other_start, other_end) = busy_span(other);
+            if other_start >= occurrence_end || other_end <= occurrence_start {
+                continue;
+            }
+            conflicts.push(Conflict {
+                occurrence_start: occurrence.start_time,
+                occurrence_end: occurrence.end_time,
+                event: other.clone(),
+                overlap_minutes: (occurrence_end.min(other_end) - occurrence_start.max(other_start)).num_minutes(),
+            });
+            if conflicts.len() == MAX_CONFLICTS {
+                return conflicts;
+            }
+        }
+    }
+    conflicts
+}
+
+// The machine's zone or a named one, for turning the constraint hours into instants
+enum SlotZone {
+    Named(Tz),
+    Local,
+}
+
+impl SlotZone {
+    fn local(&self, time: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            SlotZone::Named(tz) => time.with_timezone(tz).naive_local(),
+            SlotZone::Local => time.with_timezone(&Local).naive_local(),
+        }
+    }
+
+    fn resolve(&self, naive: NaiveDateTime) -> DateTime<Utc> {
+        match self {
+            SlotZone::Named(tz) => recurrence::resolve_local(tz, naive),
+            SlotZone::Local => recurrence::resolve_local(&Local, naive),
+        }
+    }
+
+    // Rounds up to the next wall-clock multiple of `step` minutes
+    fn round_up(&self, time: DateTime<Utc>, step: u32) -> DateTime<Utc> {
+        let whole = time.with_nanosecond(0).unwrap();
+        let time = if whole < time { whole + Duration::seconds(1) } else { whole };
+        let seconds = self.local(time).num_seconds_from_midnight() as i64;
+        let step = step as i64 * 60;
+        time + Duration::seconds((seconds + step - 1) / step * step - seconds)
+    }
+}
+
+// Merged busy intervals, widened by the buffer
+fn busy_intervals(events: &[PracticeEvent], constraints: &SlotConstraints) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let buffer = Duration::minutes(constraints.buffer_minutes as i64);
+    let mut busy: Vec<(DateTime<Utc>, DateTime<Utc>)> = events
+        .iter()
+        .filter(|event| blocks_time(event) && !constraints.ignore_types.contains(&event.event_type))
+        .map(|event| {
+            let (start, end) = busy_span(event);
+            (start - buffer, end + buffer)
+        })
+        .collect();
+    busy.sort();
+    let mut merged: Vec<(DateTime<Utc>, DateTime<Utc>)> = Vec::new();
+    for (start, end) in busy {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+fn free_slots(
+    events: &[PracticeEvent],
+    duration: Duration,
+    window: TimeWindow,
+    constraints: &SlotConstraints,
+    zone: &SlotZone,
+) -> Vec<FreeSlot> {
+    let busy = busy_intervals(events, constraints);
+    let step = constraints.step_minutes.max(1);
+    let mut slots = Vec::new();
+    let mut date: NaiveDate = zone.local(window.start).date();
+    let last_date = zone.local(window.end).date();
+    while date <= last_date && slots.len() < constraints.max_slots {
+        let weekday_allowed = constraints.weekdays.is_empty() || constraints.weekdays.contains(&date.weekday());
+        let day_start = zone.resolve(date.and_time(constraints.day_start)).max(window.start);
+        let day_end = zone.resolve(date.and_time(constraints.day_end)).min(window.end);
+        date = date.succ_opt().unwrap();
+        if !weekday_allowed || day_start >= day_end {
+            continue;
+        }
+
+        // Walk the gaps between busy intervals inside the day's hours
+        let mut cursor = day_start;
+        let mut blocks = busy.iter().filter(|(start, end)| *end > day_start && *start < day_end).peekable();
+        loop {
+            let gap_end = blocks.peek().map(|(start, _)| (*start).min(day_end)).unwrap_or(day_end);
+            let start = zone.round_up(cursor, step);
+            if start + duration <= gap_end {
+                slots.push(FreeSlot {
+                    start,
+                    end: start + duration,
+                    free_until: gap_end,
+                });
+                if slots.len() == constraints.max_slots {
+                    break;
+                }
+            }
+            match blocks.next() {
+                Some((_, end)) => cursor = cursor.max(*end),
+                None => break,
+            }
+            if cursor >= day_end {
+                break;
+            }
+        }
+    }
+    slots
+}
+
+// Checks an event before it's saved, e.g. while the form is open; repeating events are checked a year ahead
+#[tauri::command]
+pub fn check_event_conflicts(mut event: PracticeEvent) -> Result<Vec<Conflict>, String> {
+    if event.id.is_empty() {
+        event.id = "unsaved".to_string();
+    }
+    event.recurrence()?;
+    calendar::read(|calendar| {
+        let mut candidate = calendar.clone();
+        candidate.events.retain(|other| other.id != event.id);
+        candidate.events.push(event.clone());
+        find_conflicts(&candidate, &event)
+    })
+}
+
+// Proposes blocks of `durationMinutes` that fit around existing commitments, earliest first, one per free gap
+#[tauri::command]
+pub fn find_free_slots(duration_minutes: u32, window: TimeWindow, constraints: Option<SlotConstraints>) -> Result<Vec<FreeSlot>, String> {
+    let constraints = constraints.unwrap_or_default();
+    if duration_minutes == 0 {
+        return Err("Slot duration must be at least a minute".to_string());
+    }
+    if window.end <= window.start {
+        return Err("Window end must be after its start".to_string());
+    }
+    if constraints.day_end <= constraints.day_start {
+        return Err("Day end must be after day start".to_string());
+    }
+    let zone = match &constraints.time_zone {
+        Some(name) => SlotZone::Named(calendar::parse_zone(name)?),
+        None => SlotZone::Local,
+    };
+    let buffer = Duration::minutes(constraints.buffer_minutes as i64);
+    let events = calendar::read(|calendar| calendar.in_range(window.start - buffer, window.end + buffer))?;
+    Ok(free_slots(&events, Duration::minutes(duration_minutes as i64), window, &constraints, &zone))
+}
@@ -0,0 +1,69 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::uploads;
+
+// Tokens are only held in memory; the frontend hands them over again after each login or restart
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub user_id: Option<String>,
+}
+
+static SESSION: Lazy<Mutex<Option<Session>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn current() -> Option<Session> {
+    SESSION.lock().unwrap().clone()
+}
+
+pub fn access_token() -> Option<String> {
+    current().map(|session| session.access_token)
+}
+
+pub fn set(session: Session) {
+    *SESSION.lock().unwrap() = Some(session);
+    uploads::session_renewed();
+}
+
+// Keeps the refresh token and user when only the access token changes, e.g. after a refresh
+pub fn update_access_token(access_token: String, expires_at: Option<DateTime<Utc>>) {
+    let mut guard = SESSION.lock().unwrap();
+    match guard.as_mut() {
+        Some(session) => {
+            session.access_token = access_token;
+            session.expires_at = expires_at;
+        }
+        None => {
+            *guard = Some(Session {
+                access_token,
+                refresh_token: None,
+                expires_at,
+                user_id: None,
+            })
+        }
+    }
+    drop(guard);
+    uploads::session_renewed();
+}
+
+#[tauri::command]
+pub fn set_session(session: Session) {
+    println!("Session updated for user {:?}", session.user_id);
+    set(session);
+}
+
+#[tauri::command]
+pub fn clear_session() {
+    println!("Session cleared");
+    *SESSION.lock().unwrap() = None;
+}
+
+#[tauri::command]
+pub fn has_session() -> bool {
+    SESSION.lock().unwrap().is_some()
+}
@@ -0,0 +1,741 @@
+use serde::{Deserialize, Serialize};
+
+// Durations are counted in twelfths of a quarter so eighth triplets and sixteenths are both whole numbers
+const DIVISIONS: u32 = 12;
+const LETTERS: [&str; 7] = ["C", "D", "E", "F", "G", "A", "B"];
+// Letters sharpened, in order, by each sharp in the key signature; flats go the other way
+const SHARP_ORDER: [i32; 7] = [3, 0, 4, 1, 5, 2, 6];
+
+// SplitMix64. Kept here rather than taken from rand so a seed gives the same exercise across dependency updates
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())]
+    }
+
+    // Index into `weights`, chosen in proportion to each weight
+    fn weighted(&mut self, weights: &[u32]) -> usize {
+        let mut roll = (self.next() % weights.iter().map(|weight| *weight as u64).sum::<u64>()) as u32;
+        for (index, weight) in weights.iter().enumerate() {
+            if roll < *weight {
+                return index;
+            }
+            roll -= weight;
+        }
+        weights.len() - 1
+    }
+}
+
+// A beat-aligned rhythmic figure; exercises are built from these so they always read cleanly
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Rhythm {
+    Quarter,
+    QuarterRest,
+    Half,
+    DottedHalf,
+    Whole,
+    EighthPair,
+    DottedQuarterEighth,
+    Triplet,
+    Sixteenths,
+    EighthTwoSixteenths,
+    TwoSixteenthsEighth,
+    // Eighth, quarter, eighth
+    Syncopation,
+}
+
+impl Rhythm {
+    // (duration, is a rest)
+    fn notes(&self) -> &'static [(u32, bool)] {
+        match self {
+            Rhythm::Quarter => &[(12, false)],
+            Rhythm::QuarterRest => &[(12, true)],
+            Rhythm::Half => &[(24, false)],
+            Rhythm::DottedHalf => &[(36, false)],
+            Rhythm::Whole => &[(48, false)],
+            Rhythm::EighthPair => &[(6, false), (6, false)],
+            Rhythm::DottedQuarterEighth => &[(18, false), (6, false)],
+            Rhythm::Triplet => &[(4, false), (4, false), (4, false)],
+            Rhythm::Sixteenths => &[(3, false), (3, false), (3, false), (3, false)],
+            Rhythm::EighthTwoSixteenths => &[(6, false), (3, false), (3, false)],
+            Rhythm::TwoSixteenthsEighth => &[(3, false), (3, false), (6, false)],
+            Rhythm::Syncopation => &[(6, false), (12, false), (6, false)],
+        }
+    }
+
+    fn beats(&self) -> u32 {
+        self.notes().iter().map(|(duration, _)| duration).sum::<u32>() / DIVISIONS
+    }
+
+    // Long figures start where the bar's pulse puts them: wholes and dotted halves on the downbeat,
+    // two-beat figures on a strong beat of 4/4
+    fn fits(&self, position: u32, beats_per_bar: u32) -> bool {
+        let beats = self.beats();
+        position + beats <= beats_per_bar
+            && match beats {
+                1 => true,
+                2 => beats_per_bar != 4 || matches!(position, 0 | 2),
+                _ => position == 0,
+            }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Clef {
+    Treble,
+    Bass,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum NotationFormat {
+    Abc,
+    MusicXml,
+}
+
+// One of the Sight Reading World levels; names and difficulties match the map in Practice.tsx
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LevelSpec {
+    pub id: u32,
+    pub name: &'static str,
+    pub difficulty: &'static str,
+    // Key signatures as a count of fifths, negative for flats
+    pub keys: Vec<i32>,
+    pub minor_keys: bool,
+    // Lowest and highest note as diatonic steps from middle C, in treble clef
+    pub range: (i32, i32),
+    // Widest leap in diatonic steps: 1 is stepwise, 2 a third, 4 a fifth, 7 an octave
+    pub max_interval: i32,
+    pub beats_per_bar: Vec<u32>,
+    pub bars: u32,
+    pub tempo: u32,
+    pub rhythms: Vec<(Rhythm, u32)>,
+    // Score needed to pass and unlock the next level
+    pub pass_percent: u32,
+}
+
+pub fn levels() -> Vec<LevelSpec> {
+    use Rhythm::*;
+    vec![
+        LevelSpec {
+            id: 1,
+            name: "Note Basics",
+            difficulty: "Easy",
+            keys: vec![0],
+            minor_keys: false,
+            range: (0, 7),
+            max_interval: 1,
+            beats_per_bar: vec![4],
+            bars: 4,
+            tempo: 60,
+            rhythms: vec![(Quarter, 1)],
+            pass_percent: 70,
+        },
+        LevelSpec {
+            id: 2,
+            name: "Quarter Notes",
+            difficulty: "Easy",
+            keys: vec![-1, 0, 1],
+            minor_keys: false,
+            range: (0, 7),
+            max_interval: 2,
+            beats_per_bar: vec![4, 3],
+            bars: 4,
+            tempo: 66,
+            rhythms: vec![(Quarter, 4), (QuarterRest, 1)],
+            pass_percent: 70,
+        },
+        LevelSpec {
+            id: 3,
+            name: "Half Notes",
+            difficulty: "Easy",
+            keys: vec![-1, 0, 1],
+            minor_keys: false,
+            range: (-1, 8),
+            max_interval: 2,
+            beats_per_bar: vec![4, 3],
+            bars: 6,
+            tempo: 66,
+            rhythms: vec![(Quarter, 3), (QuarterRest, 1), (Half, 2)],
+            pass_percent: 70,
+        },
+        LevelSpec {
+            id: 4,
+            name: "Whole Notes",
+            difficulty: "Medium",
+            keys: (-2..=2).collect(),
+            minor_keys: true,
+            range: (-1, 9),
+            max_interval: 3,
+            beats_per_bar: vec![4, 3, 2],
+            bars: 8,
+            tempo: 72,
+            rhythms: vec![(Quarter, 3), (QuarterRest, 1), (Half, 2), (DottedHalf, 1), (Whole, 1)],
+            pass_percent: 75,
+        },
+        LevelSpec {
+            id: 5,
+            name: "Eighth Notes",
+            difficulty: "Medium",
+            keys: (-2..=2).collect(),
+            minor_keys: true,
+            range: (-3, 9),
+            max_interval: 4,
+            beats_per_bar: vec![4, 3, 2],
+            bars: 8,
+            tempo: 76,
+            rhythms: vec![(Quarter, 3), (QuarterRest, 1), (Half, 2), (EighthPair, 3)],
+            pass_percent: 75,
+        },
+        LevelSpec {
+            id: 6,
+            name: "Dotted Notes",
+            difficulty: "Hard",
+            keys: (-3..=3).collect(),
+            minor_keys: true,
+            range: (-3, 10),
+            max_interval: 4,
+            beats_per_bar: vec![4, 3],
+            bars: 8,
+            tempo: 80,
+            rhythms: vec![(Quarter, 3), (QuarterRest, 1), (Half, 2), (DottedHalf, 1), (EighthPair, 2), (DottedQuarterEighth, 2)],
+            pass_percent: 80,
+        },
+        LevelSpec {
+            id: 7,
+            name: "Triplets",
+            difficulty: "Hard",
+            keys: (-4..=4).collect(),
+            minor_keys: true,
+            range: (-3, 11),
+            max_interval: 5,
+            beats_per_bar: vec![4, 3, 2],
+            bars: 8,
+            tempo: 80,
+            rhythms: vec![(Quarter, 3), (QuarterRest, 1), (Half, 1), (EighthPair, 2), (DottedQuarterEighth, 1), (Triplet, 3)],
+            pass_percent: 80,
+        },
+        LevelSpec {
+            id: 8,
+            name: "Sixteenth Notes",
+            difficulty: "Expert",
+            keys: (-5..=5).collect(),
+            minor_keys: true,
+            range: (-4, 12),
+            max_interval: 7,
+            beats_per_bar: vec![4, 2],
+            bars: 8,
+            tempo: 72,
+            rhythms: vec![
+                (Quarter, 2),
+                (QuarterRest, 1),
+                (Half, 1),
+                (EighthPair, 2),
+                (Sixteenths, 2),
+                (EighthTwoSixteenths, 1),
+                (TwoSixteenthsEighth, 1),
+            ],
+            pass_percent: 80,
+        },
+        LevelSpec {
+            id: 9,
+            name: "Complex Rhythms",
+            difficulty: "Expert",
+            keys: (-6..=6).collect(),
+            minor_keys: true,
+            range: (-5, 12),
+            max_interval: 7,
+            beats_per_bar: vec![4, 3, 2],
+            bars: 12,
+            tempo: 80,
+            rhythms: vec![
+                (Quarter, 2),
+                (QuarterRest, 1),
+                (Half, 1),
+                (DottedHalf, 1),
+                (EighthPair, 2),
+                (DottedQuarterEighth, 2),
+                (Triplet, 2),
+                (Sixteenths, 1),
+                (EighthTwoSixteenths, 1),
+                (TwoSixteenthsEighth, 1),
+                (Syncopation, 2),
+            ],
+            pass_percent: 80,
+        },
+    ]
+}
+
+// Replaces the level's random choices; anything left out is still picked from the seed
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExerciseOverrides {
+    pub key_fifths: Option<i32>,
+    pub minor: Option<bool>,
+    pub clef: Option<Clef>,
+    pub bars: Option<u32>,
+    pub beats_per_bar: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Beam {
+    Begin,
+    Continue,
+    End,
+}
+
+#[derive(Clone, Debug)]
+struct Note {
+    duration: u32,
+    rest: bool,
+    // Diatonic steps from middle C
+    step: i32,
+    triplet: Option<Beam>,
+    beam: Option<Beam>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Exercise {
+    pub level: u32,
+    pub seed: u64,
+    pub format: NotationFormat,
+    // e.g. "E minor"
+    pub key: String,
+    pub key_fifths: i32,
+    pub minor: bool,
+    pub clef: Clef,
+    pub time_signature: String,
+    pub bars: u32,
+    pub tempo: u32,
+    pub note_count: usize,
+    pub notation: String,
+}
+
+// The letter (0 = C) and accidental of a diatonic step under a key signature
+fn spell(step: i32, fifths: i32) -> (usize, i32) {
+    let letter = step.rem_euclid(7);
+    let alter = if fifths >= 0 {
+        SHARP_ORDER[..fifths as usize].contains(&letter) as i32
+    } else {
+        -(SHARP_ORDER[7 - (-fifths) as usize..].contains(&letter) as i32)
+    };
+    (letter as usize, alter)
+}
+
+fn octave(step: i32) -> i32 {
+    4 + step.div_euclid(7)
+}
+
+fn tonic_letter(fifths: i32, minor: bool) -> i32 {
+    (fifths * 4 + if minor { 5 } else { 0 }).rem_euclid(7)
+}
+
+fn key_name(fifths: i32, minor: bool) -> String {
+    let (letter, alter) = spell(tonic_letter(fifths, minor), fifths);
+    format!("{}{} {}", LETTERS[letter], ["b", "", "#"][(alter + 1) as usize], if minor { "minor" } else { "major" })
+}
+
+// Bars of rhythm figures, the last ending on the longest note the level knows
+fn rhythm(rng: &mut Rng, level: &LevelSpec, beats_per_bar: u32, bars: u32) -> Vec<Vec<Rhythm>> {
+    let mut result = Vec::new();
+    for bar in 0..bars {
+        let mut figures = Vec::new();
+        let mut ending = None;
+        if bar + 1 == bars {
+            ending = [Rhythm::Whole, Rhythm::DottedHalf, Rhythm::Half, Rhythm::Quarter]
+                .into_iter()
+                .filter(|figure| *figure == Rhythm::Quarter || level.rhythms.iter().any(|(rhythm, _)| rhythm == figure))
+                .find(|figure| figure.beats() <= beats_per_bar && figure.fits(beats_per_bar - figure.beats(), beats_per_bar));
+        }
+        let end = beats_per_bar - ending.map(|figure| figure.beats()).unwrap_or(0);
+        let mut position = 0;
+        while position < end {
+            let options: Vec<(Rhythm, u32)> = level
+                .rhythms
+                .iter()
+                .filter(|(figure, _)| figure.fits(position, end))
+                // A bar of silence or an exercise starting on a rest isn't much to read
+                .filter(|(figure, _)| *figure != Rhythm::QuarterRest || (position > 0 && !figures.contains(&Rhythm::QuarterRest)))
+                .copied()
+                .collect();
+            let figure = if options.is_empty() {
+                Rhythm::Quarter
+            } else {
+                options[rng.weighted(&options.iter().map(|(_, weight)| *weight).collect::<Vec<_>>())].0
+            };
+            position += figure.beats();
+            figures.push(figure);
+        }
+        figures.extend(ending);
+        result.push(figures);
+    }
+    result
+}
+
+// A random walk through the scale that starts on a chord tone and is steered so it can always land on the tonic
+fn melody(rng: &mut Rng, count: usize, range: (i32, i32), max_interval: i32, tonic: i32) -> Vec<i32> {
+    let centre = (range.0 + range.1) / 2;
+    let target = (range.0..=range.1)
+        .filter(|step| step.rem_euclid(7) == tonic)
+        .min_by_key(|step| (step - centre).abs())
+        .unwrap_or(centre);
+    let reachable = |step: i32, left: usize| (step - target).abs() <= left as i32 * max_interval;
+    if count <= 1 {
+        return vec![target; count];
+    }
+
+    let starts: Vec<i32> = (range.0..=range.1)
+        .filter(|step| [0, 2, 4].contains(&(step - tonic).rem_euclid(7)) && reachable(*step, count - 1))
+        .collect();
+    let mut steps = vec![if starts.is_empty() { target } else { rng.pick(&starts) }];
+    for index in 1..count - 1 {
+        let current = steps[index - 1];
+        let repeated = index >= 2 && steps[index - 2] == current;
+        let options: Vec<i32> = (-max_interval..=max_interval)
+            .map(|interval| current + interval)
+            .filter(|step| (range.0..=range.1).contains(step) && reachable(*step, count - 1 - index))
+            .filter(|step| !(repeated && *step == current))
+            .collect();
+        // Steps are the bread and butter of sight-reading; leaps and repeats come less often
+        let weights: Vec<u32> = options
+            .iter()
+            .map(|step| match (step - current).abs() {
+                0 => 1,
+                1 => 6,
+                2 => 3,
+                _ => 1,
+            })
+            .collect();
+        steps.push(if options.is_empty() { target } else { options[rng.weighted(&weights)] });
+    }
+    steps.push(target);
+    steps
+}
+
+fn notes_for(figures: &[Rhythm]) -> Vec<Note> {
+    let mut notes = Vec::new();
+    for figure in figures {
+        let start = notes.len();
+        for (duration, rest) in figure.notes() {
+            notes.push(Note {
+                duration: *duration,
+                rest: *rest,
+                step: 0,
+                triplet: None,
+                beam: None,
+            });
+        }
+        let group = &mut notes[start..];
+        let last = group.len() - 1;
+        // Beam the figure when it's all flagged notes; a lone eighth in a syncopation keeps its flag
+        if group.len() > 1 && group.iter().all(|note| note.duration < DIVISIONS) {
+            for (index, note) in group.iter_mut().enumerate() {
+                note.beam = Some(if index == 0 { Beam::Begin } else if index == last { Beam::End } else { Beam::Continue });
+            }
+        }
+        if *figure == Rhythm::Triplet {
+            group[0].triplet = Some(Beam::Begin);
+            group[last].triplet = Some(Beam::End);
+        }
+    }
+    notes
+}
+
+fn abc_note(note: &Note, fifths: i32) -> String {
+    // ABC lengths here are in sixteenths; triplet notes are written as eighths inside (3
+    let length = if note.triplet.is_some() || note.duration == 4 { 2 } else { note.duration / 3 };
+    let length = if length == 1 { String::new() } else { length.to_string() };
+    if note.rest {
+        return format!("z{}", length);
+    }
+    let step = note.step;
+    let (letter, _) = spell(step, fifths);
+    let octave = octave(step);
+    let name = match octave {
+        octave if octave >= 5 => format!("{}{}", LETTERS[letter].to_lowercase(), "'".repeat((octave - 5) as usize)),
+        octave => format!("{}{}", LETTERS[letter], ",".repeat((4 - octave) as usize)),
+    };
+    format!("{}{}", name, length)
+}
+
+fn abc(exercise: &Exercise, bars: &[Vec<Note>], title: &str) -> String {
+    let (letter, alter) = spell(tonic_letter(exercise.key_fifths, exercise.minor), exercise.key_fifths);
+    let key = format!(
+        "{}{}{}",
+        LETTERS[letter],
+        ["b", "", "#"][(alter + 1) as usize],
+        if exercise.minor { "m" } else { "" }
+    );
+    let mut out = format!(
+        "X:1\nT:{}\nM:{}\nL:1/16\nQ:1/4={}\nK:{}{}\n",
+        title,
+        exercise.time_signature,
+        exercise.tempo,
+        key,
+        if exercise.clef == Clef::Bass { " clef=bass" } else { "" }
+    );
+    for (index, bar) in bars.iter().enumerate() {
+        let mut text = String::new();
+        for note in bar {
+            if note.triplet == Some(Beam::Begin) {
+                text.push_str("(3");
+            }
+            text.push_str(&abc_note(note, exercise.key_fifths));
+            // Notes beamed together are written without a space between them
+            if !matches!(note.beam, Some(Beam::Begin) | Some(Beam::Continue)) {
+                text.push(' ');
+            }
+        }
+        out.push_str(&text);
+        out.push_str(if index + 1 == bars.len() { "|]\n" } else if index % 4 == 3 { "|\n" } else { "| " });
+    }
+    out
+}
+
+fn note_type(duration: u32) -> (&'static str, bool) {
+    match duration {
+        48 => ("whole", false),
+        36 => ("half", true),
+        24 => ("half", false),
+        18 => ("quarter", true),
+        12 => ("quarter", false),
+        6 | 4 => ("eighth", false),
+        _ => ("16th", false),
+    }
+}
+
+fn beam_name(beam: Beam) -> &'static str {
+    match beam {
+        Beam::Begin => "begin",
+        Beam::Continue => "continue",
+        Beam::End => "end",
+    }
+}
+
+fn music_xml(exercise: &Exercise, bars: &[Vec<Note>], title: &str) -> String {
+    let (beats, beat_type) = exercise.time_signature.split_once('/').unwrap();
+    let (sign, line) = match exercise.clef {
+        Clef::Treble => ("G", 2),
+        Clef::Bass => ("F", 4),
+    };
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 4.0 Partwise//EN\" \"http://www.musicxml.org/dtds/partwise.dtd\">\n");
+    out.push_str("<score-partwise version=\"4.0\">\n");
+    out.push_str(&format!("  <work><work-title>{}</work-title></work>\n", quick_xml::escape::escape(title)));
+    out.push_str("  <part-list><score-part id=\"P1\"><part-name>Sight-reading</part-name></score-part></part-list>\n");
+    out.push_str("  <part id=\"P1\">\n");
+    for (index, bar) in bars.iter().enumerate() {
+        out.push_str(&format!("    <measure number=\"{}\">\n", index + 1));
+        if index == 0 {
+            out.push_str(&format!(
+                "      <attributes><divisions>{}</divisions><key><fifths>{}</fifths><mode>{}</mode></key><time><beats>{}</beats><beat-type>{}</beat-type></time><clef><sign>{}</sign><line>{}</line></clef></attributes>\n",
+                DIVISIONS,
+                exercise.key_fifths,
+                if exercise.minor { "minor" } else { "major" },
+                beats,
+                beat_type,
+                sign,
+                line
+            ));
+            out.push_str(&format!(
+                "      <direction placement=\"above\"><direction-type><metronome><beat-unit>quarter</beat-unit><per-minute>{}</per-minute></metronome></direction-type><sound tempo=\"{}\"/></direction>\n",
+                exercise.tempo, exercise.tempo
+            ));
+        }
+        for note in bar {
+            out.push_str("      <note>");
+            if note.rest {
+                out.push_str("<rest/>");
+            } else {
+                let step = note.step;
+                let (letter, alter) = spell(step, exercise.key_fifths);
+                out.push_str(&format!("<pitch><step>{}</step>", LETTERS[letter]));
+                if alter != 0 {
+                    out.push_str(&format!("<alter>{}</alter>", alter));
+                }
+                out.push_str(&format!("<octave>{}</octave></pitch>", octave(step)));
+            }
+            let (kind, dotted) = note_type(note.duration);
+            out.push_str(&format!("<duration>{}</duration><type>{}</type>", note.duration, kind));
+            if dotted {
+                out.push_str("<dot/>");
+            }
+            if note.triplet.is_some() || note.duration == 4 {
+                out.push_str("<time-modification><actual-notes>3</actual-notes><normal-notes>2</normal-notes></time-modification>");
+            }
+            if let Some(beam) = note.beam {
+                out.push_str(&format!("<beam number=\"1\">{}</beam>", beam_name(beam)));
+            }
+            match note.triplet {
+                Some(Beam::Begin) => out.push_str("<notations><tuplet type=\"start\" bracket=\"no\"/></notations>"),
+                Some(_) => out.push_str("<notations><tuplet type=\"stop\"/></notations>"),
+                None => {}
+            }
+            out.push_str("</note>\n");
+        }
+        if index + 1 == bars.len() {
+            out.push_str("      <barline location=\"right\"><bar-style>light-heavy</bar-style></barline>\n");
+        }
+        out.push_str("    </measure>\n");
+    }
+    out.push_str("  </part>\n</score-partwise>\n");
+    out
+}
+
+pub fn generate(level_id: u32, seed: u64, format: NotationFormat, overrides: &ExerciseOverrides) -> Result<Exercise, String> {
+    let level = levels()
+        .into_iter()
+        .find(|level| level.id == level_id)
+        .ok_or_else(|| format!("Sight-reading level {} not found", level_id))?;
+    if overrides.key_fifths.is_some_and(|fifths| !(-7..=7).contains(&fifths)) {
+        return Err("Key signatures go from 7 flats (-7) to 7 sharps (7)".to_string());
+    }
+    if overrides.bars.is_some_and(|bars| !(1..=64).contains(&bars)) {
+        return Err("An exercise has to be between 1 and 64 bars".to_string());
+    }
+    if overrides.beats_per_bar.is_some_and(|beats| !(2..=4).contains(&beats)) {
+        return Err("Exercises are in 2/4, 3/4 or 4/4".to_string());
+    }
+
+    // Every choice comes from the one generator in a fixed order, so the seed alone decides the exercise
+    let mut rng = Rng(seed);
+    let key_fifths = overrides.key_fifths.unwrap_or_else(|| rng.pick(&level.keys));
+    let minor = overrides.minor.unwrap_or_else(|| level.minor_keys && rng.below(3) == 0);
+    let beats_per_bar = overrides.beats_per_bar.unwrap_or_else(|| rng.pick(&level.beats_per_bar));
+    let bars = overrides.bars.unwrap_or(level.bars);
+    let clef = overrides.clef.unwrap_or(Clef::Treble);
+
+    let mut bar_notes: Vec<Vec<Note>> = rhythm(&mut rng, &level, beats_per_bar, bars).iter().map(|figures| notes_for(figures)).collect();
+    let count = bar_notes.iter().flatten().filter(|note| !note.rest).count();
+    // Bass clef reads the same staff positions an octave and a sixth lower
+    let range = match clef {
+        Clef::Treble => level.range,
+        Clef::Bass => (level.range.0 - 12, level.range.1 - 12),
+    };
+    let steps = melody(&mut rng, count, range, level.max_interval, tonic_letter(key_fifths, minor));
+    for (note, step) in bar_notes.iter_mut().flatten().filter(|note| !note.rest).zip(steps) {
+        note.step = step;
+    }
+
+    let mut exercise = Exercise {
+        level: level.id,
+        seed,
+        format,
+        key: key_name(key_fifths, minor),
+        key_fifths,
+        minor,
+        clef,
+        time_signature: format!("{}/4", beats_per_bar),
+        bars,
+        tempo: level.tempo,
+        note_count: count,
+        notation: String::new(),
+    };
+    let title = format!("Level {}: {} (#{})", level.id, level.name, seed);
+    exercise.notation = match format {
+        NotationFormat::Abc => abc(&exercise, &bar_notes, &title),
+        NotationFormat::MusicXml => music_xml(&exercise, &bar_notes, &title),
+    };
+    Ok(exercise)
+}
+
+#[tauri::command]
+pub fn list_sight_reading_levels() -> Vec<LevelSpec> {
+    levels()
+}
+
+// Leave out the seed for a fresh exercise; the one used comes back so the same exercise can be dealt again.
+// Random seeds stay below 2^53 so they survive the round trip through a JavaScript number.
+#[tauri::command]
+pub fn generate_sight_reading_exercise(
+    level: u32,
+    seed: Option<u64>,
+    format: Option<NotationFormat>,
+    overrides: Option<ExerciseOverrides>,
+) -> Result<Exercise, String> {
+    generate(
+        level,
+        seed.unwrap_or_else(|| rand::random::<u64>() >> 11),
+        format.unwrap_or(NotationFormat::MusicXml),
+        &overrides.unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exercise(level: u32, seed: u64, format: NotationFormat) -> Exercise {
+        generate(level, seed, format, &ExerciseOverrides::default()).unwrap()
+    }
+
+    #[test]
+    fn a_seed_always_deals_the_same_exercise() {
+        for level in levels() {
+            for seed in [0, 1, 42, 1 << 52] {
+                for format in [NotationFormat::Abc, NotationFormat::MusicXml] {
+                    let first = serde_json::to_string(&exercise(level.id, seed, format)).unwrap();
+                    let second = serde_json::to_string(&exercise(level.id, seed, format)).unwrap();
+                    assert_eq!(first, second, "level {} seed {}", level.id, seed);
+                }
+            }
+        }
+        assert_ne!(exercise(3, 1, NotationFormat::Abc).notation, exercise(3, 2, NotationFormat::Abc).notation);
+    }
+
+    #[test]
+    fn abc_output_is_pinned() {
+        // Changing this means saved seeds deal different exercises than before
+        let expected = "X:1\nT:Level 3: Half Notes (#42)\nM:3/4\nL:1/16\nQ:1/4=66\nK:C\nG8 A4 | B4 B4 G4 | F8 E4 | E8 z4 |\nC4 B,4 z4 | C4 C8 |]\n";
+        assert_eq!(exercise(3, 42, NotationFormat::Abc).notation, expected);
+    }
+
+    #[test]
+    fn every_bar_adds_up_to_the_time_signature() {
+        for level in levels() {
+            for beats_per_bar in 2..=4 {
+                for seed in 0..50 {
+                    let mut rng = Rng(seed);
+                    for (index, figures) in rhythm(&mut rng, &level, beats_per_bar, level.bars).iter().enumerate() {
+                        let total: u32 = notes_for(figures).iter().map(|note| note.duration).sum();
+                        assert_eq!(total, beats_per_bar * DIVISIONS, "level {} seed {} bar {}: {:?}", level.id, seed, index + 1, figures);
+                    }
+                }
+            }
+
+            // The written score agrees, measure by measure
+            for seed in 0..10 {
+                let exercise = exercise(level.id, seed, NotationFormat::MusicXml);
+                let beats: u32 = exercise.time_signature.split('/').next().unwrap().parse().unwrap();
+                let measures: Vec<&str> = exercise.notation.split("<measure ").skip(1).collect();
+                assert_eq!(measures.len() as u32, exercise.bars);
+                for measure in measures {
+                    let total: u32 = measure
+                        .split("<duration>")
+                        .skip(1)
+                        .map(|rest| rest.split('<').next().unwrap().parse::<u32>().unwrap())
+                        .sum();
+                    assert_eq!(total, beats * DIVISIONS, "level {} seed {}", level.id, seed);
+                }
+            }
+        }
+    }
+}
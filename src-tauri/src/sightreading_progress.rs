@@ -0,0 +1,230 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use crate::api_client::{ApiClient, ApiError};
+use crate::sightreading::{self, LevelSpec};
+use crate::{library, store};
+
+const PROGRESS_DOCUMENT: &str = "sight_reading_progress";
+// Scores for the second and third star; passing the level earns the first
+const TWO_STAR_PERCENT: f64 = 85.0;
+const THREE_STAR_PERCENT: f64 = 95.0;
+
+// One play-through of an exercise. Attempts never change once recorded, so syncing is a union by id
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Attempt {
+    pub id: String,
+    pub level: u32,
+    // The exercise played, so it can be dealt again from sightreading::generate
+    pub seed: u64,
+    // Percentage of notes read correctly
+    pub score: f64,
+    pub duration_seconds: f64,
+    pub completed_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ProgressLog {
+    attempts: Vec<Attempt>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LevelProgress {
+    pub level: u32,
+    pub name: String,
+    pub difficulty: String,
+    pub pass_percent: u32,
+    pub unlocked: bool,
+    pub passed: bool,
+    pub stars: u32,
+    pub attempts: usize,
+    pub best_score: Option<f64>,
+    // Fastest passing attempt
+    pub best_time_seconds: Option<f64>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldProgress {
+    pub levels: Vec<LevelProgress>,
+    pub total_stars: u32,
+    pub max_stars: u32,
+    // The first unlocked level not passed yet, where the map puts the player
+    pub current_level: Option<u32>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AttemptResult {
+    pub attempt: Attempt,
+    pub level: LevelProgress,
+    pub new_best_score: bool,
+    pub new_best_time: bool,
+    // Set when this attempt opened up the next level
+    pub unlocked_level: Option<u32>,
+}
+
+static PROGRESS_LOG: Lazy<Mutex<Option<ProgressLog>>> = Lazy::new(|| Mutex::new(None));
+
+fn with_log<R>(f: impl FnOnce(&mut ProgressLog) -> Result<R, String>) -> Result<R, String> {
+    let mut guard = PROGRESS_LOG.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(PROGRESS_DOCUMENT)?);
+    }
+    let log = guard.as_mut().unwrap();
+    let result = f(log)?;
+    store::save(PROGRESS_DOCUMENT, log)?;
+    Ok(result)
+}
+
+// Loads the log without saving it back, for callers that only look
+fn read<R>(f: impl FnOnce(&ProgressLog) -> R) -> Result<R, String> {
+    let mut guard = PROGRESS_LOG.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(PROGRESS_DOCUMENT)?);
+    }
+    Ok(f(guard.as_ref().unwrap()))
+}
+
+fn stars(best_score: f64, pass_percent: u32) -> u32 {
+    if best_score < pass_percent as f64 {
+        0
+    } else if best_score >= THREE_STAR_PERCENT {
+        3
+    } else if best_score >= TWO_STAR_PERCENT {
+        2
+    } else {
+        1
+    }
+}
+
+// Everything is derived from the attempts, so merged history from another device settles the same way.
+// Level 1 is always open and passing a level opens the one after it.
+fn world(levels: &[LevelSpec], attempts: &[Attempt]) -> WorldProgress {
+    let mut progress = Vec::new();
+    let mut previous_passed = true;
+    for spec in levels {
+        let played: Vec<&Attempt> = attempts.iter().filter(|attempt| attempt.level == spec.id).collect();
+        let best_score = played.iter().map(|attempt| attempt.score).max_by(f64::total_cmp);
+        let passing = played.iter().filter(|attempt| attempt.score >= spec.pass_percent as f64);
+        let best_time_seconds = passing.clone().map(|attempt| attempt.duration_seconds).min_by(f64::total_cmp);
+        let passed = passing.count() > 0;
+        progress.push(LevelProgress {
+            level: spec.id,
+            name: spec.name.to_string(),
+            difficulty: spec.difficulty.to_string(),
+            pass_percent: spec.pass_percent,
+            // Attempts synced from a device where the level was open count, even if this one got there differently
+            unlocked: previous_passed || !played.is_empty(),
+            passed,
+            stars: best_score.map(|score| stars(score, spec.pass_percent)).unwrap_or(0),
+            attempts: played.len(),
+            best_score,
+            best_time_seconds,
+            last_attempt_at: played.iter().map(|attempt| attempt.completed_at).max(),
+        });
+        previous_passed = passed;
+    }
+    WorldProgress {
+        total_stars: progress.iter().map(|level| level.stars).sum(),
+        max_stars: 3 * progress.len() as u32,
+        current_level: progress.iter().find(|level| level.unlocked && !level.passed).map(|level| level.level),
+        levels: progress,
+    }
+}
+
+// Attempts recorded on this device or pulled from others
+pub fn attempt_count() -> Result<usize, String> {
+    read(|log| log.attempts.len())
+}
+
+// Pulls attempts made on other devices and pushes the ones the account doesn't have yet
+pub fn sync(api: &ApiClient, user_id: &str) -> Result<(usize, usize), ApiError> {
+    let local_error = |message: String| ApiError::Local { message };
+    let remote = api.list_sight_reading_attempts(user_id)?;
+    let remote_ids: HashSet<&str> = remote.iter().map(|attempt| attempt.id.as_str()).collect();
+    let local = read(|log| log.attempts.clone()).map_err(local_error)?;
+    let local_ids: HashSet<&str> = local.iter().map(|attempt| attempt.id.as_str()).collect();
+
+    let missing_remotely: Vec<Attempt> = local.iter().filter(|attempt| !remote_ids.contains(attempt.id.as_str())).cloned().collect();
+    if !missing_remotely.is_empty() {
+        api.add_sight_reading_attempts(user_id, &missing_remotely)?;
+    }
+    let missing_locally: Vec<Attempt> = remote.iter().filter(|attempt| !local_ids.contains(attempt.id.as_str())).cloned().collect();
+    let pulled = if missing_locally.is_empty() {
+        0
+    } else {
+        // Another sync may have pulled the same attempts while this one was talking to the server
+        with_log(|log| {
+            let known: HashSet<String> = log.attempts.iter().map(|attempt| attempt.id.clone()).collect();
+            let new: Vec<Attempt> = missing_locally.into_iter().filter(|attempt| !known.contains(&attempt.id)).collect();
+            let pulled = new.len();
+            log.attempts.extend(new);
+            log.attempts.sort_by_key(|attempt| attempt.completed_at);
+            Ok(pulled)
+        })
+        .map_err(local_error)?
+    };
+    Ok((pulled, missing_remotely.len()))
+}
+
+#[tauri::command]
+pub fn get_sight_reading_progress() -> Result<WorldProgress, String> {
+    let levels = sightreading::levels();
+    read(|log| world(&levels, &log.attempts))
+}
+
+#[tauri::command]
+pub fn record_sight_reading_attempt(level: u32, seed: u64, score: f64, duration_seconds: f64) -> Result<AttemptResult, String> {
+    if !(0.0..=100.0).contains(&score) {
+        return Err("Score has to be a percentage between 0 and 100".to_string());
+    }
+    if !(duration_seconds > 0.0 && duration_seconds.is_finite()) {
+        return Err("An attempt has to take some time".to_string());
+    }
+    let levels = sightreading::levels();
+    with_log(|log| {
+        let before = world(&levels, &log.attempts);
+        let previous = before
+            .levels
+            .iter()
+            .find(|progress| progress.level == level)
+            .ok_or_else(|| format!("Sight-reading level {} not found", level))?;
+        if !previous.unlocked {
+            return Err(format!("Level {} is still locked; pass level {} first", level, level - 1));
+        }
+
+        let attempt = Attempt {
+            id: library::new_id(),
+            level,
+            seed,
+            score,
+            duration_seconds,
+            completed_at: Utc::now(),
+        };
+        log.attempts.push(attempt.clone());
+        let after = world(&levels, &log.attempts);
+        let current = after.levels.iter().find(|progress| progress.level == level).unwrap().clone();
+        let unlocked_level = after
+            .levels
+            .iter()
+            .zip(&before.levels)
+            .find(|(now, then)| now.unlocked && !then.unlocked)
+            .map(|(now, _)| now.level);
+        println!("Recorded sight-reading attempt on level {}: {:.0}%", level, score);
+        Ok(AttemptResult {
+            new_best_score: previous.best_score.map_or(true, |best| score > best),
+            new_best_time: current.best_time_seconds != previous.best_time_seconds,
+            attempt,
+            level: current,
+            unlocked_level,
+        })
+    })
+}
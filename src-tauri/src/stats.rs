@@ -0,0 +1,332 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::goals;
+use crate::library::{self, SheetMusicItem};
+use crate::practice::{self, PracticeSession};
+use crate::scheduling::TimeWindow;
+
+// Less than a minute on a day doesn't keep a streak going
+const STREAK_MIN_SECONDS: i64 = 60;
+const UNKNOWN_COMPOSER: &str = "Unknown composer";
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum GroupBy {
+    Day,
+    Week,
+    Month,
+    Piece,
+    Composer,
+}
+
+// Shaped like Chart.js data so the Dashboard can hand it straight to a chart
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartDataset {
+    pub label: String,
+    pub data: Vec<f64>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartSeries {
+    pub labels: Vec<String>,
+    pub datasets: Vec<ChartDataset>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PieceTotal {
+    pub sheet_music_id: String,
+    pub title: String,
+    pub composer: String,
+    pub minutes: f64,
+    pub sessions: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ComposerTotal {
+    pub composer: String,
+    pub minutes: f64,
+    pub sessions: usize,
+    pub pieces: usize,
+}
+
+// Over the whole history, not just the range; today not practised yet doesn't break the current streak
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Streaks {
+    pub current_days: u32,
+    pub longest_days: u32,
+    pub longest_start: Option<NaiveDate>,
+    pub longest_end: Option<NaiveDate>,
+    pub practiced_today: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalProgress {
+    pub daily_minutes: Option<u32>,
+    pub today_minutes: f64,
+    pub weekly_minutes: Option<u32>,
+    pub this_week_minutes: f64,
+    // Days and weeks (Monday to Sunday) in the range that reached their goal
+    pub days_met: u32,
+    pub weeks_met: u32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PracticeStats {
+    pub total_minutes: f64,
+    pub session_count: usize,
+    pub days_practiced: usize,
+    pub average_session_minutes: f64,
+    pub series: ChartSeries,
+    pub by_piece: Vec<PieceTotal>,
+    pub by_composer: Vec<ComposerTotal>,
+    pub streaks: Streaks,
+    pub goals: GoalProgress,
+}
+
+fn minutes(seconds: i64) -> f64 {
+    (seconds as f64 / 6.0).round() / 10.0
+}
+
+// Sessions count towards the day they started on, in the machine's zone
+pub fn local_date(time: DateTime<Utc>) -> NaiveDate {
+    time.with_timezone(&Local).date_naive()
+}
+
+pub fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap()
+}
+
+fn daily_seconds(sessions: &[(&PracticeSession, i64)]) -> BTreeMap<NaiveDate, i64> {
+    let mut days = BTreeMap::new();
+    for (session, seconds) in sessions {
+        *days.entry(local_date(session.started_at)).or_insert(0) += seconds;
+    }
+    days
+}
+
+fn streaks(days: &BTreeMap<NaiveDate, i64>, today: NaiveDate) -> Streaks {
+    let practiced: BTreeSet<NaiveDate> = days
+        .iter()
+        .filter(|(_, seconds)| **seconds >= STREAK_MIN_SECONDS)
+        .map(|(date, _)| *date)
+        .collect();
+    let mut result = Streaks {
+        practiced_today: practiced.contains(&today),
+        ..Streaks::default()
+    };
+
+    let mut run: Option<(NaiveDate, NaiveDate)> = None;
+    for date in &practiced {
+        run = match run {
+            Some((start, end)) if end.succ_opt() == Some(*date) => Some((start, *date)),
+            _ => Some((*date, *date)),
+        };
+        let (start, end) = run.unwrap();
+        let length = (end - start).num_days() as u32 + 1;
+        if length > result.longest_days {
+            result.longest_days = length;
+            result.longest_start = Some(start);
+            result.longest_end = Some(end);
+        }
+    }
+
+    let mut day = if result.practiced_today { today } else { today - Duration::days(1) };
+    while practiced.contains(&day) {
+        result.current_days += 1;
+        day -= Duration::days(1);
+    }
+    result
+}
+
+fn bucket_series(
+    days: &BTreeMap<NaiveDate, i64>,
+    first: NaiveDate,
+    last: NaiveDate,
+    group_by: GroupBy,
+    goal_minutes: Option<u32>,
+) -> ChartSeries {
+    let (bucket, label): (fn(NaiveDate) -> NaiveDate, &str) = match group_by {
+        GroupBy::Week => (week_start, "%Y-%m-%d"),
+        GroupBy::Month => (month_start, "%Y-%m"),
+        _ => (|date| date, "%Y-%m-%d"),
+    };
+    // Every bucket in the range is listed so quiet days show up as gaps in the chart
+    let mut buckets: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    let mut date = first;
+    while date <= last {
+        buckets.entry(bucket(date)).or_insert(0);
+        date = date.succ_opt().unwrap();
+    }
+    for (date, seconds) in days.range(first..=last) {
+        *buckets.get_mut(&bucket(*date)).unwrap() += seconds;
+    }
+
+    let mut datasets = vec![ChartDataset {
+        label: "Minutes practised".to_string(),
+        data: buckets.values().map(|seconds| minutes(*seconds)).collect(),
+    }];
+    if let Some(goal) = goal_minutes {
+        datasets.push(ChartDataset {
+            label: "Goal".to_string(),
+            data: vec![goal as f64; buckets.len()],
+        });
+    }
+    ChartSeries {
+        labels: buckets.keys().map(|date| date.format(label).to_string()).collect(),
+        datasets,
+    }
+}
+
+fn totals_series(labels: Vec<String>, data: Vec<f64>) -> ChartSeries {
+    ChartSeries {
+        labels,
+        datasets: vec![ChartDataset {
+            label: "Minutes practised".to_string(),
+            data,
+        }],
+    }
+}
+
+fn piece_totals(sessions: &[(&PracticeSession, i64)], items: &HashMap<String, SheetMusicItem>) -> Vec<PieceTotal> {
+    let mut pieces: HashMap<&str, (i64, usize)> = HashMap::new();
+    for (session, seconds) in sessions {
+        let entry = pieces.entry(session.sheet_music_id.as_str()).or_insert((0, 0));
+        entry.0 += seconds;
+        entry.1 += 1;
+    }
+    let mut totals: Vec<(PieceTotal, i64)> = pieces
+        .into_iter()
+        .map(|(id, (seconds, count))| {
+            let item = items.get(id);
+            let composer = item.map(|item| item.composer.trim()).filter(|composer| !composer.is_empty());
+            let total = PieceTotal {
+                sheet_music_id: id.to_string(),
+                title: item.map(|item| item.title.clone()).unwrap_or_else(|| "Removed piece".to_string()),
+                composer: composer.unwrap_or(UNKNOWN_COMPOSER).to_string(),
+                minutes: minutes(seconds),
+                sessions: count,
+            };
+            (total, seconds)
+        })
+        .collect();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.title.cmp(&b.0.title)));
+    totals.into_iter().map(|(total, _)| total).collect()
+}
+
+fn composer_totals(sessions: &[(&PracticeSession, i64)], pieces: &[PieceTotal]) -> Vec<ComposerTotal> {
+    let composer_of: HashMap<&str, &str> = pieces
+        .iter()
+        .map(|piece| (piece.sheet_music_id.as_str(), piece.composer.as_str()))
+        .collect();
+    let mut composers: HashMap<&str, (i64, usize, HashSet<&str>)> = HashMap::new();
+    for (session, seconds) in sessions {
+        let composer = composer_of.get(session.sheet_music_id.as_str()).copied().unwrap_or(UNKNOWN_COMPOSER);
+        let entry = composers.entry(composer).or_default();
+        entry.0 += seconds;
+        entry.1 += 1;
+        entry.2.insert(&session.sheet_music_id);
+    }
+    let mut totals: Vec<(ComposerTotal, i64)> = composers
+        .into_iter()
+        .map(|(composer, (seconds, count, pieces))| {
+            let total = ComposerTotal {
+                composer: composer.to_string(),
+                minutes: minutes(seconds),
+                sessions: count,
+                pieces: pieces.len(),
+            };
+            (total, seconds)
+        })
+        .collect();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.composer.cmp(&b.0.composer)));
+    totals.into_iter().map(|(total, _)| total).collect()
+}
+
+// One call for the Dashboard: totals, the chart for `groupBy`, per-piece and per-composer time, streaks and goals.
+// Sessions belong to the range by their start; a running session counts with the time it has so far
+#[tauri::command]
+pub fn practice_stats(range: TimeWindow, group_by: GroupBy) -> Result<PracticeStats, String> {
+    if range.end <= range.start {
+        return Err("Range end must be after its start".to_string());
+    }
+    let now = Utc::now();
+    let today = local_date(now);
+    let goals = goals::current()?;
+    let items: HashMap<String, SheetMusicItem> = library::read(|library| {
+        library.items.iter().map(|item| (item.id.clone(), item.clone())).collect()
+    })?;
+    let all_sessions = practice::read(|log| log.sessions.clone())?;
+
+    let timed: Vec<(&PracticeSession, i64)> = all_sessions
+        .iter()
+        .map(|session| (session, session.active_duration(now).num_seconds()))
+        .filter(|(_, seconds)| *seconds > 0)
+        .collect();
+    let in_range: Vec<(&PracticeSession, i64)> = timed
+        .iter()
+        .filter(|(session, _)| session.started_at >= range.start && session.started_at < range.end)
+        .copied()
+        .collect();
+
+    let all_days = daily_seconds(&timed);
+    let range_days = daily_seconds(&in_range);
+    let first = local_date(range.start);
+    let last = local_date(range.end - Duration::nanoseconds(1));
+    let total_seconds: i64 = in_range.iter().map(|(_, seconds)| seconds).sum();
+
+    let by_piece = piece_totals(&in_range, &items);
+    let by_composer = composer_totals(&in_range, &by_piece);
+    let series = match group_by {
+        GroupBy::Day => bucket_series(&range_days, first, last, group_by, goals.daily_minutes),
+        GroupBy::Week => bucket_series(&range_days, first, last, group_by, goals.weekly_minutes),
+        GroupBy::Month => bucket_series(&range_days, first, last, group_by, None),
+        GroupBy::Piece => totals_series(
+            by_piece.iter().map(|piece| piece.title.clone()).collect(),
+            by_piece.iter().map(|piece| piece.minutes).collect(),
+        ),
+        GroupBy::Composer => totals_series(
+            by_composer.iter().map(|composer| composer.composer.clone()).collect(),
+            by_composer.iter().map(|composer| composer.minutes).collect(),
+        ),
+    };
+
+    let this_week = week_start(today);
+    let mut weeks: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    for (date, seconds) in &range_days {
+        *weeks.entry(week_start(*date)).or_insert(0) += seconds;
+    }
+    let reached = |seconds: i64, goal: Option<u32>| goal.is_some_and(|goal| seconds >= goal as i64 * 60);
+    let progress = GoalProgress {
+        daily_minutes: goals.daily_minutes,
+        today_minutes: minutes(all_days.get(&today).copied().unwrap_or(0)),
+        weekly_minutes: goals.weekly_minutes,
+        this_week_minutes: minutes(all_days.range(this_week..=today).map(|(_, seconds)| seconds).sum()),
+        days_met: range_days.values().filter(|seconds| reached(**seconds, goals.daily_minutes)).count() as u32,
+        weeks_met: weeks.values().filter(|seconds| reached(**seconds, goals.weekly_minutes)).count() as u32,
+    };
+
+    Ok(PracticeStats {
+        total_minutes: minutes(total_seconds),
+        session_count: in_range.len(),
+        days_practiced: range_days.len(),
+        average_session_minutes: if in_range.is_empty() { 0.0 } else { minutes(total_seconds / in_range.len() as i64) },
+        series,
+        by_piece,
+        by_composer,
+        streaks: streaks(&all_days, today),
+        goals: progress,
+    })
+}
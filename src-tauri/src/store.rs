@@ -0,0 +1,66 @@
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// Directory holding the backend's JSON documents, set once the app has a data dir
+static DATA_DIR: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn init(dir: PathBuf) -> Result<(), String> {
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create data dir {}: {}", dir.display(), e))?;
+    println!("Using data directory: {}", dir.display());
+    *DATA_DIR.lock().unwrap() = Some(dir);
+    Ok(())
+}
+
+pub fn data_dir() -> Result<PathBuf, String> {
+    DATA_DIR
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| "Data directory not initialized".to_string())
+}
+
+// Returns a subdirectory of the data dir, creating it if needed
+pub fn sub_dir(name: &str) -> Result<PathBuf, String> {
+    let dir = data_dir()?.join(name);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+// Loads a JSON document, falling back to the default value when it doesn't exist yet
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> Result<T, String> {
+    let path = data_dir()?.join(format!("{}.json", name));
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let data = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_slice(&data).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+// Writes to a temporary file first so a crash never leaves a half-written document
+pub fn save<T: Serialize>(name: &str, value: &T) -> Result<(), String> {
+    let dir = data_dir()?;
+    let path = dir.join(format!("{}.json", name));
+    let tmp_path = dir.join(format!("{}.json.tmp", name));
+    let data = serde_json::to_vec_pretty(value).map_err(|e| format!("Failed to serialize {}: {}", name, e))?;
+    fs::write(&tmp_path, data).map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+    fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+// Tests that touch the shared documents hold this, so they take turns on one scratch data dir
+#[cfg(test)]
+pub fn lock_for_test() -> std::sync::MutexGuard<'static, ()> {
+    let guard = TEST_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if data_dir().is_err() {
+        let dir = std::env::temp_dir().join(format!("partitura-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        init(dir).unwrap();
+    }
+    guard
+}
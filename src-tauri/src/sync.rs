@@ -0,0 +1,1131 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::api_client::{self, ApiClient, ApiError, SheetMusicUpdate};
+use crate::calendar::{self, PracticeEvent};
+use crate::library::{self, SheetMusicItem};
+use crate::{session, sightreading_progress, store, uploads};
+
+const SYNC_DOCUMENT: &str = "sync_state";
+const SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+// Fields the API lets us change after creation; these are merged one by one
+const SHEET_MUSIC_FIELDS: [&str; 3] = ["title", "composer", "isFavorite"];
+// Everything addEvent and updateEvent in calendarService.ts send; zones and all-day stay on this device
+const EVENT_FIELDS: [&str; 8] = ["title", "description", "startTime", "endTime", "isCompleted", "sheetMusicId", "color", "type"];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum EntityKind {
+    SheetMusic,
+    CalendarEvent,
+}
+
+// Pulled in this order, so events arrive after the pieces they link to
+const ENTITY_KINDS: [EntityKind; 2] = [EntityKind::SheetMusic, EntityKind::CalendarEvent];
+
+impl EntityKind {
+    fn fields(self) -> &'static [&'static str] {
+        match self {
+            EntityKind::SheetMusic => &SHEET_MUSIC_FIELDS,
+            EntityKind::CalendarEvent => &EVENT_FIELDS,
+        }
+    }
+
+    fn endpoint(self, user_id: &str) -> String {
+        match self {
+            EntityKind::SheetMusic => format!("/sheet-music/{}", user_id),
+            EntityKind::CalendarEvent => format!("/calendar/{}", user_id),
+        }
+    }
+
+    fn is_valid(self, record: &Value) -> bool {
+        match self {
+            EntityKind::SheetMusic => serde_json::from_value::<SheetMusicItem>(record.clone()).is_ok(),
+            EntityKind::CalendarEvent => serde_json::from_value::<PracticeEvent>(record.clone()).is_ok(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum OperationKind {
+    Upsert,
+    Delete,
+}
+
+// One local change waiting to be pushed
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Operation {
+    pub id: String,
+    pub entity: EntityKind,
+    pub entity_id: String,
+    pub kind: OperationKind,
+    pub fields: Vec<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictRecord {
+    pub entity: EntityKind,
+    pub entity_id: String,
+    pub field: String,
+    pub local_value: Value,
+    pub remote_value: Value,
+    pub winner: String,
+    pub resolved_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConfig {
+    pub user_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncState {
+    pub config: Option<SyncConfig>,
+    // Remote records as of the last pull, used to tell remote edits apart from local ones
+    pub base: HashMap<EntityKind, HashMap<String, Value>>,
+    pub operations: Vec<Operation>,
+    pub conflicts: Vec<ConflictRecord>,
+    pub last_sync: Option<DateTime<Utc>>,
+    // The API picks its own IDs for new events; local event IDs map to them here
+    #[serde(default)]
+    pub remote_ids: HashMap<String, String>,
+}
+
+// What a pull decided. It lands in the library or calendar first and only then in the sync state,
+// so a failed apply can't leave a base that claims remote changes were merged
+#[derive(Default)]
+struct Merge {
+    base: HashMap<String, Value>,
+    upserts: Vec<Value>,
+    removals: Vec<String>,
+    conflicts: Vec<ConflictRecord>,
+    // Pending local changes the remote side won
+    dropped_entities: Vec<String>,
+    dropped_fields: Vec<(String, String)>,
+    pulled: usize,
+}
+
+// Outcome of pushing one entity
+enum Pushed {
+    Removed,
+    // The record the server now has, and the ID it gave a newly created event
+    Saved { record: Value, remote_id: Option<String> },
+    // A new piece waiting in the upload queue; its operations stay until the upload is done
+    Queued,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub status: String,
+    pub pulled: usize,
+    pub pushed: usize,
+    pub conflicts: usize,
+    pub pending_operations: usize,
+    // Sight-reading attempts that only exist on this device because the server can't take them yet
+    pub unsynced_sight_reading_attempts: usize,
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    pub configured: bool,
+    pub pending_operations: usize,
+    pub conflicts: usize,
+    pub last_sync: Option<DateTime<Utc>>,
+}
+
+static SYNC_STATE: Lazy<Mutex<Option<SyncState>>> = Lazy::new(|| Mutex::new(None));
+// Only one sync may talk to the API at a time
+static SYNC_RUNNING: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+fn read_state<R>(f: impl FnOnce(&SyncState) -> R) -> Result<R, String> {
+    let mut guard = SYNC_STATE.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(SYNC_DOCUMENT)?);
+    }
+    Ok(f(guard.as_ref().unwrap()))
+}
+
+// Same copy-then-save approach as the library, so a failed save leaves memory untouched
+fn with_state<R>(f: impl FnOnce(&mut SyncState) -> Result<R, String>) -> Result<R, String> {
+    let mut guard = SYNC_STATE.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(SYNC_DOCUMENT)?);
+    }
+    let mut next = guard.as_ref().unwrap().clone();
+    let result = f(&mut next)?;
+    store::save(SYNC_DOCUMENT, &next)?;
+    *guard = Some(next);
+    Ok(result)
+}
+
+fn to_record<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+fn changed_fields(kind: EntityKind, before: &Value, after: &Value) -> Vec<String> {
+    kind.fields()
+        .iter()
+        .filter(|field| before.get(**field) != after.get(**field))
+        .map(|field| field.to_string())
+        .collect()
+}
+
+// Called by the library after every local change to append to the operation log
+pub fn record_item_changes(before: &[SheetMusicItem], after: &[SheetMusicItem]) {
+    let keyed = |items: &[SheetMusicItem]| items.iter().map(|item| (item.id.clone(), to_record(item))).collect();
+    record_changes(EntityKind::SheetMusic, keyed(before), keyed(after));
+}
+
+// The API has no recurrence, so series and their edited occurrences stay on this device
+fn is_synced_event(event: &PracticeEvent) -> bool {
+    event.rrule.is_none() && event.recurring_event_id.is_none()
+}
+
+// Called by the calendar after every local change
+pub fn record_event_changes(before: &[PracticeEvent], after: &[PracticeEvent]) {
+    let synced: Vec<(String, Value)> = after
+        .iter()
+        .filter(|event| is_synced_event(event))
+        .map(|event| (event.id.clone(), to_record(event)))
+        .collect();
+    // An event that became a series stays on the account as it was instead of being deleted there
+    let previous: Vec<(String, Value)> = before
+        .iter()
+        .filter(|event| is_synced_event(event))
+        .filter(|event| synced.iter().any(|(id, _)| *id == event.id) || !after.iter().any(|other| other.id == event.id))
+        .map(|event| (event.id.clone(), to_record(event)))
+        .collect();
+    record_changes(EntityKind::CalendarEvent, previous, synced);
+}
+
+fn record_changes(kind: EntityKind, before: Vec<(String, Value)>, after: Vec<(String, Value)>) {
+    let now = Utc::now();
+    let mut operations = Vec::new();
+    for (id, record) in &after {
+        let fields = match before.iter().find(|(old_id, _)| old_id == id) {
+            Some((_, old)) => changed_fields(kind, old, record),
+            None => kind.fields().iter().map(|field| field.to_string()).collect(),
+        };
+        if !fields.is_empty() {
+            operations.push(new_operation(kind, id, OperationKind::Upsert, fields, now));
+        }
+    }
+    for (id, _) in before.iter().filter(|(id, _)| !after.iter().any(|(other, _)| other == id)) {
+        operations.push(new_operation(kind, id, OperationKind::Delete, Vec::new(), now));
+    }
+    if operations.is_empty() {
+        return;
+    }
+
+    if let Err(e) = with_state(|state| {
+        state.operations.extend(operations);
+        Ok(())
+    }) {
+        println!("Failed to record sync operations: {}", e);
+    }
+}
+
+// Called by the upload queue once a piece's PDF is on the server, so pushes treat the piece as known there
+pub fn upload_finished(user_id: &str, item: &SheetMusicItem, pdf_path: &str) {
+    let exists_locally = library::read(|library| library.item(&item.id).is_some()).unwrap_or(true);
+    let mut record = to_record(item);
+    record["pdfPath"] = Value::String(pdf_path.to_string());
+    let result = with_state(|state| {
+        if state.config.as_ref().map(|config| config.user_id.as_str()) != Some(user_id) {
+            return Ok(());
+        }
+        state.base.entry(EntityKind::SheetMusic).or_default().insert(item.id.clone(), record);
+        // Deleted here while it was uploading, so the next push removes it again
+        if !exists_locally {
+            state.operations.push(new_operation(EntityKind::SheetMusic, &item.id, OperationKind::Delete, Vec::new(), Utc::now()));
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        println!("Failed to record upload of {}: {}", item.id, e);
+    }
+}
+
+fn new_operation(entity: EntityKind, entity_id: &str, kind: OperationKind, fields: Vec<String>, timestamp: DateTime<Utc>) -> Operation {
+    Operation {
+        id: library::new_id(),
+        entity,
+        entity_id: entity_id.to_string(),
+        kind,
+        fields,
+        timestamp,
+    }
+}
+
+fn fetch_remote(api: &ApiClient, config: &SyncConfig, kind: EntityKind) -> Result<Vec<Value>, ApiError> {
+    let records: Option<Vec<Value>> = api.get_json(&kind.endpoint(&config.user_id))?;
+    let mut records = records.unwrap_or_default();
+    if kind == EntityKind::CalendarEvent {
+        records.iter_mut().for_each(normalize_event_record);
+    }
+    Ok(records)
+}
+
+// Times are compared as the strings we write ourselves, so "10:00:00.000Z" from the API isn't an edit
+fn normalize_event_record(record: &mut Value) {
+    for field in ["startTime", "endTime"] {
+        let time = record
+            .get(field)
+            .and_then(Value::as_str)
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok());
+        if let Some(time) = time {
+            record[field] = to_record(&time.with_timezone(&Utc));
+        }
+    }
+    if record.get("description").map(Value::is_null).unwrap_or(false) {
+        record["description"] = Value::String(String::new());
+    }
+}
+
+// Local records taking part in the merge, and IDs remote records must leave alone
+fn local_records(kind: EntityKind) -> Result<(Vec<Value>, HashSet<String>), String> {
+    match kind {
+        EntityKind::SheetMusic => library::read(|library| (library.items.iter().map(to_record).collect(), HashSet::new())),
+        EntityKind::CalendarEvent => calendar::read(|calendar| {
+            let (synced, local_only): (Vec<&PracticeEvent>, Vec<&PracticeEvent>) =
+                calendar.events.iter().partition(|event| is_synced_event(event));
+            (
+                synced.into_iter().map(to_record).collect(),
+                local_only.into_iter().map(|event| event.id.clone()).collect(),
+            )
+        }),
+    }
+}
+
+fn update_remote(api: &ApiClient, config: &SyncConfig, item: &SheetMusicItem) -> Result<(), ApiError> {
+    let update = SheetMusicUpdate {
+        title: Some(item.title.clone()),
+        composer: Some(item.composer.clone()),
+        is_favorite: Some(item.is_favorite),
+    };
+    api.update_sheet_music(&config.user_id, &item.id, &update)
+}
+
+fn delete_remote(api: &ApiClient, config: &SyncConfig, id: &str) -> Result<(), ApiError> {
+    match api.delete_sheet_music(&config.user_id, id) {
+        Ok(()) | Err(ApiError::NotFound) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn push_item(api: &ApiClient, config: &SyncConfig, entity_id: &str, is_delete: bool, known_remotely: bool) -> Result<Pushed, ApiError> {
+    let local = library::read(|library| library.item(entity_id).cloned()).map_err(local_error)?;
+    let record = match (is_delete, local) {
+        (true, _) if known_remotely => delete_remote(api, config, entity_id).map(|_| None)?,
+        (true, _) | (false, None) => None,
+        (false, Some(item)) if known_remotely => update_remote(api, config, &item).map(|_| Some(to_record(&item)))?,
+        // New pieces go through the resumable upload queue, which also picks up ones the user queued
+        (false, Some(item)) => {
+            uploads::enqueue(&config.user_id, &item, &item.pdf_path).map_err(local_error)?;
+            return Ok(Pushed::Queued);
+        }
+    };
+    Ok(match record {
+        Some(record) => Pushed::Saved { record, remote_id: None },
+        None => Pushed::Removed,
+    })
+}
+
+fn push_event(api: &ApiClient, config: &SyncConfig, entity_id: &str, remote_id: &str, is_delete: bool, known_remotely: bool) -> Result<Pushed, ApiError> {
+    let local = calendar::read(|calendar| calendar.event(entity_id).filter(|event| is_synced_event(event)).cloned()).map_err(local_error)?;
+    match (is_delete, local) {
+        (true, _) if known_remotely => match api.delete_event(&config.user_id, remote_id) {
+            Ok(()) | Err(ApiError::NotFound) => Ok(Pushed::Removed),
+            Err(e) => Err(e),
+        },
+        (true, _) | (false, None) => Ok(Pushed::Removed),
+        (false, Some(event)) if known_remotely => {
+            let remote = PracticeEvent {
+                id: remote_id.to_string(),
+                ..event.clone()
+            };
+            api.update_event(&config.user_id, &remote)?;
+            Ok(Pushed::Saved {
+                record: to_record(&event),
+                remote_id: None,
+            })
+        }
+        (false, Some(event)) => {
+            let created = api.create_event(&config.user_id, &event)?;
+            if created.id.is_empty() {
+                return Err(ApiError::InvalidResponse {
+                    message: "Created event has no ID".to_string(),
+                });
+            }
+            Ok(Pushed::Saved {
+                record: to_record(&event),
+                remote_id: Some(created.id).filter(|id| id != entity_id),
+            })
+        }
+    }
+}
+
+// The API's records carry no updatedAt, so all we know is that a remote change came after the last sync.
+// Local edits made since then win; before the first sync every local edit does
+fn remote_timestamp(record: &Value, last_sync: Option<DateTime<Utc>>) -> DateTime<Utc> {
+    record
+        .get("updatedAt")
+        .or_else(|| record.get("updated_at"))
+        .and_then(Value::as_str)
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|value| value.with_timezone(&Utc))
+        .or(last_sync)
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+}
+
+fn pending_field_times(operations: &[&Operation], entity_id: &str) -> HashMap<String, DateTime<Utc>> {
+    let mut times = HashMap::new();
+    for operation in operations.iter().filter(|op| op.entity_id == entity_id && op.kind == OperationKind::Upsert) {
+        for field in &operation.fields {
+            let time = times.entry(field.clone()).or_insert(operation.timestamp);
+            if operation.timestamp > *time {
+                *time = operation.timestamp;
+            }
+        }
+    }
+    times
+}
+
+fn drop_pending_field(operations: &mut Vec<Operation>, kind: EntityKind, entity_id: &str, field: &str) {
+    let matches = |op: &Operation| op.entity == kind && op.entity_id == entity_id;
+    for operation in operations.iter_mut().filter(|op| matches(op)) {
+        operation.fields.retain(|pending| pending != field);
+    }
+    operations.retain(|op| !matches(op) || op.kind == OperationKind::Delete || !op.fields.is_empty());
+}
+
+// Three-way merge of the remote list against the last known base and the local records; changes nothing itself
+fn merge_remote(
+    state: &SyncState,
+    kind: EntityKind,
+    local_records: &[Value],
+    local_only: &HashSet<String>,
+    mut remote: Vec<Value>,
+    pulled_at: DateTime<Utc>,
+) -> Merge {
+    // Events we created carry the server's ID there; everything here goes by the local one
+    let local_ids: HashMap<&str, &str> = state.remote_ids.iter().map(|(local, remote)| (remote.as_str(), local.as_str())).collect();
+    for record in &mut remote {
+        if let Some(local_id) = record.get("id").and_then(Value::as_str).and_then(|id| local_ids.get(id)) {
+            record["id"] = Value::String(local_id.to_string());
+        }
+    }
+
+    let operations: Vec<&Operation> = state.operations.iter().filter(|op| op.entity == kind).collect();
+    let mut merge = Merge {
+        base: state.base.get(&kind).cloned().unwrap_or_default(),
+        ..Merge::default()
+    };
+    let record_id = |record: &Value| record.get("id").and_then(Value::as_str).map(str::to_string);
+
+    for record in &remote {
+        let id = match record_id(record) {
+            Some(id) => id,
+            None => continue,
+        };
+        if local_only.contains(&id) {
+            merge.base.insert(id, record.clone());
+            continue;
+        }
+        let base_record = merge.base.get(&id).cloned();
+        let remote_changed = kind
+            .fields()
+            .iter()
+            .any(|field| base_record.as_ref().and_then(|base| base.get(*field)) != record.get(*field));
+        let remote_time = remote_timestamp(record, state.last_sync);
+        let field_times = pending_field_times(&operations, &id);
+        let pending_delete = operations.iter().any(|op| op.entity_id == id && op.kind == OperationKind::Delete);
+
+        match local_records.iter().find(|local| local.get("id").and_then(Value::as_str) == Some(id.as_str())) {
+            None if pending_delete => {
+                // Deleted here but edited remotely since: the later change wins
+                let delete_time = operations
+                    .iter()
+                    .filter(|op| op.entity_id == id && op.kind == OperationKind::Delete)
+                    .map(|op| op.timestamp)
+                    .max()
+                    .unwrap_or(pulled_at);
+                if base_record.is_some() && remote_changed && remote_time > delete_time && kind.is_valid(record) {
+                    merge.conflicts.push(conflict(kind, &id, "deleted", Value::Bool(true), record.clone(), "remote", pulled_at));
+                    merge.dropped_entities.push(id.clone());
+                    merge.upserts.push(record.clone());
+                    merge.pulled += 1;
+                }
+            }
+            None => {
+                if kind.is_valid(record) {
+                    merge.upserts.push(record.clone());
+                    merge.pulled += 1;
+                }
+            }
+            Some(local) => {
+                let mut merged = local.clone();
+                let mut changed = false;
+                for field in kind.fields() {
+                    let remote_value = record.get(*field).cloned().unwrap_or(Value::Null);
+                    let base_value = base_record.as_ref().and_then(|base| base.get(*field)).cloned();
+                    let local_value = merged.get(*field).cloned().unwrap_or(Value::Null);
+                    if base_value.as_ref() == Some(&remote_value) || local_value == remote_value {
+                        continue;
+                    }
+                    match field_times.get(*field) {
+                        Some(local_time) if *local_time > remote_time => {
+                            merge.conflicts.push(conflict(kind, &id, field, local_value, remote_value, "local", pulled_at));
+                        }
+                        Some(_) => {
+                            merge.conflicts.push(conflict(kind, &id, field, local_value, remote_value.clone(), "remote", pulled_at));
+                            merge.dropped_fields.push((id.clone(), field.to_string()));
+                            merged[*field] = remote_value;
+                            changed = true;
+                        }
+                        None => {
+                            merged[*field] = remote_value;
+                            changed = true;
+                        }
+                    }
+                }
+                // Pieces that only exist remotely follow the server's file location
+                let local_pdf = local.get("pdfPath").and_then(Value::as_str).unwrap_or_default();
+                if kind == EntityKind::SheetMusic && !Path::new(local_pdf).is_file() {
+                    if let Some(pdf_path) = record.get("pdfPath").filter(|path| **path != merged["pdfPath"]) {
+                        merged["pdfPath"] = pdf_path.clone();
+                        changed = true;
+                    }
+                }
+                if changed && kind.is_valid(&merged) {
+                    merge.upserts.push(merged);
+                    merge.pulled += 1;
+                }
+            }
+        }
+        merge.base.insert(id, record.clone());
+    }
+
+    // Records that vanished remotely since the last pull were deleted there
+    let remote_ids: HashSet<String> = remote.iter().filter_map(record_id).collect();
+    let deleted: Vec<String> = merge.base.keys().filter(|id| !remote_ids.contains(*id)).cloned().collect();
+    for id in deleted {
+        merge.base.remove(&id);
+        let has_local_edits = operations.iter().any(|op| op.entity_id == id && op.kind == OperationKind::Upsert);
+        if has_local_edits {
+            // Local edits are newer than anything we know about the remote copy, so it gets recreated
+            merge.conflicts.push(conflict(kind, &id, "deleted", Value::Bool(false), Value::Bool(true), "local", pulled_at));
+        } else if local_records.iter().any(|local| local.get("id").and_then(Value::as_str) == Some(id.as_str())) {
+            merge.removals.push(id);
+            merge.pulled += 1;
+        }
+    }
+    merge
+}
+
+fn apply_merge(kind: EntityKind, merge: &Merge) -> Result<(), String> {
+    match kind {
+        EntityKind::SheetMusic => library::apply_remote(|library| {
+            for item in merge.upserts.iter().filter_map(|record| serde_json::from_value::<SheetMusicItem>(record.clone()).ok()) {
+                library.upsert_item(item);
+            }
+            for id in &merge.removals {
+                library.remove_item(id);
+            }
+            Ok(())
+        }),
+        EntityKind::CalendarEvent => calendar::apply_remote(|calendar| {
+            for event in merge.upserts.iter().filter_map(|record| serde_json::from_value::<PracticeEvent>(record.clone()).ok()) {
+                match calendar.events.iter_mut().find(|existing| existing.id == event.id) {
+                    Some(existing) => *existing = event,
+                    None => calendar.events.push(event),
+                }
+            }
+            calendar.events.retain(|event| !merge.removals.contains(&event.id));
+            Ok(())
+        }),
+    }
+}
+
+fn commit_merge(state: &mut SyncState, kind: EntityKind, merge: Merge) {
+    state.base.insert(kind, merge.base);
+    state.conflicts.extend(merge.conflicts);
+    for id in &merge.dropped_entities {
+        state.operations.retain(|op| op.entity != kind || op.entity_id != *id);
+    }
+    for (id, field) in &merge.dropped_fields {
+        drop_pending_field(&mut state.operations, kind, id, field);
+    }
+}
+
+// Returns how many records were pulled and how many conflicts were resolved
+fn pull(api: &ApiClient, config: &SyncConfig, kind: EntityKind, pulled_at: DateTime<Utc>) -> Result<(usize, usize), ApiError> {
+    let remote = fetch_remote(api, config, kind)?;
+    let (local, local_only) = local_records(kind).map_err(local_error)?;
+    let merge = read_state(|state| merge_remote(state, kind, &local, &local_only, remote, pulled_at)).map_err(local_error)?;
+    apply_merge(kind, &merge).map_err(local_error)?;
+    let counts = (merge.pulled, merge.conflicts.len());
+    with_state(|state| {
+        commit_merge(state, kind, merge);
+        Ok(())
+    })
+    .map_err(local_error)?;
+    Ok(counts)
+}
+
+fn conflict(
+    entity: EntityKind,
+    entity_id: &str,
+    field: &str,
+    local_value: Value,
+    remote_value: Value,
+    winner: &str,
+    resolved_at: DateTime<Utc>,
+) -> ConflictRecord {
+    ConflictRecord {
+        entity,
+        entity_id: entity_id.to_string(),
+        field: field.to_string(),
+        local_value,
+        remote_value,
+        winner: winner.to_string(),
+        resolved_at,
+    }
+}
+
+fn local_error(message: String) -> ApiError {
+    ApiError::Local { message }
+}
+
+// Pushes pending operations entity by entity; stops early when the API goes away
+fn push_operations(api: &ApiClient, config: &SyncConfig, report: &mut SyncReport) -> Result<(), ApiError> {
+    let operations = read_state(|state| state.operations.clone()).map_err(local_error)?;
+    let mut targets: Vec<(EntityKind, String)> = Vec::new();
+    for operation in &operations {
+        let target = (operation.entity, operation.entity_id.clone());
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+
+    for (kind, entity_id) in targets {
+        let batch: Vec<&Operation> = operations.iter().filter(|op| op.entity == kind && op.entity_id == entity_id).collect();
+        let op_ids: Vec<String> = batch.iter().map(|op| op.id.clone()).collect();
+        let is_delete = batch.last().map(|op| op.kind == OperationKind::Delete).unwrap_or(false);
+        let (known_remotely, remote_id) = read_state(|state| {
+            (
+                state.base.get(&kind).map(|base| base.contains_key(&entity_id)).unwrap_or(false),
+                state.remote_ids.get(&entity_id).cloned().unwrap_or_else(|| entity_id.clone()),
+            )
+        })
+        .map_err(local_error)?;
+
+        let result = match kind {
+            EntityKind::SheetMusic => push_item(api, config, &entity_id, is_delete, known_remotely),
+            EntityKind::CalendarEvent => push_event(api, config, &entity_id, &remote_id, is_delete, known_remotely),
+        };
+
+        match result {
+            Ok(Pushed::Queued) => {}
+            Ok(pushed) => {
+                with_state(|state| {
+                    state.operations.retain(|op| !op_ids.contains(&op.id));
+                    let base = state.base.entry(kind).or_default();
+                    match pushed {
+                        Pushed::Saved { record, remote_id } => {
+                            let merged = match base.get(&entity_id) {
+                                Some(Value::Object(existing)) => {
+                                    let mut merged: Map<String, Value> = existing.clone();
+                                    for field in kind.fields() {
+                                        merged.insert(field.to_string(), record[*field].clone());
+                                    }
+                                    Value::Object(merged)
+                                }
+                                _ => record,
+                            };
+                            base.insert(entity_id.clone(), merged);
+                            if let Some(remote_id) = remote_id {
+                                state.remote_ids.insert(entity_id.clone(), remote_id);
+                            }
+                        }
+                        Pushed::Removed => {
+                            base.remove(&entity_id);
+                            state.remote_ids.remove(&entity_id);
+                        }
+                        Pushed::Queued => {}
+                    }
+                    Ok(())
+                })
+                .map_err(local_error)?;
+                report.pushed += 1;
+            }
+            Err(e @ (ApiError::Rejected { .. } | ApiError::Forbidden | ApiError::NotFound | ApiError::Local { .. })) => {
+                // The server will never accept this change, so retrying forever doesn't help
+                println!("Dropping rejected sync operations for {}: {}", entity_id, e);
+                report.errors.push(e.to_string());
+                with_state(|state| {
+                    state.operations.retain(|op| !op_ids.contains(&op.id));
+                    Ok(())
+                })
+                .map_err(local_error)?;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+pub fn sync_once() -> SyncReport {
+    let _running = SYNC_RUNNING.lock().unwrap();
+    let mut report = SyncReport::default();
+
+    let config = read_state(|state| state.config.clone()).ok().flatten();
+    let config = match config {
+        Some(config) if session::access_token().is_some() => config,
+        _ => {
+            report.status = "notConfigured".to_string();
+            return report;
+        }
+    };
+
+    // Same check ApiStatusContext does before declaring the API available
+    let api = api_client::client();
+    if api.health().is_err() {
+        report.status = "offline".to_string();
+        report.pending_operations = read_state(|state| state.operations.len()).unwrap_or(0);
+        return report;
+    }
+    sync_with(&api, &config)
+}
+
+// One pull-then-push round against an API that answered its health check
+fn sync_with(api: &ApiClient, config: &SyncConfig) -> SyncReport {
+    let mut report = SyncReport::default();
+    let pulled_at = Utc::now();
+    let result = sync_entities(api, config, pulled_at, &mut report);
+
+    report.status = match result {
+        Ok(()) => {
+            let _ = with_state(|state| {
+                state.last_sync = Some(pulled_at);
+                Ok(())
+            });
+            "synced".to_string()
+        }
+        Err(e) => {
+            report.errors.push(e.to_string());
+            if e.is_connection_error() {
+                "offline".to_string()
+            } else {
+                "error".to_string()
+            }
+        }
+    };
+    report.pending_operations = read_state(|state| state.operations.len()).unwrap_or(0);
+    println!(
+        "Sync finished ({}): {} pulled, {} pushed, {} conflicts, {} pending, {} sight-reading attempts not synced",
+        report.status,
+        report.pulled,
+        report.pushed,
+        report.conflicts,
+        report.pending_operations,
+        report.unsynced_sight_reading_attempts
+    );
+    report
+}
+
+fn sync_entities(api: &ApiClient, config: &SyncConfig, pulled_at: DateTime<Utc>, report: &mut SyncReport) -> Result<(), ApiError> {
+    // Pull first so pushes are based on the latest remote copy and creates aren't duplicated
+    for kind in ENTITY_KINDS {
+        let (pulled, conflicts) = pull(api, config, kind, pulled_at)?;
+        report.pulled += pulled;
+        report.conflicts += conflicts;
+    }
+    push_operations(api, config, report)?;
+    match sightreading_progress::sync(api, &config.user_id) {
+        Ok((pulled, pushed)) => {
+            report.pulled += pulled;
+            report.pushed += pushed;
+            Ok(())
+        }
+        // Syncing attempts needs the server to add the /sight-reading/{user}/attempts endpoint. Until it
+        // does, the 404 leaves every attempt on this device, so the report says how many rather than
+        // passing for a full sync; they are pushed on the first sync after the endpoint ships
+        Err(ApiError::NotFound) => {
+            report.unsynced_sight_reading_attempts = sightreading_progress::attempt_count().map_err(local_error)?;
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// Periodically syncs in the background and reports each run to the UI
+pub fn start(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(SYNC_INTERVAL);
+        let report = sync_once();
+        if report.status == "notConfigured" {
+            continue;
+        }
+        if let Err(e) = app.emit("sync-status", report) {
+            println!("Failed to emit sync status: {}", e);
+        }
+    });
+}
+
+// Requests are authorized with the token from the session store
+#[tauri::command]
+pub fn configure_sync(user_id: String) -> Result<(), String> {
+    with_state(|state| {
+        let user_changed = state.config.as_ref().map(|config| config.user_id != user_id).unwrap_or(false);
+        if user_changed {
+            // Another account's base snapshot would make its records look deleted
+            state.base.clear();
+            state.remote_ids.clear();
+        }
+        state.config = Some(SyncConfig { user_id });
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub async fn sync_now() -> Result<SyncReport, String> {
+    tauri::async_runtime::spawn_blocking(sync_once)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_sync_status() -> Result<SyncStatus, String> {
+    read_state(|state| SyncStatus {
+        configured: state.config.is_some(),
+        pending_operations: state.operations.len(),
+        conflicts: state.conflicts.len(),
+        last_sync: state.last_sync,
+    })
+}
+
+#[tauri::command]
+pub fn list_sync_conflicts() -> Result<Vec<ConflictRecord>, String> {
+    read_state(|state| state.conflicts.clone())
+}
+
+#[tauri::command]
+pub fn clear_sync_conflicts() -> Result<(), String> {
+    with_state(|state| {
+        state.conflicts.clear();
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+    use std::sync::Arc;
+
+    const USER_ID: &str = "user-1";
+
+    // Just the routes the sync engine uses, backed by two lists
+    #[derive(Default)]
+    struct MockApi {
+        sheet_music: Vec<Value>,
+        events: Vec<Value>,
+        requests: Vec<String>,
+    }
+
+    fn serve(api: Arc<Mutex<MockApi>>) -> ApiClient {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let body: Value = serde_json::from_str(&body).unwrap_or(Value::Null);
+                let method = request.method().to_string();
+                let url = request.url().to_string();
+                let path: Vec<&str> = url.trim_start_matches("/api/").split('/').collect();
+                let mut api = api.lock().unwrap();
+                api.requests.push(format!("{} {}", method, url));
+                let (status, response) = match (method.as_str(), path.as_slice()) {
+                    ("GET", ["sheet-music", _]) => (200, Value::Array(api.sheet_music.clone())),
+                    ("PUT", ["sheet-music", _, id]) => match api.sheet_music.iter_mut().find(|item| item["id"] == *id) {
+                        Some(item) => {
+                            for (field, value) in body.as_object().unwrap().iter().filter(|(_, value)| !value.is_null()) {
+                                item[field] = value.clone();
+                            }
+                            (200, json!({}))
+                        }
+                        None => (404, json!({ "error": "Not found" })),
+                    },
+                    ("GET", ["calendar", _]) => (200, Value::Array(api.events.clone())),
+                    ("POST", ["calendar", _]) => {
+                        let mut event = body;
+                        event["id"] = Value::String(format!("remote-{}", api.events.len() + 1));
+                        api.events.push(event.clone());
+                        (201, event)
+                    }
+                    ("PUT", ["calendar", _, id]) => match api.events.iter_mut().find(|event| event["id"] == *id) {
+                        Some(event) => {
+                            for (field, value) in body.as_object().unwrap() {
+                                event[field] = value.clone();
+                            }
+                            (200, json!({}))
+                        }
+                        None => (404, json!({ "error": "Not found" })),
+                    },
+                    ("DELETE", ["calendar", _, id]) => {
+                        api.events.retain(|event| event["id"] != *id);
+                        (204, Value::Null)
+                    }
+                    _ => (404, json!({ "error": "Not found" })),
+                };
+                let body = if response.is_null() { String::new() } else { response.to_string() };
+                let _ = request.respond(tiny_http::Response::from_string(body).with_status_code(status));
+            }
+        });
+        ApiClient::new(&url)
+    }
+
+    fn reset() -> SyncConfig {
+        library::apply_remote(|library| {
+            library.items.clear();
+            Ok(())
+        })
+        .unwrap();
+        calendar::clear_for_test();
+        let config = SyncConfig { user_id: USER_ID.to_string() };
+        with_state(|state| {
+            *state = SyncState {
+                config: Some(config.clone()),
+                ..SyncState::default()
+            };
+            Ok(())
+        })
+        .unwrap();
+        config
+    }
+
+    fn remote_item(id: &str, title: &str, composer: &str, updated_at: DateTime<Utc>) -> Value {
+        json!({
+            "id": id,
+            "title": title,
+            "composer": composer,
+            "pdfPath": format!("https://files.example.com/{}.pdf", id),
+            "isFavorite": false,
+            "dateAdded": "2026-01-05T10:00:00Z",
+            "updatedAt": updated_at.to_rfc3339(),
+        })
+    }
+
+    // What the real API sends: no updatedAt at all
+    fn api_item(id: &str, title: &str, composer: &str) -> Value {
+        let mut item = remote_item(id, title, composer, Utc::now());
+        item.as_object_mut().unwrap().remove("updatedAt");
+        item
+    }
+
+    #[test]
+    fn pulls_remote_records_and_pushes_local_events() {
+        let _lock = store::lock_for_test();
+        let config = reset();
+        let mock = Arc::new(Mutex::new(MockApi::default()));
+        let api = serve(mock.clone());
+        {
+            let mut mock = mock.lock().unwrap();
+            mock.sheet_music.push(remote_item("s1", "Clair de lune", "Debussy", Utc::now()));
+            mock.events.push(json!({
+                "id": "r1",
+                "title": "Lesson",
+                "description": null,
+                "startTime": "2026-03-02T16:00:00.000Z",
+                "endTime": "2026-03-02T17:00:00.000Z",
+                "isCompleted": false,
+                "sheetMusicId": "s1",
+                "color": "#3B82F6",
+                "type": "lesson",
+            }));
+        }
+        let local_id = calendar::create_event(calendar::test_event("Scales")).unwrap().event.id;
+
+        let report = sync_with(&api, &config);
+        assert_eq!(report.status, "synced");
+        assert_eq!((report.pulled, report.pushed, report.conflicts), (2, 1, 0));
+        assert_eq!(library::read(|library| library.item("s1").map(|item| item.title.clone())).unwrap().as_deref(), Some("Clair de lune"));
+        let lesson = calendar::get_event("r1".to_string()).unwrap();
+        assert_eq!(lesson.sheet_music_id.as_deref(), Some("s1"));
+        assert_eq!(lesson.event_type, calendar::EventType::Lesson);
+        // The server picked its own ID for the pushed event; locally it keeps the one it had
+        assert_eq!(read_state(|state| state.remote_ids.get(&local_id).cloned()).unwrap().as_deref(), Some("remote-2"));
+        assert_eq!(mock.lock().unwrap().events[1]["title"], "Scales");
+
+        // Millisecond timestamps and the created event coming back under its remote ID are not changes
+        let report = sync_with(&api, &config);
+        assert_eq!((report.pulled, report.pushed, report.pending_operations), (0, 0, 0));
+        assert_eq!(calendar::read(|calendar| calendar.events.len()).unwrap(), 2);
+
+        let mut edited = calendar::get_event(local_id.clone()).unwrap();
+        edited.title = "Scales and arpeggios".to_string();
+        calendar::update_event(edited).unwrap();
+        mock.lock().unwrap().events.retain(|event| event["id"] != "r1");
+        let report = sync_with(&api, &config);
+        assert_eq!((report.pulled, report.pushed), (1, 1));
+        assert!(mock.lock().unwrap().requests.contains(&format!("PUT /api/calendar/{}/remote-2", USER_ID)));
+        assert_eq!(mock.lock().unwrap().events[0]["title"], "Scales and arpeggios");
+        assert!(calendar::get_event("r1".to_string()).is_err());
+    }
+
+    #[test]
+    fn sight_reading_attempts_the_server_cannot_take_are_reported() {
+        let _lock = store::lock_for_test();
+        let config = reset();
+        let api = serve(Arc::new(Mutex::new(MockApi::default())));
+        sightreading_progress::record_sight_reading_attempt(1, 7, 90.0, 60.0).unwrap();
+        let attempts = sightreading_progress::attempt_count().unwrap();
+
+        // The mock has no sight-reading endpoint, like the real server today
+        let report = sync_with(&api, &config);
+        assert_eq!(report.status, "synced");
+        assert!(attempts > 0);
+        assert_eq!(report.unsynced_sight_reading_attempts, attempts);
+    }
+
+    #[test]
+    fn resolves_conflicts_field_by_field() {
+        let _lock = store::lock_for_test();
+        let config = reset();
+        let mock = Arc::new(Mutex::new(MockApi::default()));
+        let api = serve(mock.clone());
+        let long_ago = Utc::now() - chrono::Duration::days(1);
+        mock.lock().unwrap().sheet_music = vec![
+            remote_item("s1", "Gymnopedie", "Satie", long_ago),
+            remote_item("s2", "Arabesque", "Debussy", long_ago),
+        ];
+        assert_eq!(sync_with(&api, &config).pulled, 2);
+
+        library::update(|library| {
+            let first = library.items.iter_mut().find(|item| item.id == "s1").unwrap();
+            first.title = "Gymnopédie No. 1".to_string();
+            first.is_favorite = true;
+            let second = library.items.iter_mut().find(|item| item.id == "s2").unwrap();
+            second.title = "Arabesque No. 1".to_string();
+            Ok(())
+        })
+        .unwrap();
+        // s1 was edited remotely before the local edit, s2 after it
+        let later = Utc::now() + chrono::Duration::hours(1);
+        mock.lock().unwrap().sheet_music = vec![
+            remote_item("s1", "Gymnopedie 1", "Erik Satie", long_ago + chrono::Duration::hours(1)),
+            remote_item("s2", "Deux arabesques", "Debussy", later),
+        ];
+
+        let report = sync_with(&api, &config);
+        assert_eq!(report.conflicts, 2);
+        let item = |id: &str| library::read(|library| library.item(id).cloned()).unwrap().unwrap();
+        let first = item("s1");
+        assert_eq!((first.title.as_str(), first.composer.as_str(), first.is_favorite), ("Gymnopédie No. 1", "Erik Satie", true));
+        assert_eq!(item("s2").title, "Deux arabesques");
+
+        let conflicts = list_sync_conflicts().unwrap();
+        let winner = |id: &str| conflicts.iter().find(|conflict| conflict.entity_id == id && conflict.field == "title").unwrap().winner.clone();
+        assert_eq!(winner("s1"), "local");
+        assert_eq!(winner("s2"), "remote");
+
+        // Only the fields local won go back; s2 lost its only pending change
+        let mock = mock.lock().unwrap();
+        assert_eq!(mock.sheet_music[0]["title"], "Gymnopédie No. 1");
+        assert_eq!(mock.sheet_music[0]["composer"], "Erik Satie");
+        assert_eq!(mock.sheet_music[0]["isFavorite"], true);
+        assert!(!mock.requests.iter().any(|request| request.ends_with("/s2") && request.starts_with("PUT")));
+        assert_eq!(report.pending_operations, 0);
+    }
+
+    #[test]
+    fn local_edits_since_the_last_sync_win_without_remote_timestamps() {
+        let _lock = store::lock_for_test();
+        let config = reset();
+        let mock = Arc::new(Mutex::new(MockApi::default()));
+        let api = serve(mock.clone());
+        mock.lock().unwrap().sheet_music = vec![api_item("s1", "Gymnopedie", "Satie")];
+        assert_eq!(sync_with(&api, &config).pulled, 1);
+
+        library::update(|library| {
+            library.items.iter_mut().find(|item| item.id == "s1").unwrap().title = "Gymnopédie No. 1".to_string();
+            Ok(())
+        })
+        .unwrap();
+        mock.lock().unwrap().sheet_music = vec![api_item("s1", "Gymnopedie 1", "Erik Satie")];
+
+        let report = sync_with(&api, &config);
+        assert_eq!(report.conflicts, 1);
+        let item = library::read(|library| library.item("s1").cloned()).unwrap().unwrap();
+        assert_eq!((item.title.as_str(), item.composer.as_str()), ("Gymnopédie No. 1", "Erik Satie"));
+        let conflicts = list_sync_conflicts().unwrap();
+        assert_eq!(conflicts.iter().find(|conflict| conflict.entity_id == "s1").unwrap().winner, "local");
+        assert_eq!(mock.lock().unwrap().sheet_music[0]["title"], "Gymnopédie No. 1");
+        assert_eq!(report.pending_operations, 0);
+    }
+
+    #[test]
+    fn new_pieces_go_through_the_upload_queue() {
+        let _lock = store::lock_for_test();
+        let config = reset();
+        let mock = Arc::new(Mutex::new(MockApi::default()));
+        let api = serve(mock.clone());
+        let pdf_path = store::sub_dir("pdfs").unwrap().join(format!("{}.pdf", library::new_id()));
+        fs::write(&pdf_path, b"%PDF-1.4\n%%EOF\n").unwrap();
+        let item = library::update(|library| {
+            let item = SheetMusicItem {
+                id: library::new_id(),
+                title: "Clair de lune".to_string(),
+                composer: "Debussy".to_string(),
+                pdf_path: pdf_path.to_string_lossy().to_string(),
+                is_favorite: false,
+                date_added: Utc::now(),
+            };
+            library.upsert_item(item.clone());
+            Ok(item)
+        })
+        .unwrap();
+        let jobs = || uploads::list_uploads().unwrap().into_iter().filter(|job| job.item.id == item.id).collect::<Vec<_>>();
+
+        // Queued once, however often sync runs before the upload is done, and nothing is sent directly
+        for _ in 0..2 {
+            let report = sync_with(&api, &config);
+            assert_eq!((report.pushed, report.pending_operations), (0, 1));
+        }
+        assert_eq!(jobs().len(), 1);
+        assert!(!mock.lock().unwrap().requests.iter().any(|request| request.starts_with("POST /api/sheet-music")));
+
+        // Once the queue has it on the server, later edits go out as updates
+        let remote_pdf = format!("https://files.example.com/{}.pdf", item.id);
+        upload_finished(USER_ID, &item, &remote_pdf);
+        let mut remote = to_record(&item);
+        remote["pdfPath"] = Value::String(remote_pdf);
+        mock.lock().unwrap().sheet_music.push(remote);
+        let report = sync_with(&api, &config);
+        assert_eq!((report.pushed, report.pending_operations), (1, 0));
+        assert!(mock.lock().unwrap().requests.contains(&format!("PUT /api/sheet-music/{}/{}", USER_ID, item.id)));
+
+        tauri::async_runtime::block_on(uploads::cancel_upload(jobs()[0].id.clone())).unwrap();
+    }
+}
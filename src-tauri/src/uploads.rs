@@ -0,0 +1,485 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::api_client::{self, ApiClient, ApiError};
+use crate::api_status::{self, ApiState};
+use crate::library::{self, SheetMusicItem};
+use crate::{session, store, sync};
+
+const UPLOAD_DOCUMENT: &str = "upload_queue";
+// Small enough to get through a slow connection well within the upload timeout
+const CHUNK_SIZE: u64 = 1024 * 1024;
+const MAX_CONCURRENT_UPLOADS: usize = 2;
+// Picks up uploads waiting for a retry or for the API to come back
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_secs(15);
+const RETRY_BASE_SECONDS: i64 = 10;
+const RETRY_MAX_SECONDS: i64 = 600;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum UploadStatus {
+    Queued,
+    Uploading,
+    Paused,
+    // The session couldn't be refreshed; picked up again once the frontend hands over a new one
+    NeedsSignIn,
+    Completed,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadJob {
+    pub id: String,
+    pub user_id: String,
+    pub item: SheetMusicItem,
+    pub file_path: String,
+    pub file_name: String,
+    pub total_bytes: u64,
+    pub sha256: String,
+    // Used to notice the file changing between queueing and a resumed upload
+    pub file_modified: Option<DateTime<Utc>>,
+    pub upload_id: Option<String>,
+    pub uploaded_bytes: u64,
+    pub status: UploadStatus,
+    pub attempts: u32,
+    pub retry_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+    pub pdf_path: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct UploadQueue {
+    jobs: Vec<UploadJob>,
+}
+
+static UPLOAD_QUEUE: Lazy<Mutex<Option<UploadQueue>>> = Lazy::new(|| Mutex::new(None));
+static CONTROL: Lazy<Mutex<Option<Sender<()>>>> = Lazy::new(|| Mutex::new(None));
+// Jobs that currently have a worker thread
+static ACTIVE: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+fn read_queue<R>(f: impl FnOnce(&UploadQueue) -> R) -> Result<R, String> {
+    let mut guard = UPLOAD_QUEUE.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(UPLOAD_DOCUMENT)?);
+    }
+    Ok(f(guard.as_ref().unwrap()))
+}
+
+fn with_queue<R>(f: impl FnOnce(&mut UploadQueue) -> Result<R, String>) -> Result<R, String> {
+    let mut guard = UPLOAD_QUEUE.lock().unwrap();
+    if guard.is_none() {
+        *guard = Some(store::load(UPLOAD_DOCUMENT)?);
+    }
+    let queue = guard.as_mut().unwrap();
+    let result = f(queue)?;
+    store::save(UPLOAD_DOCUMENT, queue)?;
+    Ok(result)
+}
+
+fn emit_progress(app: &AppHandle, job: &UploadJob) {
+    if let Err(e) = app.emit("upload-progress", job) {
+        println!("Failed to emit upload progress: {}", e);
+    }
+}
+
+// Applies a change to one job and reports it; None means the job was cancelled in the meantime
+fn update_job(app: &AppHandle, id: &str, f: impl FnOnce(&mut UploadJob)) -> Result<Option<UploadJob>, String> {
+    let job = with_queue(|queue| {
+        Ok(queue.jobs.iter_mut().find(|job| job.id == id).map(|job| {
+            f(job);
+            job.updated_at = Utc::now();
+            job.clone()
+        }))
+    })?;
+    if let Some(job) = &job {
+        emit_progress(app, job);
+    }
+    Ok(job)
+}
+
+fn file_modified(path: &str) -> Option<DateTime<Utc>> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok().map(DateTime::<Utc>::from)
+}
+
+fn hash_file(path: &str) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn retry_delay(attempts: u32) -> ChronoDuration {
+    let seconds = RETRY_BASE_SECONDS.saturating_mul(1 << attempts.min(10)).min(RETRY_MAX_SECONDS);
+    ChronoDuration::seconds(seconds)
+}
+
+// Offline or slow failures are retried later; anything the server refuses is final
+fn is_retryable(error: &ApiError) -> bool {
+    matches!(error, ApiError::Offline { .. } | ApiError::Timeout | ApiError::Server { .. })
+}
+
+fn start_session(app: &AppHandle, api: &ApiClient, job: &UploadJob) -> Result<(String, u64), ApiError> {
+    let existing = match &job.upload_id {
+        Some(upload_id) => match api.get_upload(&job.user_id, upload_id) {
+            Ok(session) => Some(session),
+            // The server forgets abandoned uploads after a while, so start over
+            Err(ApiError::NotFound) => None,
+            Err(e) => return Err(e),
+        },
+        None => None,
+    };
+    let session = match existing {
+        Some(session) => session,
+        None => api.create_upload(&job.user_id, &job.item, &job.file_name, job.total_bytes, &job.sha256)?,
+    };
+    update_job(app, &job.id, |job| {
+        job.upload_id = Some(session.upload_id.clone());
+        job.uploaded_bytes = session.received_bytes;
+    })
+    .map_err(|message| ApiError::Local { message })?;
+    Ok((session.upload_id, session.received_bytes))
+}
+
+// Sends the remaining chunks; returns None when the job was paused or cancelled along the way
+fn upload(app: &AppHandle, api: &ApiClient, job: &UploadJob) -> Result<Option<String>, ApiError> {
+    let local_error = |message: String| ApiError::Local { message };
+    let size = fs::metadata(&job.file_path)
+        .map(|meta| meta.len())
+        .map_err(|e| local_error(format!("Failed to read {}: {}", job.file_path, e)))?;
+    if size != job.total_bytes || file_modified(&job.file_path) != job.file_modified {
+        return Err(local_error(format!("{} changed after it was queued", job.file_name)));
+    }
+
+    let (upload_id, mut offset) = match start_session(app, api, job) {
+        // Servers without the chunked upload routes still take the whole file the way saveSheetMusic sends it
+        Err(ApiError::NotFound) => return upload_whole(api, job).map(Some),
+        result => result?,
+    };
+    let mut file = File::open(&job.file_path).map_err(|e| local_error(format!("Failed to open {}: {}", job.file_path, e)))?;
+    while offset < job.total_bytes {
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE as usize);
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| (&mut file).take(CHUNK_SIZE).read_to_end(&mut chunk))
+            .map_err(|e| local_error(format!("Failed to read {}: {}", job.file_path, e)))?;
+
+        let session = api.upload_chunk(&job.user_id, &upload_id, offset, &chunk)?;
+        if session.received_bytes <= offset {
+            return Err(ApiError::InvalidResponse {
+                message: format!("Upload did not advance past byte {}", offset),
+            });
+        }
+        offset = session.received_bytes.min(job.total_bytes);
+
+        let updated = update_job(app, &job.id, |job| job.uploaded_bytes = offset).map_err(local_error)?;
+        if updated.map(|job| job.status != UploadStatus::Uploading).unwrap_or(true) {
+            return Ok(None);
+        }
+    }
+
+    let result = api.complete_upload(&job.user_id, &upload_id)?;
+    Ok(Some(result.pdf_path))
+}
+
+fn upload_whole(api: &ApiClient, job: &UploadJob) -> Result<String, ApiError> {
+    println!("Chunked uploads aren't available, sending {} in one request", job.file_name);
+    let pdf = fs::read(&job.file_path).map_err(|e| ApiError::Local {
+        message: format!("Failed to read {}: {}", job.file_path, e),
+    })?;
+    let result = api.upload_sheet_music(&job.user_id, &job.item, &job.file_name, &pdf)?;
+    Ok(result.pdf_path)
+}
+
+fn run_job(app: &AppHandle, id: &str) {
+    let job = match read_queue(|queue| queue.jobs.iter().find(|job| job.id == id).cloned()) {
+        Ok(Some(job)) if job.status == UploadStatus::Uploading => job,
+        _ => return,
+    };
+    let api = api_client::client();
+    let result = upload(app, &api, &job);
+
+    let updated = update_job(app, id, |job| {
+        // A pause that arrived during the last request wins over anything but a finished upload
+        if job.status != UploadStatus::Uploading && !matches!(result, Ok(Some(_))) {
+            return;
+        }
+        match &result {
+            Ok(Some(pdf_path)) => {
+                job.status = UploadStatus::Completed;
+                job.uploaded_bytes = job.total_bytes;
+                job.pdf_path = Some(pdf_path.clone());
+                job.error = None;
+                job.retry_at = None;
+            }
+            Ok(None) => {}
+            Err(ApiError::Unauthorized) => {
+                job.status = UploadStatus::NeedsSignIn;
+                job.retry_at = None;
+                job.error = Some(ApiError::Unauthorized.to_string());
+            }
+            Err(e) if is_retryable(e) => {
+                job.status = UploadStatus::Queued;
+                job.retry_at = Some(Utc::now() + retry_delay(job.attempts));
+                job.attempts += 1;
+                job.error = Some(e.to_string());
+            }
+            Err(e) => {
+                job.status = UploadStatus::Failed;
+                job.error = Some(e.to_string());
+            }
+        }
+    });
+    if let Ok(Some(pdf_path)) = &result {
+        sync::upload_finished(&job.user_id, &job.item, pdf_path);
+    }
+    match (&result, updated) {
+        (Ok(Some(_)), _) => println!("Uploaded {}", job.file_name),
+        (Err(e), _) => println!("Upload of {} failed: {}", job.file_name, e),
+        (_, Err(e)) => println!("Failed to update upload {}: {}", job.file_name, e),
+        _ => {}
+    }
+}
+
+// Starts workers for waiting jobs as long as there are free slots
+fn dispatch(app: &AppHandle) {
+    if session::access_token().is_none() || api_status::get_api_status().status == ApiState::Offline {
+        return;
+    }
+
+    let mut active = ACTIVE.lock().unwrap();
+    let free = MAX_CONCURRENT_UPLOADS.saturating_sub(active.len());
+    if free == 0 {
+        return;
+    }
+    let now = Utc::now();
+    let started = with_queue(|queue| {
+        let mut started = Vec::new();
+        for job in queue.jobs.iter_mut() {
+            if started.len() == free {
+                break;
+            }
+            let due = job.retry_at.map(|retry_at| retry_at <= now).unwrap_or(true);
+            if job.status == UploadStatus::Queued && due && !active.contains(&job.id) {
+                job.status = UploadStatus::Uploading;
+                job.updated_at = now;
+                started.push(job.clone());
+            }
+        }
+        Ok(started)
+    });
+    let started = match started {
+        Ok(started) => started,
+        Err(e) => {
+            println!("Failed to read upload queue: {}", e);
+            return;
+        }
+    };
+
+    for job in started {
+        active.insert(job.id.clone());
+        emit_progress(app, &job);
+        let app = app.clone();
+        thread::spawn(move || {
+            run_job(&app, &job.id);
+            ACTIVE.lock().unwrap().remove(&job.id);
+            wake();
+        });
+    }
+}
+
+// Called by the session store whenever a new access token arrives
+pub fn session_renewed() {
+    let waiting = read_queue(|queue| queue.jobs.iter().any(|job| job.status == UploadStatus::NeedsSignIn)).unwrap_or(false);
+    if !waiting {
+        return;
+    }
+    let resumed = with_queue(|queue| {
+        for job in queue.jobs.iter_mut().filter(|job| job.status == UploadStatus::NeedsSignIn) {
+            job.status = UploadStatus::Queued;
+            job.error = None;
+            job.updated_at = Utc::now();
+        }
+        Ok(())
+    });
+    match resumed {
+        Ok(()) => wake(),
+        Err(e) => println!("Failed to resume uploads after sign-in: {}", e),
+    }
+}
+
+fn wake() {
+    if let Some(control) = CONTROL.lock().unwrap().as_ref() {
+        let _ = control.send(());
+    }
+}
+
+pub fn start(app: AppHandle) {
+    // Uploads cut off by quitting resume from whatever the server has received
+    let restored = with_queue(|queue| {
+        for job in queue.jobs.iter_mut().filter(|job| job.status == UploadStatus::Uploading) {
+            job.status = UploadStatus::Queued;
+        }
+        Ok(())
+    });
+    if let Err(e) = restored {
+        println!("Failed to restore upload queue: {}", e);
+    }
+
+    let (tx, rx) = mpsc::channel();
+    *CONTROL.lock().unwrap() = Some(tx);
+    thread::spawn(move || loop {
+        dispatch(&app);
+        match rx.recv_timeout(QUEUE_POLL_INTERVAL) {
+            Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    });
+}
+
+// Queues a piece's PDF, or hands back the upload already waiting for that piece; the flag says whether it's new
+pub fn enqueue(user_id: &str, item: &SheetMusicItem, pdf_path: &str) -> Result<(UploadJob, bool), String> {
+    let waiting = |queue: &UploadQueue| {
+        queue
+            .jobs
+            .iter()
+            .find(|other| other.item.id == item.id && other.status != UploadStatus::Completed)
+            .cloned()
+    };
+    if let Some(job) = read_queue(waiting)? {
+        return Ok((job, false));
+    }
+
+    let total_bytes = fs::metadata(pdf_path)
+        .map(|meta| meta.len())
+        .map_err(|e| format!("Failed to read {}: {}", pdf_path, e))?;
+    let file_name = Path::new(pdf_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| format!("{}.pdf", item.id));
+    let file_modified = file_modified(pdf_path);
+    let sha256 = hash_file(pdf_path)?;
+    let now = Utc::now();
+    let job = UploadJob {
+        id: library::new_id(),
+        user_id: user_id.to_string(),
+        item: item.clone(),
+        file_path: pdf_path.to_string(),
+        file_name,
+        total_bytes,
+        sha256,
+        file_modified,
+        upload_id: None,
+        uploaded_bytes: 0,
+        status: UploadStatus::Queued,
+        attempts: 0,
+        retry_at: None,
+        error: None,
+        pdf_path: None,
+        created_at: now,
+        updated_at: now,
+    };
+    let queued = with_queue(|queue| {
+        if let Some(existing) = waiting(queue) {
+            return Ok((existing, false));
+        }
+        queue.jobs.push(job.clone());
+        Ok((job, true))
+    })?;
+    if queued.1 {
+        println!("Queued upload of {} ({} bytes)", queued.0.file_name, queued.0.total_bytes);
+        wake();
+    }
+    Ok(queued)
+}
+
+#[tauri::command]
+pub async fn enqueue_upload(app: AppHandle, user_id: String, item: SheetMusicItem, pdf_path: String) -> Result<UploadJob, String> {
+    let (job, queued) = tauri::async_runtime::spawn_blocking(move || enqueue(&user_id, &item, &pdf_path))
+        .await
+        .map_err(|e| e.to_string())??;
+    if !queued {
+        return Err(format!("{} is already queued for upload", job.item.title));
+    }
+    emit_progress(&app, &job);
+    Ok(job)
+}
+
+#[tauri::command]
+pub fn list_uploads() -> Result<Vec<UploadJob>, String> {
+    read_queue(|queue| queue.jobs.clone())
+}
+
+// A running upload stops after its current chunk
+#[tauri::command]
+pub fn pause_upload(app: AppHandle, id: String) -> Result<UploadJob, String> {
+    update_job(&app, &id, |job| {
+        if matches!(job.status, UploadStatus::Queued | UploadStatus::Uploading | UploadStatus::NeedsSignIn) {
+            job.status = UploadStatus::Paused;
+        }
+    })?
+    .ok_or_else(|| format!("Upload {} not found", id))
+}
+
+// Also retries failed uploads, starting over with a fresh attempt count
+#[tauri::command]
+pub fn resume_upload(app: AppHandle, id: String) -> Result<UploadJob, String> {
+    let job = update_job(&app, &id, |job| {
+        if matches!(job.status, UploadStatus::Paused | UploadStatus::Failed) {
+            job.status = UploadStatus::Queued;
+            job.attempts = 0;
+            job.retry_at = None;
+            job.error = None;
+        }
+    })?
+    .ok_or_else(|| format!("Upload {} not found", id))?;
+    wake();
+    Ok(job)
+}
+
+#[tauri::command]
+pub async fn cancel_upload(id: String) -> Result<(), String> {
+    let job = with_queue(|queue| {
+        let index = queue
+            .jobs
+            .iter()
+            .position(|job| job.id == id)
+            .ok_or_else(|| format!("Upload {} not found", id))?;
+        Ok(queue.jobs.remove(index))
+    })?;
+
+    // Lets the server drop the chunks it already has; it cleans up abandoned uploads anyway
+    if job.status == UploadStatus::Completed {
+        return Ok(());
+    }
+    if let Some(upload_id) = job.upload_id.clone() {
+        tauri::async_runtime::spawn_blocking(move || {
+            if let Err(e) = api_client::client().abort_upload(&job.user_id, &upload_id) {
+                println!("Failed to abort upload {}: {}", upload_id, e);
+            }
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn clear_finished_uploads() -> Result<Vec<UploadJob>, String> {
+    with_queue(|queue| {
+        queue.jobs.retain(|job| job.status != UploadStatus::Completed);
+        Ok(queue.jobs.clone())
+    })
+}
@@ -0,0 +1,556 @@
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use crate::{duplicates, library, metadata, store};
+
+const WATCH_DOCUMENT: &str = "watch_folders";
+
+// A file has to keep the same size for this long before we try to import it
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+// Give up on files that never become a complete PDF (e.g. an aborted scan)
+const MAX_PENDING_TIME: Duration = Duration::from_secs(300);
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchConfig {
+    pub folders: Vec<String>,
+    // Files inside watched folders that should never be imported again, e.g. merged duplicates
+    #[serde(default)]
+    pub ignored: Vec<String>,
+}
+
+// Payload of the "import-progress" event
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportProgress {
+    pub path: String,
+    pub stage: &'static str,
+    pub sheet_music_id: Option<String>,
+    pub error: Option<String>,
+    pub pending: usize,
+}
+
+enum WatchMessage {
+    Fs(Event),
+    Scan(PathBuf),
+}
+
+struct PendingFile {
+    size: u64,
+    last_change: Instant,
+    first_seen: Instant,
+    // The file disappeared in a rename whose other side hasn't shown up yet
+    vanished: bool,
+}
+
+static WATCHER: Lazy<Mutex<Option<RecommendedWatcher>>> = Lazy::new(|| Mutex::new(None));
+static CONTROL: Lazy<Mutex<Option<Sender<WatchMessage>>>> = Lazy::new(|| Mutex::new(None));
+// Every event checks the ignore list, so it is read from disk only once
+static IGNORED: Lazy<Mutex<Option<HashSet<String>>>> = Lazy::new(|| Mutex::new(None));
+
+// Starts watching the configured folders and the worker thread that imports from them
+pub fn start(app: AppHandle) -> Result<(), String> {
+    let (tx, rx) = mpsc::channel();
+    let fs_tx = tx.clone();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| match result {
+        Ok(event) => {
+            let _ = fs_tx.send(WatchMessage::Fs(event));
+        }
+        Err(e) => println!("Watch error: {}", e),
+    })
+    .map_err(|e| format!("Failed to create folder watcher: {}", e))?;
+
+    let config: WatchConfig = store::load(WATCH_DOCUMENT)?;
+    for folder in &config.folders {
+        match watcher.watch(Path::new(folder), RecursiveMode::Recursive) {
+            Ok(()) => {
+                println!("Watching import folder: {}", folder);
+                let _ = tx.send(WatchMessage::Scan(PathBuf::from(folder)));
+            }
+            Err(e) => println!("Failed to watch {}: {}", folder, e),
+        }
+    }
+
+    *WATCHER.lock().unwrap() = Some(watcher);
+    *CONTROL.lock().unwrap() = Some(tx);
+
+    thread::spawn(move || {
+        let mut pending: HashMap<PathBuf, PendingFile> = HashMap::new();
+        loop {
+            match rx.recv_timeout(Duration::from_millis(500)) {
+                Ok(WatchMessage::Fs(event)) => handle_event(&app, event, &mut pending),
+                Ok(WatchMessage::Scan(folder)) => scan_folder(&app, &folder, &mut pending),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            import_settled(&app, &mut pending);
+        }
+        println!("Import folder watcher stopped");
+    });
+
+    Ok(())
+}
+
+fn is_watched_pdf(path: &Path) -> bool {
+    let hidden = path
+        .file_name()
+        .map(|name| {
+            let name = name.to_string_lossy();
+            name.starts_with('.') || name.starts_with('~')
+        })
+        .unwrap_or(true);
+    let is_pdf = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("pdf"))
+        .unwrap_or(false);
+    is_pdf && !hidden
+}
+
+// Where the worker reports what it did; the app forwards it to the UI as "import-progress"
+trait ProgressSink {
+    fn report(&self, progress: ImportProgress);
+}
+
+impl ProgressSink for AppHandle {
+    fn report(&self, progress: ImportProgress) {
+        if let Err(e) = self.emit("import-progress", progress) {
+            println!("Failed to emit import progress: {}", e);
+        }
+    }
+}
+
+fn emit_progress(app: &dyn ProgressSink, path: &Path, stage: &'static str, sheet_music_id: Option<String>, error: Option<String>, pending: usize) {
+    app.report(ImportProgress {
+        path: path.to_string_lossy().to_string(),
+        stage,
+        sheet_music_id,
+        error,
+        pending,
+    });
+}
+
+fn is_ignored(path: &Path) -> bool {
+    let mut ignored = IGNORED.lock().unwrap();
+    if ignored.is_none() {
+        match store::load::<WatchConfig>(WATCH_DOCUMENT) {
+            Ok(config) => *ignored = Some(config.ignored.into_iter().collect()),
+            Err(_) => return false,
+        }
+    }
+    ignored.as_ref().unwrap().contains(path.to_string_lossy().as_ref())
+}
+
+pub fn ignore_file(path: &str) -> Result<(), String> {
+    let mut config: WatchConfig = store::load(WATCH_DOCUMENT)?;
+    if !config.ignored.iter().any(|ignored| ignored == path) {
+        config.ignored.push(path.to_string());
+        store::save(WATCH_DOCUMENT, &config)?;
+    }
+    if let Some(ignored) = IGNORED.lock().unwrap().as_mut() {
+        ignored.insert(path.to_string());
+    }
+    Ok(())
+}
+
+fn mark_pending(app: &dyn ProgressSink, path: &Path, pending: &mut HashMap<PathBuf, PendingFile>) {
+    if is_ignored(path) {
+        return;
+    }
+    let size = fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+    let now = Instant::now();
+    let is_new = !pending.contains_key(path);
+    let entry = pending.entry(path.to_path_buf()).or_insert(PendingFile {
+        size,
+        last_change: now,
+        first_seen: now,
+        vanished: false,
+    });
+    entry.size = size;
+    entry.last_change = now;
+    entry.vanished = false;
+    if is_new {
+        emit_progress(app, path, "detected", None, None, pending.len());
+    }
+}
+
+fn handle_event(app: &dyn ProgressSink, event: Event, pending: &mut HashMap<PathBuf, PendingFile>) {
+    match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+            handle_rename(app, &event.paths[0], &event.paths[1], pending);
+        }
+        // FSEvents reports both sides of a rename as Any and some backends send To on its own,
+        // so whether a path is the old or the new name has to come from the disk
+        EventKind::Modify(ModifyKind::Name(RenameMode::Any | RenameMode::To)) => {
+            for path in event.paths.iter().filter(|path| is_watched_pdf(path)) {
+                if path.exists() {
+                    reconcile_moved(app, path, pending);
+                } else {
+                    mark_vanished(path, pending);
+                }
+            }
+        }
+        // inotify and Windows send From ahead of To and Both, so the old name only vanishes for now
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            for path in event.paths.iter().filter(|path| is_watched_pdf(path)) {
+                mark_vanished(path, pending);
+            }
+        }
+        EventKind::Remove(_) => {
+            for path in event.paths.iter().filter(|path| is_watched_pdf(path)) {
+                handle_removal(app, path, pending);
+            }
+        }
+        EventKind::Create(_) | EventKind::Modify(_) => {
+            for path in event.paths.iter().filter(|path| is_watched_pdf(path)) {
+                if path.is_file() {
+                    mark_pending(app, path, pending);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn handle_removal(app: &dyn ProgressSink, path: &Path, pending: &mut HashMap<PathBuf, PendingFile>) {
+    pending.remove(path);
+    let pdf_path = path.to_string_lossy().to_string();
+    let removed = library::update(|library| {
+        let id = library.items.iter().find(|item| item.pdf_path == pdf_path).map(|item| item.id.clone());
+        Ok(id.and_then(|id| library.remove_item(&id)))
+    });
+    match removed {
+        Ok(Some(item)) => {
+            println!("Removed {} from the catalog after deletion", pdf_path);
+            emit_progress(app, path, "removed", Some(item.id), None, pending.len());
+        }
+        Ok(None) => {}
+        Err(e) => emit_progress(app, path, "failed", None, Some(e), pending.len()),
+    }
+}
+
+fn handle_rename(app: &dyn ProgressSink, from: &Path, to: &Path, pending: &mut HashMap<PathBuf, PendingFile>) {
+    if !is_watched_pdf(to) {
+        if is_watched_pdf(from) {
+            handle_removal(app, from, pending);
+        }
+        return;
+    }
+
+    // A file that is still being written keeps its pending state under the new name
+    if let Some(entry) = pending.remove(from) {
+        pending.insert(to.to_path_buf(), entry);
+        return;
+    }
+
+    let from_path = from.to_string_lossy().to_string();
+    let to_path = to.to_string_lossy().to_string();
+    let renamed = library::update(|library| {
+        let item = library.items.iter_mut().find(|item| item.pdf_path == from_path);
+        Ok(item.map(|item| {
+            item.pdf_path = to_path.clone();
+            item.id.clone()
+        }))
+    });
+    match renamed {
+        Ok(Some(id)) => {
+            println!("Catalog entry {} moved to {}", id, to_path);
+            emit_progress(app, to, "renamed", Some(id), None, pending.len());
+        }
+        // Already moved when the To half of this rename came in
+        Ok(None) if library::read(|library| library.items.iter().any(|item| item.pdf_path == to_path)).unwrap_or(false) => {}
+        Ok(None) => mark_pending(app, to, pending),
+        Err(e) => emit_progress(app, to, "failed", None, Some(e), pending.len()),
+    }
+}
+
+// The catalog entry stays until SETTLE_TIME passes, giving the new name a chance to claim it
+fn mark_vanished(path: &Path, pending: &mut HashMap<PathBuf, PendingFile>) {
+    let now = Instant::now();
+    pending.insert(
+        path.to_path_buf(),
+        PendingFile {
+            size: 0,
+            last_change: now,
+            first_seen: now,
+            vanished: true,
+        },
+    );
+}
+
+// Matches a file that appeared under a new name against catalog entries whose file is gone
+fn reconcile_moved(app: &dyn ProgressSink, path: &Path, pending: &mut HashMap<PathBuf, PendingFile>) {
+    let pdf_path = path.to_string_lossy().to_string();
+    if library::read(|library| library.items.iter().any(|item| item.pdf_path == pdf_path)).unwrap_or(false) {
+        return;
+    }
+    let moved_from = duplicates::content_hash(&pdf_path).and_then(|hash| {
+        library::read(|library| {
+            library
+                .items
+                .iter()
+                .filter(|item| !Path::new(&item.pdf_path).exists())
+                .find(|item| duplicates::cached_hash(&item.pdf_path).as_deref() == Some(hash.as_str()))
+                .map(|item| PathBuf::from(&item.pdf_path))
+        })
+        .ok()
+        .flatten()
+    });
+    match moved_from {
+        Some(from) => {
+            pending.remove(&from);
+            handle_rename(app, &from, path, pending);
+        }
+        None => mark_pending(app, path, pending),
+    }
+}
+
+// Imports files whose size stopped changing, leaving partial writes in the queue
+fn import_settled(app: &dyn ProgressSink, pending: &mut HashMap<PathBuf, PendingFile>) {
+    let ready: Vec<PathBuf> = pending
+        .iter()
+        .filter(|(_, file)| file.last_change.elapsed() >= SETTLE_TIME)
+        .map(|(path, _)| path.clone())
+        .collect();
+
+    for path in ready {
+        let size = match fs::metadata(&path) {
+            Ok(meta) => meta.len(),
+            Err(_) => {
+                // Nothing claimed the old name, so it was a move out of the watched folders
+                if pending.remove(&path).is_some_and(|file| file.vanished) {
+                    handle_removal(app, &path, pending);
+                }
+                continue;
+            }
+        };
+
+        let entry = pending.get_mut(&path).unwrap();
+        if entry.vanished {
+            // Renamed back before anything else happened
+            pending.remove(&path);
+            continue;
+        }
+        if size != entry.size {
+            entry.size = size;
+            entry.last_change = Instant::now();
+            continue;
+        }
+
+        let complete = fs::read(&path).map(|data| metadata::is_complete_pdf(&data)).unwrap_or(false);
+        if !complete {
+            if entry.first_seen.elapsed() >= MAX_PENDING_TIME {
+                pending.remove(&path);
+                let error = "File never became a complete PDF".to_string();
+                emit_progress(app, &path, "failed", None, Some(error), pending.len());
+            } else {
+                entry.last_change = Instant::now();
+            }
+            continue;
+        }
+
+        pending.remove(&path);
+        emit_progress(app, &path, "importing", None, None, pending.len());
+        match library::import_pdf(&path) {
+            Ok(item) => {
+                println!("Imported {} as {}", path.display(), item.id);
+                duplicates::content_hash(&item.pdf_path);
+                emit_progress(app, &path, "imported", Some(item.id), None, pending.len());
+            }
+            Err(e) => {
+                println!("Failed to import {}: {}", path.display(), e);
+                emit_progress(app, &path, "failed", None, Some(e), pending.len());
+            }
+        }
+    }
+}
+
+fn collect_pdfs(dir: &Path, found: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            println!("Failed to read {}: {}", dir.display(), e);
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_pdfs(&path, found);
+        } else if is_watched_pdf(&path) {
+            found.push(path);
+        }
+    }
+}
+
+// Catches up with changes made while the app wasn't running
+fn scan_folder(app: &dyn ProgressSink, folder: &Path, pending: &mut HashMap<PathBuf, PendingFile>) {
+    let mut found = Vec::new();
+    collect_pdfs(folder, &mut found);
+
+    let known = library::read(|library| {
+        library
+            .items
+            .iter()
+            .filter(|item| Path::new(&item.pdf_path).starts_with(folder))
+            .map(|item| item.pdf_path.clone())
+            .collect::<Vec<String>>()
+    })
+    .unwrap_or_default();
+
+    for path in &found {
+        if !known.contains(&path.to_string_lossy().to_string()) {
+            mark_pending(app, path, pending);
+        }
+    }
+    for missing in known.iter().map(PathBuf::from).filter(|path| !path.exists()) {
+        handle_removal(app, &missing, pending);
+    }
+    // Hashes of the files still here, so a later rename can be recognised once the old name is gone
+    for path in known.iter().filter(|path| Path::new(path).exists()) {
+        duplicates::content_hash(path);
+    }
+}
+
+#[tauri::command]
+pub fn list_watch_folders() -> Result<Vec<String>, String> {
+    let config: WatchConfig = store::load(WATCH_DOCUMENT)?;
+    Ok(config.folders)
+}
+
+#[tauri::command]
+pub fn add_watch_folder(path: String) -> Result<Vec<String>, String> {
+    let folder = fs::canonicalize(&path).map_err(|e| format!("Invalid folder {}: {}", path, e))?;
+    if !folder.is_dir() {
+        return Err(format!("{} is not a folder", path));
+    }
+    let folder_string = folder.to_string_lossy().to_string();
+
+    let mut config: WatchConfig = store::load(WATCH_DOCUMENT)?;
+    if config.folders.contains(&folder_string) {
+        return Ok(config.folders);
+    }
+
+    let mut watcher = WATCHER.lock().unwrap();
+    let watcher = watcher.as_mut().ok_or("Folder watcher is not running")?;
+    watcher
+        .watch(&folder, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch {}: {}", folder_string, e))?;
+
+    config.folders.push(folder_string.clone());
+    store::save(WATCH_DOCUMENT, &config)?;
+    println!("Watching import folder: {}", folder_string);
+
+    if let Some(control) = CONTROL.lock().unwrap().as_ref() {
+        let _ = control.send(WatchMessage::Scan(folder));
+    }
+    Ok(config.folders)
+}
+
+// Stops watching a folder; pieces already imported from it stay in the catalog
+#[tauri::command]
+pub fn remove_watch_folder(path: String) -> Result<Vec<String>, String> {
+    let mut config: WatchConfig = store::load(WATCH_DOCUMENT)?;
+    if !config.folders.contains(&path) {
+        return Err(format!("{} is not a watched folder", path));
+    }
+
+    if let Some(watcher) = WATCHER.lock().unwrap().as_mut() {
+        if let Err(e) = watcher.unwatch(Path::new(&path)) {
+            println!("Failed to unwatch {}: {}", path, e);
+        }
+    }
+
+    config.folders.retain(|folder| *folder != path);
+    store::save(WATCH_DOCUMENT, &config)?;
+    Ok(config.folders)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recordings;
+    use base64::Engine;
+    use notify::event::RemoveKind;
+    use std::cell::RefCell;
+    use std::io::Cursor;
+
+    #[derive(Default)]
+    struct Reported(RefCell<Vec<ImportProgress>>);
+
+    impl ProgressSink for Reported {
+        fn report(&self, progress: ImportProgress) {
+            self.0.borrow_mut().push(progress);
+        }
+    }
+
+    fn rename(mode: RenameMode, paths: &[&Path]) -> Event {
+        paths
+            .iter()
+            .fold(Event::new(EventKind::Modify(ModifyKind::Name(mode))), |event, path| event.add_path(path.to_path_buf()))
+    }
+
+    fn wav() -> String {
+        let mut data = Cursor::new(Vec::new());
+        let spec = hound::WavSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+        let mut writer = hound::WavWriter::new(&mut data, spec).unwrap();
+        for sample in 0..8000 {
+            writer.write_sample((sample % 100) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        base64::engine::general_purpose::STANDARD.encode(data.into_inner())
+    }
+
+    #[test]
+    fn a_rename_keeps_the_catalog_entry_and_everything_linked_to_it() {
+        let _lock = store::lock_for_test();
+        let folder = store::sub_dir("watched").unwrap().join(library::new_id());
+        fs::create_dir_all(&folder).unwrap();
+        let from = folder.join("Nocturne.pdf");
+        let to = folder.join("Chopin - Nocturne.pdf");
+        fs::write(&from, format!("%PDF-1.4\n% {}\n%%EOF\n", folder.display())).unwrap();
+
+        let item = library::import_pdf(&from).unwrap();
+        let tag = library::create_tag(library::new_id(), None).unwrap();
+        library::tag_sheet_music(item.id.clone(), tag.id.clone()).unwrap();
+        let recording = tauri::async_runtime::block_on(recordings::save_recording(wav(), item.id.clone(), None, None, None)).unwrap();
+        duplicates::content_hash(&from.to_string_lossy());
+
+        fs::rename(&from, &to).unwrap();
+        let reported = Reported::default();
+        let mut pending = HashMap::new();
+        handle_event(&reported, rename(RenameMode::From, &[&from]), &mut pending);
+        handle_event(&reported, rename(RenameMode::To, &[&to]), &mut pending);
+        handle_event(&reported, rename(RenameMode::Both, &[&from, &to]), &mut pending);
+
+        let moved = library::read(|library| library.item(&item.id).cloned()).unwrap().expect("the piece is still in the catalog");
+        assert_eq!(moved.pdf_path, to.to_string_lossy());
+        assert_eq!(library::get_sheet_music_tags(item.id.clone()).unwrap(), vec![tag]);
+        let kept: Vec<String> = recordings::list_recordings(Some(item.id.clone()), None)
+            .unwrap()
+            .into_iter()
+            .map(|file| file.recording.id)
+            .collect();
+        assert_eq!(kept, vec![recording.recording.id]);
+        // Nothing is left waiting to be removed or imported once the settle time passes
+        assert!(pending.is_empty());
+        let stages: Vec<&str> = reported.0.borrow().iter().map(|progress| progress.stage).collect();
+        assert_eq!(stages, vec!["renamed"]);
+
+        // An actual deletion still removes the piece
+        fs::remove_file(&to).unwrap();
+        handle_event(&reported, Event::new(EventKind::Remove(RemoveKind::File)).add_path(to.clone()), &mut pending);
+        assert!(library::read(|library| library.item(&item.id).is_none()).unwrap());
+        assert!(recordings::list_recordings(Some(item.id), None).unwrap().is_empty());
+    }
+}